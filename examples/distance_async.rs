@@ -0,0 +1,25 @@
+use hc_sr04::{HcSr04Async, Result, Unit};
+use std::time::Duration;
+use tokio::time;
+
+async fn run() -> Result<()> {
+    // TRIGGER on GPIO Pin 24 & ECHO on GPIO Pin 23.
+    let ultrasonic = HcSr04Async::new(24, 23, None)?;
+
+    let mut interval = time::interval(Duration::from_secs(1));
+    loop {
+        interval.tick().await;
+
+        match ultrasonic.measure_distance(Unit::Meters).await? {
+            Some(dist) => println!("Distance: {:.2}m", dist),
+            None => println!("Object out of range"),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(err) = run().await {
+        eprintln!("error: {err}");
+    }
+}
@@ -0,0 +1,23 @@
+use async_std::task;
+use hc_sr04::{async_std::HcSr04Async, Result, Unit};
+use std::time::Duration;
+
+async fn run() -> Result<()> {
+    // TRIGGER on GPIO Pin 24 & ECHO on GPIO Pin 23.
+    let ultrasonic = HcSr04Async::new(24, 23, None)?;
+
+    loop {
+        match ultrasonic.measure_distance(Unit::Meters).await? {
+            Some(dist) => println!("Distance: {:.2}m", dist),
+            None => println!("Object out of range"),
+        }
+
+        task::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+fn main() {
+    if let Err(err) = task::block_on(run()) {
+        eprintln!("error: {err}");
+    }
+}
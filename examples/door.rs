@@ -4,36 +4,38 @@
 // Configure THRESHOLD_DIST constant so that opening the door corresponds to creating an
 // obstacle for the ultrasonic sensor palced at a distance lower than the THRESHOLD_DIST.
 
-use hc_sr04::{HcSr04, Result, Unit};
-use std::{thread, time::Duration};
+use hc_sr04::{
+    door::{DoorEvent, DoorSensor},
+    Distance, Float, HcSr04, Result, Unit,
+};
+use std::time::Duration;
 
 // Threshold distance expressed in meters.
-const THRESHOLD_DIST: f32 = 1.2;
+const THRESHOLD_DIST: Float = 1.2;
+// Hysteresis band expressed in meters.
+const HYSTERESIS: Float = 0.1;
 
 fn run() -> Result<()> {
     // TRIGGER on GPIO Pin 24 & ECHO on GPIO Pin 23.
-    let mut ultrasonic = HcSr04::new(24, 23, None)?;
+    let ultrasonic = HcSr04::new(24, 23, None)?;
 
-    let below_threshold = |ultrasonic: &mut HcSr04| -> Result<bool> {
-        Ok(ultrasonic
-            .measure_distance(Unit::Meters)?
-            .unwrap_or(f32::MAX)
-            < THRESHOLD_DIST)
-    };
+    // If measured distance is lower than THRESHOLD_DIST, door is open.
+    let mut door = DoorSensor::new(
+        ultrasonic,
+        Distance::new(THRESHOLD_DIST, Unit::Meters),
+        Distance::new(HYSTERESIS, Unit::Meters),
+    )
+    .debounce(2)
+    .interval(Duration::from_millis(500));
 
-    let mut closed = true;
-    loop {
-        // If measured distance is lower than THRESHOLD_DIST, door is open.
-        if below_threshold(&mut ultrasonic)? == closed {
-            closed = !closed;
-            match closed {
-                true => println!("Door closed!"),
-                false => println!("Door opened!"),
-            }
+    for event in door.events() {
+        match event? {
+            DoorEvent::Opened => println!("Door opened!"),
+            DoorEvent::Closed => println!("Door closed!"),
         }
-
-        thread::sleep(Duration::from_millis(500));
     }
+
+    Ok(())
 }
 
 fn main() {
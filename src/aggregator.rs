@@ -0,0 +1,211 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! Multiple sensors measuring on their own worker threads, merged into one stream.
+//!
+//! [`Aggregator`] runs a continuous worker per sensor (see [`HcSr04::spawn_continuous`]), each
+//! at its own interval, and merges their measurements into a single stream of [`Reading`]s
+//! labeled with the sensor they come from, so that callers don't juggle one channel per sensor.
+//! Readings are timestamped on a clock shared by all sensors.
+//!
+//! Sensors measure independently of each other: sensors whose bursts can reach one another
+//! should rather measure in sequence (see [`HcSr04Array`](crate::HcSr04Array)).
+//!
+//! ```rust
+//! # #[cfg(feature = "mock")]
+//! # {
+//! use hc_sr04::{aggregator::Aggregator, MockHcSr04, Unit};
+//! use std::time::Duration;
+//!
+//! let mut front = MockHcSr04::mock(None);
+//! front.push_distance(1., Unit::Meters);
+//! let mut rear = MockHcSr04::mock(None);
+//! rear.push_distance(2., Unit::Meters);
+//!
+//! let mut aggregator = Aggregator::new();
+//! aggregator.add("front", front, Duration::from_millis(10));
+//! aggregator.add("rear", rear, Duration::from_millis(20));
+//!
+//! let readings: Vec<_> = aggregator.by_ref().take(8).collect();
+//! assert!(readings.iter().any(|reading| &*reading.label == "front"));
+//! assert!(readings.iter().any(|reading| &*reading.label == "rear"));
+//! aggregator.stop();
+//! # }
+//! ```
+
+use crate::{backend::Backend, continuous::ContinuousWorker, HcSr04, Measurement, Result};
+use std::{
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Interval at which waiting for readings checks whether every worker stopped.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Measurement of one of the sensors of an [`Aggregator`].
+///
+/// # Fields
+///
+/// - `sensor`: index of the sensor, in the order sensors were added
+/// - `label`: label of the sensor
+/// - `elapsed`: time the measurement completed at, since the aggregator was created
+/// - `measurement`: measurement, or the error it failed with, timestamped as the aggregator's
+///   creation time plus `elapsed`
+#[derive(Debug)]
+pub struct Reading {
+    pub sensor: usize,
+    pub label: Arc<str>,
+    pub elapsed: Duration,
+    pub measurement: Result<Measurement>,
+}
+
+/// Sensors measuring on their own worker threads, merged into one stream of [`Reading`]s.
+///
+/// Readings come in the order measurements complete, timestamped on the monotonic clock started
+/// when the aggregator was created, so that they stay consistent across sensors even if the
+/// system clock is adjusted. Dropping the aggregator asks every worker to stop, without waiting
+/// for them.
+///
+/// # Fields
+///
+/// - `started`: creation time of the aggregator, on the monotonic clock
+/// - `epoch`: creation time of the aggregator, on the system clock
+/// - `sender`: sending side of the merged stream, cloned for every worker
+/// - `receiver`: receiving side of the merged stream
+/// - `workers`: label and worker of every sensor, in the order they were added
+#[derive(Debug)]
+pub struct Aggregator {
+    started: Instant,
+    epoch: SystemTime,
+    sender: Sender<Reading>,
+    receiver: Receiver<Reading>,
+    workers: Vec<(Arc<str>, ContinuousWorker)>,
+}
+
+impl Aggregator {
+    /// Create an aggregator with no sensors.
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            started: Instant::now(),
+            epoch: SystemTime::now(),
+            sender,
+            receiver,
+            workers: Vec::new(),
+        }
+    }
+
+    /// Start measuring distance through `sensor` every `interval` on its own worker thread,
+    /// labeling its readings with `label`, and return the sensor index.
+    pub fn add<B: Backend + Send + 'static>(
+        &mut self,
+        label: impl Into<String>,
+        sensor: HcSr04<B>,
+        interval: Duration,
+    ) -> usize {
+        let index = self.workers.len();
+        let label: Arc<str> = label.into().into();
+        let sender = self.sender.clone();
+        let (started, epoch) = (self.started, self.epoch);
+        let tag = label.clone();
+
+        let worker = sensor.spawn_ticks(interval, move |sensor, _| {
+            let measurement = sensor.measurement();
+            let elapsed = started.elapsed();
+            let measurement = measurement.map(|measurement| Measurement {
+                timestamp: epoch + elapsed,
+                ..measurement
+            });
+            // Stop measuring as soon as the aggregator is dropped.
+            sender
+                .send(Reading {
+                    sensor: index,
+                    label: tag.clone(),
+                    elapsed,
+                    measurement,
+                })
+                .is_ok()
+        });
+        self.workers.push((label, worker));
+
+        index
+    }
+
+    /// Number of sensors added to the aggregator.
+    pub fn len(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Returns `true` if no sensor was added to the aggregator.
+    pub fn is_empty(&self) -> bool {
+        self.workers.is_empty()
+    }
+
+    /// Labels of the sensors, in the order they were added.
+    pub fn labels(&self) -> impl Iterator<Item = &str> + '_ {
+        self.workers.iter().map(|(label, _)| &**label)
+    }
+
+    /// Wait for the next reading of any sensor, or `None` once every worker stopped (e.g. after
+    /// panicking) and no reading is left.
+    pub fn recv(&self) -> Option<Reading> {
+        loop {
+            match self.receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(reading) => return Some(reading),
+                Err(RecvTimeoutError::Timeout) if !self.is_finished() => continue,
+                Err(_) => return self.receiver.try_recv().ok(),
+            }
+        }
+    }
+
+    /// Take the next reading if one is available, without waiting.
+    pub fn try_recv(&self) -> Option<Reading> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Whether every worker stopped.
+    pub fn is_finished(&self) -> bool {
+        self.workers.iter().all(|(_, worker)| worker.is_finished())
+    }
+
+    /// Stop every worker gracefully, waiting for the in-flight measurements to complete and for
+    /// the sensors to be dropped, releasing the pins. Readings not received yet are discarded.
+    ///
+    /// # Panics
+    ///
+    /// Resumes the panic of a worker thread, if any panicked.
+    pub fn stop(self) {
+        for (_, worker) in self.workers {
+            worker.stop();
+        }
+    }
+}
+
+impl Default for Aggregator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Iterator for Aggregator {
+    type Item = Reading;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+}
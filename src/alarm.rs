@@ -0,0 +1,300 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! Proximity alarm driving an output pin.
+//!
+//! [`Alarm`] turns distance readings into an on/off alarm state according to threshold
+//! [`Rule`]s: the alarm turns on as soon as any rule is met, and off again only once no rule is
+//! met even accounting for a hysteresis band, so that objects lingering around a threshold don't
+//! make the output chatter. Minimum on and off times additionally keep the output in each state
+//! for a while, e.g. to sound a buzzer long enough to be noticed or to spare relay contacts.
+//!
+//! ```rust
+//! use hc_sr04::{
+//!     alarm::{Alarm, Rule},
+//!     Distance, Unit,
+//! };
+//! use std::time::{Duration, Instant};
+//!
+//! let mut alarm = Alarm::new(Rule::Closer(Distance::new(30., Unit::Centimeters)))
+//!     .hysteresis(Distance::new(5., Unit::Centimeters))
+//!     .min_on(Duration::from_secs(2));
+//!
+//! let reading = |cm| Some(Distance::new(cm, Unit::Centimeters));
+//! let start = Instant::now();
+//! assert_eq!(alarm.update_at(reading(25.), start), Some(true));
+//! // Within the hysteresis band: still on.
+//! assert_eq!(alarm.update_at(reading(32.), start + Duration::from_secs(3)), None);
+//! assert_eq!(alarm.update_at(reading(40.), start + Duration::from_secs(4)), Some(false));
+//! // No minimum off time: on again right away.
+//! assert_eq!(alarm.update_at(reading(20.), start + Duration::from_secs(4)), Some(true));
+//! // Kept on for at least 2s.
+//! assert_eq!(alarm.update_at(None, start + Duration::from_secs(5)), None);
+//! assert_eq!(alarm.update_at(None, start + Duration::from_secs(6)), Some(false));
+//! ```
+//!
+//! With the `rppal` feature enabled, [`HcSr04::start_alarm`] drives an [`AlarmOutput`] pin (e.g.
+//! a relay or buzzer) from the continuous mode, turning the sensor into a standalone proximity
+//! alarm:
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "rppal")]
+//! # {
+//! use hc_sr04::{
+//!     alarm::{Alarm, AlarmOutput, Rule},
+//!     Distance, HcSr04, Unit,
+//! };
+//! use rppal::gpio::Gpio;
+//! use std::{thread, time::Duration};
+//!
+//! let ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//!
+//! let alarm = Alarm::new(Rule::Closer(Distance::new(50., Unit::Centimeters)))
+//!     .hysteresis(Distance::new(10., Unit::Centimeters))
+//!     .min_on(Duration::from_secs(1))
+//!     .min_off(Duration::from_millis(500));
+//! // Relay board switching on a low level.
+//! let pin = Gpio::new().unwrap().get(17).unwrap().into_output();
+//! let relay = AlarmOutput::new(pin).active_low(true);
+//!
+//! let worker = ultrasonic.start_alarm(Duration::from_millis(100), alarm, relay);
+//! thread::sleep(Duration::from_secs(3600));
+//! worker.stop();
+//! # }
+//! ```
+
+#[cfg(feature = "rppal")]
+use crate::{backend::Backend, continuous::ContinuousWorker, HcSr04};
+use crate::{Distance, Measurement, Result};
+#[cfg(feature = "rppal")]
+use rppal::gpio::OutputPin;
+use std::time::{Duration, Instant};
+
+/// Condition on the measured distance turning the alarm on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rule {
+    /// An object is closer than the distance.
+    Closer(Distance),
+    /// No object is closer than the distance, including when no object is in range.
+    Farther(Distance),
+}
+
+impl Rule {
+    /// Whether `distance` (`None` if no object is in range) meets the rule, with the threshold
+    /// relaxed by `hysteresis` while the alarm is on.
+    fn is_met(self, distance: Option<Distance>, hysteresis: Option<Distance>) -> bool {
+        match (self, distance) {
+            (Self::Closer(threshold), Some(distance)) => match hysteresis {
+                Some(hysteresis) => distance <= threshold + hysteresis,
+                None => distance < threshold,
+            },
+            (Self::Closer(_), None) => false,
+            (Self::Farther(threshold), Some(distance)) => match hysteresis {
+                Some(hysteresis) => distance + hysteresis >= threshold,
+                None => distance > threshold,
+            },
+            (Self::Farther(_), None) => true,
+        }
+    }
+}
+
+/// Alarm state driven by threshold rules, with hysteresis and minimum on and off times.
+///
+/// # Fields
+///
+/// - `rules`: rules turning the alarm on, any of them being enough
+/// - `hysteresis`: distance the thresholds are relaxed by while the alarm is on
+/// - `min_on`: minimum time the alarm stays on
+/// - `min_off`: minimum time the alarm stays off
+/// - `active`: whether the alarm is on
+/// - `since`: time of the latest transition (`None` before the first one)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alarm {
+    rules: Vec<Rule>,
+    hysteresis: Distance,
+    min_on: Duration,
+    min_off: Duration,
+    active: bool,
+    since: Option<Instant>,
+}
+
+impl Alarm {
+    /// Create a new alarm turning on when `rule` is met, with no hysteresis nor minimum on and
+    /// off times. Starts off.
+    pub fn new(rule: Rule) -> Self {
+        Self {
+            rules: vec![rule],
+            hysteresis: Distance::from_meters(0.),
+            min_on: Duration::ZERO,
+            min_off: Duration::ZERO,
+            active: false,
+            since: None,
+        }
+    }
+
+    /// Turn the alarm on when `rule` is met as well, e.g. when a tank is either too full or too
+    /// empty.
+    pub fn or(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Distance the thresholds are relaxed by while the alarm is on (defaults to zero): an object
+    /// triggering a `Closer` rule must move `hysteresis` beyond the threshold to turn the alarm
+    /// off, and conversely for `Farther` rules.
+    pub fn hysteresis(mut self, hysteresis: Distance) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    /// Minimum time the alarm stays on once turned on (defaults to zero).
+    pub fn min_on(mut self, time: Duration) -> Self {
+        self.min_on = time;
+        self
+    }
+
+    /// Minimum time the alarm stays off once turned off (defaults to zero).
+    pub fn min_off(mut self, time: Duration) -> Self {
+        self.min_off = time;
+        self
+    }
+
+    /// Whether the alarm is on.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Update the alarm with the measured `distance` (`None` if no object is in range), returning
+    /// the new state on transitions.
+    pub fn update(&mut self, distance: Option<Distance>) -> Option<bool> {
+        self.update_at(distance, Instant::now())
+    }
+
+    /// Update the alarm with the `distance` measured at `now` (see [`Alarm::update`]).
+    ///
+    /// Transitions due before the minimum on or off time elapsed are deferred: the state changes
+    /// on the first update past it, if the readings still call for it.
+    pub fn update_at(&mut self, distance: Option<Distance>, now: Instant) -> Option<bool> {
+        let hysteresis = self.active.then_some(self.hysteresis);
+        let next = self
+            .rules
+            .iter()
+            .any(|rule| rule.is_met(distance, hysteresis));
+        if next == self.active {
+            return None;
+        }
+
+        let min_time = if self.active {
+            self.min_on
+        } else {
+            self.min_off
+        };
+        if let Some(since) = self.since {
+            if now.saturating_duration_since(since) < min_time {
+                return None;
+            }
+        }
+
+        self.active = next;
+        self.since = Some(now);
+        Some(next)
+    }
+
+    /// Update the alarm with the outcome of a continuous `measurement`, leaving the state
+    /// untouched on errors.
+    pub fn handle(&mut self, measurement: &Result<Measurement>) -> Option<bool> {
+        self.update(measurement.as_ref().ok()?.distance)
+    }
+
+    /// Turn the alarm off, discarding the minimum on and off times.
+    pub fn reset(&mut self) {
+        self.active = false;
+        self.since = None;
+    }
+}
+
+/// Output pin driven by an [`Alarm`] (e.g. a relay or buzzer), available with the `rppal`
+/// feature.
+///
+/// The pin is driven to the off level when wrapped and again when dropped.
+///
+/// # Fields
+///
+/// - `pin`: output pin
+/// - `active_low`: whether the pin is driven low when the alarm is on
+#[cfg(feature = "rppal")]
+#[derive(Debug)]
+pub struct AlarmOutput {
+    pin: OutputPin,
+    active_low: bool,
+}
+
+#[cfg(feature = "rppal")]
+impl AlarmOutput {
+    /// Drive `pin`, high when the alarm is on.
+    pub fn new(mut pin: OutputPin) -> Self {
+        pin.set_low();
+        Self {
+            pin,
+            active_low: false,
+        }
+    }
+
+    /// Whether the pin is driven low when the alarm is on (disabled by default), as many relay
+    /// boards expect. Drives the pin to the new off level.
+    pub fn active_low(mut self, active_low: bool) -> Self {
+        self.active_low = active_low;
+        self.set(false);
+        self
+    }
+
+    /// Drive the pin to the on level if `active`, to the off level otherwise.
+    pub fn set(&mut self, active: bool) {
+        if active != self.active_low {
+            self.pin.set_high();
+        } else {
+            self.pin.set_low();
+        }
+    }
+}
+
+#[cfg(feature = "rppal")]
+impl Drop for AlarmOutput {
+    fn drop(&mut self) {
+        self.set(false);
+    }
+}
+
+#[cfg(feature = "rppal")]
+impl<B: Backend + Send + 'static> HcSr04<B> {
+    /// Start measuring distance every `interval` on a worker thread (see
+    /// [`HcSr04::spawn_continuous`]), driving `output` on and off as `alarm` turns.
+    ///
+    /// Failed measurements leave the output untouched. Once the worker is stopped, `output` is
+    /// driven to the off level.
+    pub fn start_alarm(
+        self,
+        interval: Duration,
+        mut alarm: Alarm,
+        mut output: AlarmOutput,
+    ) -> ContinuousWorker {
+        self.spawn_continuous(interval, move |measurement| {
+            if let Some(active) = alarm.handle(measurement) {
+                output.set(active);
+            }
+            true
+        })
+    }
+}
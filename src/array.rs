@@ -0,0 +1,138 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Multiple sensors measuring in sequence.
+//!
+//! Ultrasonic bursts from one sensor can be picked up by the others, resulting in ghost readings:
+//! [`HcSr04Array`] never pings a sensor before the configured delay has elapsed since the
+//! previous measurement completed, whichever sensor performed it.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{HcSr04, HcSr04Array, Unit};
+//! use std::time::Duration;
+//!
+//! let mut array = HcSr04Array::new(vec![
+//!     HcSr04::new(24, 23, None).unwrap(), // front
+//!     HcSr04::new(17, 27, None).unwrap(), // rear
+//! ])
+//! .with_delay(Duration::from_millis(80));
+//!
+//! for (i, dist) in array.measure_distance(Unit::Meters).unwrap().iter().enumerate() {
+//!     match dist {
+//!         Some(dist) => println!("Sensor {i} distance: {:.2}m", dist),
+//!         None => println!("Sensor {i}: object out of range"),
+//!     }
+//! }
+//! ```
+
+use crate::{backend::Backend, DefaultBackend, Float, HcSr04, Result, Unit};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Default delay between consecutive measurements, as per HC-SR04 recommended measurement cycle.
+const DEFAULT_DELAY: Duration = Duration::from_millis(60);
+
+/// Array of [`HcSr04`] sensors measuring one at a time.
+///
+/// # Fields
+///
+/// - `sensors`: sensors, measuring in order
+/// - `delay`: minimum delay between the end of a measurement and the next ping
+/// - `last`: end of the last measurement
+#[derive(Debug)]
+pub struct HcSr04Array<B = DefaultBackend> {
+    sensors: Vec<HcSr04<B>>,
+    delay: Duration,
+    last: Option<Instant>,
+}
+
+impl<B: Backend> HcSr04Array<B> {
+    /// Create an array from `sensors`, measuring in the given order with the default inter-sensor
+    /// delay of *60ms*.
+    pub fn new(sensors: Vec<HcSr04<B>>) -> Self {
+        Self {
+            sensors,
+            delay: DEFAULT_DELAY,
+            last: None,
+        }
+    }
+
+    /// Set the minimum delay between the end of a measurement and the next ping.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Append `sensor` to the array.
+    pub fn push(&mut self, sensor: HcSr04<B>) {
+        self.sensors.push(sensor);
+    }
+
+    /// Number of sensors in the array.
+    pub fn len(&self) -> usize {
+        self.sensors.len()
+    }
+
+    /// Returns `true` if the array contains no sensors.
+    pub fn is_empty(&self) -> bool {
+        self.sensors.is_empty()
+    }
+
+    /// Mutable access to the `index`-th sensor, e.g. to measure it alone.
+    ///
+    /// Measurements performed this way bypass the inter-sensor delay.
+    pub fn sensor_mut(&mut self, index: usize) -> Option<&mut HcSr04<B>> {
+        self.sensors.get_mut(index)
+    }
+
+    /// Calibrate every sensor with the given **ambient temperature** (`temp`) expressed as
+    /// *Celsius degrees*.
+    pub fn calibrate(&mut self, temp: Float) {
+        for sensor in &mut self.sensors {
+            sensor.calibrate(temp);
+        }
+    }
+
+    /// Perform **distance measurement** (see [`HcSr04::measure_distance`]) on the `index`-th
+    /// sensor, waiting for the inter-sensor delay first.
+    ///
+    /// Returns `None` if there's no sensor at `index`.
+    pub fn measure_one(&mut self, index: usize, unit: Unit) -> Option<Result<Option<Float>>> {
+        let sensor = self.sensors.get_mut(index)?;
+
+        if let Some(last) = self.last {
+            if let Some(wait) = self.delay.checked_sub(last.elapsed()) {
+                thread::sleep(wait);
+            }
+        }
+        let distance = sensor.measure_distance(unit);
+        self.last = Some(Instant::now());
+
+        Some(distance)
+    }
+
+    /// Perform **distance measurement** on every sensor in order, returning the distances in the
+    /// same order.
+    ///
+    /// Fails on the first sensor returning an error.
+    pub fn measure_distance(&mut self, unit: Unit) -> Result<Vec<Option<Float>>> {
+        (0..self.sensors.len())
+            .filter_map(|index| self.measure_one(index, unit))
+            .collect()
+    }
+}
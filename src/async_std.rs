@@ -0,0 +1,191 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Asynchronous measurement API for the async-std runtime, available with the `async-std`
+//! feature.
+//!
+//! Mirrors the tokio based [`asynchronous`](crate::asynchronous) API: measurements are performed
+//! on async-std's blocking thread pool, so that the trigger pulse and the **ECHO** pin interrupt
+//! polling never block the executor.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{async_std::HcSr04Async, Unit};
+//!
+//! # async fn run() {
+//! let ultrasonic = HcSr04Async::new(24, 23, None).unwrap();
+//!
+//! match ultrasonic.measure_distance(Unit::Meters).await.unwrap() {
+//!     Some(dist) => println!("Distance: {:.2}m", dist),
+//!     None => println!("Object out of range"),
+//! }
+//! # }
+//! ```
+//!
+//! Measurements can also be streamed at a fixed interval (see [`HcSr04Async::stream`]):
+//!
+//! ```rust,no_run
+//! use futures::StreamExt;
+//! use hc_sr04::async_std::HcSr04Async;
+//! use std::time::Duration;
+//!
+//! # async fn run() {
+//! let ultrasonic = HcSr04Async::new(24, 23, None).unwrap();
+//! let mut stream = ultrasonic.stream(Duration::from_millis(100));
+//!
+//! while let Some(measurement) = stream.next().await {
+//!     println!("Distance: {:?}", measurement.unwrap().distance);
+//! }
+//! # }
+//! ```
+
+use crate::{
+    backend::Backend,
+    filter::{DistanceFilter, Pipeline},
+    DefaultBackend, Float, HcSr04, Measurement, Result, Unit,
+};
+use async_std::{
+    channel::{self, Receiver},
+    sync::{Arc, Mutex},
+    task,
+};
+use futures_core::Stream;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+/// Asynchronous wrapper around [`HcSr04`] sensor, for the async-std runtime.
+///
+/// Cloning the wrapper yields a new handle to the same sensor: concurrent measurements are
+/// serialized.
+#[derive(Debug)]
+pub struct HcSr04Async<B = DefaultBackend> {
+    sensor: Arc<Mutex<HcSr04<B>>>,
+}
+
+#[cfg(feature = "rppal")]
+impl HcSr04Async {
+    /// Initialize HC-SR04 sensor (see [`HcSr04::new`]).
+    pub fn new(trig: u8, echo: u8, temp: Option<Float>) -> Result<Self> {
+        Ok(Self::from(HcSr04::new(trig, echo, temp)?))
+    }
+}
+
+impl<B> Clone for HcSr04Async<B> {
+    fn clone(&self) -> Self {
+        Self {
+            sensor: self.sensor.clone(),
+        }
+    }
+}
+
+impl<B: Backend + Send + 'static> HcSr04Async<B> {
+    /// Calibrate the sensor with the given **ambient temperature** (`temp`) expressed as *Celsius
+    /// degrees*.
+    pub async fn calibrate(&self, temp: Float) {
+        self.sensor.lock().await.calibrate(temp);
+    }
+
+    /// Perform **distance measurement** without blocking the executor (see
+    /// [`HcSr04::measure_distance`]).
+    pub async fn measure_distance(&self, unit: Unit) -> Result<Option<Float>> {
+        self.blocking(move |sensor| sensor.measure_distance(unit))
+            .await
+    }
+
+    /// Stream measurements taken every `interval`.
+    ///
+    /// Measuring pauses while the consumer lags behind. If a measurement takes longer than
+    /// `interval`, the next one starts right away.
+    pub fn stream(&self, interval: Duration) -> DistanceStream {
+        let (sender, receiver) = channel::bounded(1);
+        let sensor = self.clone();
+
+        task::spawn(async move {
+            loop {
+                let start = Instant::now();
+                if sender.send(sensor.measurement().await).await.is_err() {
+                    // Stream dropped: stop measuring.
+                    break;
+                }
+                task::sleep(interval.saturating_sub(start.elapsed())).await;
+            }
+        });
+
+        DistanceStream {
+            receiver: Box::pin(receiver),
+            filter: Pipeline::new(),
+        }
+    }
+
+    /// Perform **distance measurement** expressed as the default unit, timestamping the result.
+    async fn measurement(&self) -> Result<Measurement> {
+        self.blocking(HcSr04::measurement).await
+    }
+
+    /// Run `f` on the sensor on async-std's blocking thread pool.
+    async fn blocking<T, F>(&self, f: F) -> T
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut HcSr04<B>) -> T + Send + 'static,
+    {
+        let mut sensor = self.sensor.lock_arc().await;
+
+        task::spawn_blocking(move || f(&mut sensor)).await
+    }
+}
+
+impl<B> From<HcSr04<B>> for HcSr04Async<B> {
+    fn from(sensor: HcSr04<B>) -> Self {
+        Self {
+            sensor: Arc::new(Mutex::new(sensor)),
+        }
+    }
+}
+
+/// [`Stream`] of measurements taken at a fixed interval, obtained through
+/// [`HcSr04Async::stream`].
+///
+/// Measurements are taken by a background task, which stops as soon as the stream is dropped.
+#[derive(Debug)]
+pub struct DistanceStream {
+    receiver: Pin<Box<Receiver<Result<Measurement>>>>,
+    filter: Pipeline,
+}
+
+impl DistanceStream {
+    /// Filter streamed distances through `filter`, on top of the filters set on the sensor.
+    /// Calling this more than once chains the filters, in the given order.
+    ///
+    /// Readings rejected by the filter are streamed with no distance, while the variance estimate
+    /// of streamed measurements accounts for the filter (see [`Measurement::variance`]).
+    pub fn filter(mut self, filter: impl DistanceFilter + Send + 'static) -> Self {
+        self.filter.push(filter);
+        self
+    }
+}
+
+impl Stream for DistanceStream {
+    type Item = Result<Measurement>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.receiver.as_mut().poll_next(cx).map(|measurement| {
+            measurement.map(|measurement| this.filter.filter_measurement(measurement))
+        })
+    }
+}
@@ -0,0 +1,158 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Asynchronous measurement API, available with the `tokio` feature.
+//!
+//! Measurements are performed on tokio's blocking thread pool, so that the trigger pulse and the
+//! **ECHO** pin interrupt polling never block the async runtime.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{HcSr04Async, Unit};
+//!
+//! # async fn run() {
+//! let ultrasonic = HcSr04Async::new(24, 23, None).unwrap();
+//!
+//! match ultrasonic.measure_distance(Unit::Meters).await.unwrap() {
+//!     Some(dist) => println!("Distance: {:.2}m", dist),
+//!     None => println!("Object out of range"),
+//! }
+//! # }
+//! ```
+//!
+//! The latest measurement can also be shared among many tasks through a `watch` channel (see
+//! [`HcSr04Async::watch`]):
+//!
+//! ```rust,no_run
+//! use hc_sr04::HcSr04Async;
+//! use std::time::Duration;
+//!
+//! # async fn run() {
+//! let ultrasonic = HcSr04Async::new(24, 23, None).unwrap();
+//! let mut latest = ultrasonic.watch(Duration::from_millis(100));
+//!
+//! while latest.changed().await.is_ok() {
+//!     if let Some(measurement) = *latest.borrow() {
+//!         println!("Distance: {:?}", measurement.distance);
+//!     }
+//! }
+//! # }
+//! ```
+
+use crate::{backend::Backend, DefaultBackend, Float, HcSr04, Measurement, Result, Unit};
+use std::{panic, sync::Arc, time::Duration};
+use tokio::{
+    sync::{watch, Mutex},
+    task,
+    time::{self, MissedTickBehavior},
+};
+
+/// Asynchronous wrapper around [`HcSr04`] sensor.
+///
+/// Cloning the wrapper yields a new handle to the same sensor: concurrent measurements are
+/// serialized.
+#[derive(Debug)]
+pub struct HcSr04Async<B = DefaultBackend> {
+    sensor: Arc<Mutex<HcSr04<B>>>,
+}
+
+#[cfg(feature = "rppal")]
+impl HcSr04Async {
+    /// Initialize HC-SR04 sensor (see [`HcSr04::new`]).
+    pub fn new(trig: u8, echo: u8, temp: Option<Float>) -> Result<Self> {
+        Ok(Self::from(HcSr04::new(trig, echo, temp)?))
+    }
+}
+
+impl<B> Clone for HcSr04Async<B> {
+    fn clone(&self) -> Self {
+        Self {
+            sensor: self.sensor.clone(),
+        }
+    }
+}
+
+impl<B: Backend + Send + 'static> HcSr04Async<B> {
+    /// Calibrate the sensor with the given **ambient temperature** (`temp`) expressed as *Celsius
+    /// degrees*.
+    pub async fn calibrate(&self, temp: Float) {
+        self.sensor.lock().await.calibrate(temp);
+    }
+
+    /// Perform **distance measurement** without blocking the async runtime (see
+    /// [`HcSr04::measure_distance`]).
+    pub async fn measure_distance(&self, unit: Unit) -> Result<Option<Float>> {
+        self.blocking(move |sensor| sensor.measure_distance(unit))
+            .await
+    }
+
+    /// Measure distance every `interval` on a background task, exposing the most recent
+    /// measurement through a [`watch`] channel, so that many tasks can cheaply observe the current
+    /// distance without each consuming a stream.
+    ///
+    /// The channel holds `None` until the first measurement succeeds; failed measurements leave
+    /// the latest value untouched. The background task stops as soon as all receivers are
+    /// dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn watch(&self, interval: Duration) -> watch::Receiver<Option<Measurement>> {
+        let (sender, receiver) = watch::channel(None);
+        let sensor = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = time::interval(interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            // Stop measuring as soon as all receivers are dropped.
+            while !sender.is_closed() {
+                interval.tick().await;
+                if let Ok(measurement) = sensor.measurement().await {
+                    sender.send_replace(Some(measurement));
+                }
+            }
+        });
+
+        receiver
+    }
+
+    /// Perform **distance measurement** expressed as the default unit, timestamping the result.
+    pub(crate) async fn measurement(&self) -> Result<Measurement> {
+        self.blocking(HcSr04::measurement).await
+    }
+
+    /// Run `f` on the sensor on tokio's blocking thread pool.
+    async fn blocking<T, F>(&self, f: F) -> T
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut HcSr04<B>) -> T + Send + 'static,
+    {
+        let mut sensor = self.sensor.clone().lock_owned().await;
+
+        match task::spawn_blocking(move || f(&mut sensor)).await {
+            Ok(result) => result,
+            Err(err) => panic::resume_unwind(err.into_panic()),
+        }
+    }
+}
+
+impl<B> From<HcSr04<B>> for HcSr04Async<B> {
+    fn from(sensor: HcSr04<B>) -> Self {
+        Self {
+            sensor: Arc::new(Mutex::new(sensor)),
+        }
+    }
+}
@@ -0,0 +1,163 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Platform layer driving the sensor's **TRIGGER** and **ECHO** pins.
+//!
+//! [`HcSr04`](crate::HcSr04) performs speed of sound calibration and unit conversion on top of
+//! any [`Backend`], which only deals with GPIO access and pulse timing.
+//!
+//! Available backends:
+//!
+//! - [`Rppal`]: *Raspberry Pi* GPIO through [rppal](https://docs.rs/rppal), using interrupts on the
+//!   **ECHO** pin or busy polling it, optionally switching the sensor's supply through a
+//!   [`PowerGate`] (feature `rppal`, enabled by default)
+//! - `EmbeddedHal`: any [embedded-hal](https://docs.rs/embedded-hal) pins, delay and a
+//!   `Clock`, busy polling the **ECHO** pin (feature `embedded-hal`)
+//! - `Cdev`: Linux GPIO character device through [gpio-cdev](https://docs.rs/gpio-cdev), for
+//!   boards other than *Raspberry Pi*, busy polling the **ECHO** line (feature `gpio-cdev`)
+//! - `Pigpio`: *Raspberry Pi* GPIO through the pigpio daemon, locally or over the network,
+//!   timing the **ECHO** pulse from the daemon's level change reports (feature `pigpio`)
+//!
+//! Boxed backends are backends as well, so that the backend can be selected at runtime:
+//!
+//! ```rust,no_run
+//! use hc_sr04::{backend::Backend, HcSr04, HcSr04Builder};
+//!
+//! fn sensor(backend: Box<dyn Backend + Send>) -> HcSr04<Box<dyn Backend + Send>> {
+//!     HcSr04Builder::with_backend(backend).build()
+//! }
+//! ```
+
+#[cfg(feature = "gpio-cdev")]
+mod cdev;
+#[cfg(feature = "embedded-hal")]
+mod hal;
+#[cfg(feature = "pigpio")]
+mod pigpio;
+#[cfg(feature = "rppal")]
+mod rppal;
+
+#[cfg(feature = "rppal")]
+pub use self::rppal::{PowerGate, Precision, Rppal};
+#[cfg(feature = "gpio-cdev")]
+pub use cdev::Cdev;
+#[cfg(feature = "embedded-hal")]
+pub use hal::{Clock, EmbeddedHal, StdClock};
+#[cfg(feature = "pigpio")]
+pub use pigpio::Pigpio;
+
+pub use crate::portable::Timing;
+
+use crate::{CancelToken, Result};
+use std::time::Duration;
+
+/// GPIO access and pulse timing for the **HC-SR04** sensor.
+pub trait Backend {
+    /// Send a **TRIGGER** pulse, then wait for the **ECHO** pulse and return its width, as per
+    /// `timing`.
+    ///
+    /// Returns `None` if the **ECHO** pulse doesn't end within `timing.pulse_timeout`, and fails
+    /// with [`Error::EchoStartTimeout`](crate::error::Error::EchoStartTimeout) if it doesn't
+    /// start within `timing.start_timeout`.
+    fn ping(&mut self, timing: &Timing) -> Result<Option<Duration>>;
+
+    /// Ping as [`Backend::ping`] does, failing with [`Error::Cancelled`] as soon as possible
+    /// once `cancel` is cancelled, rather than waiting out the timeouts.
+    ///
+    /// Defaults to pinging regardless of `cancel`, only letting cancellation take effect before
+    /// the next ping.
+    ///
+    /// [`Error::Cancelled`]: crate::error::Error::Cancelled
+    fn ping_cancellable(
+        &mut self,
+        timing: &Timing,
+        cancel: &CancelToken,
+    ) -> Result<Option<Duration>> {
+        let _ = cancel;
+        self.ping(timing)
+    }
+
+    /// Attempt to recover from a sensor lock-up (e.g. the **ECHO** pin stuck high), after
+    /// several consecutive failed pings (see [`HcSr04Builder::watchdog`]). Does nothing by
+    /// default.
+    ///
+    /// [`HcSr04Builder::watchdog`]: crate::HcSr04Builder::watchdog
+    fn recover(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Current level of the **ECHO** pin, `true` if high, or `None` if the backend cannot read
+    /// it outside of pings (the default), e.g. to detect the pin stuck high (see
+    /// [`HcSr04::self_test`]).
+    ///
+    /// [`HcSr04::self_test`]: crate::HcSr04::self_test
+    fn echo_level(&mut self) -> Option<bool> {
+        None
+    }
+
+    /// Power the sensor on, waiting for it to warm up, or off, if the backend switches its
+    /// supply (see [`Backend::is_powered`]). Does nothing by default.
+    fn set_powered(&mut self, on: bool) -> Result<()> {
+        let _ = on;
+        Ok(())
+    }
+
+    /// Whether the sensor is powered, or `None` if the backend doesn't switch its supply (the
+    /// default).
+    fn is_powered(&self) -> Option<bool> {
+        None
+    }
+
+    /// Set the time the sensor takes to become operational after power-on, if the backend
+    /// switches its supply (see [`Backend::set_powered`]). Does nothing by default.
+    fn set_warm_up(&mut self, delay: Duration) {
+        let _ = delay;
+    }
+}
+
+impl<B: Backend + ?Sized> Backend for Box<B> {
+    fn ping(&mut self, timing: &Timing) -> Result<Option<Duration>> {
+        (**self).ping(timing)
+    }
+
+    fn ping_cancellable(
+        &mut self,
+        timing: &Timing,
+        cancel: &CancelToken,
+    ) -> Result<Option<Duration>> {
+        (**self).ping_cancellable(timing, cancel)
+    }
+
+    fn recover(&mut self) -> Result<()> {
+        (**self).recover()
+    }
+
+    fn echo_level(&mut self) -> Option<bool> {
+        (**self).echo_level()
+    }
+
+    fn set_powered(&mut self, on: bool) -> Result<()> {
+        (**self).set_powered(on)
+    }
+
+    fn is_powered(&self) -> Option<bool> {
+        (**self).is_powered()
+    }
+
+    fn set_warm_up(&mut self, delay: Duration) {
+        (**self).set_warm_up(delay);
+    }
+}
@@ -0,0 +1,109 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+use super::{Backend, Timing};
+use crate::{
+    error::Error,
+    portable::{Echo, EchoCapture},
+    CancelToken, Result,
+};
+use gpio_cdev::{Chip, LineHandle, LineRequestFlags};
+use std::{
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Consumer label of the requested GPIO lines.
+const CONSUMER: &str = "hc-sr04";
+
+/// Linux GPIO character device [`Backend`] using [gpio-cdev](https://docs.rs/gpio-cdev), busy
+/// polling the **ECHO** line value.
+///
+/// Runs on any board whose GPIO controller is exposed by the kernel as a `/dev/gpiochipN`
+/// device (e.g. *BeagleBone*, *Orange Pi* or generic industrial Linux boards).
+///
+/// # Fields
+///
+/// - `trig`: **TRIGGER** output GPIO line
+/// - `echo`: **ECHO** input GPIO line
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use hc_sr04::{backend::Cdev, HcSr04Builder, Unit};
+///
+/// let backend = Cdev::new("/dev/gpiochip0", 24, 23).unwrap();
+/// let mut ultrasonic = HcSr04Builder::with_backend(backend)
+///     .temperature(23.)
+///     .build();
+///
+/// println!("{:?}", ultrasonic.measure_distance(Unit::Meters).unwrap());
+/// ```
+#[derive(Debug)]
+pub struct Cdev {
+    trig: LineHandle,
+    echo: LineHandle,
+}
+
+impl Cdev {
+    /// Create the backend requesting the `trig` (**TRIGGER**) and `echo` (**ECHO**) line offsets
+    /// of the GPIO `chip` device (e.g. `/dev/gpiochip0`).
+    pub fn new(chip: impl AsRef<Path>, trig: u32, echo: u32) -> Result<Self> {
+        let mut chip = Chip::new(chip)?;
+
+        Ok(Self {
+            trig: chip
+                .get_line(trig)?
+                .request(LineRequestFlags::OUTPUT, 0, CONSUMER)?,
+            echo: chip
+                .get_line(echo)?
+                .request(LineRequestFlags::INPUT, 0, CONSUMER)?,
+        })
+    }
+}
+
+impl Backend for Cdev {
+    fn ping(&mut self, timing: &Timing) -> Result<Option<Duration>> {
+        self.ping_cancellable(timing, &CancelToken::new())
+    }
+
+    fn ping_cancellable(
+        &mut self,
+        timing: &Timing,
+        cancel: &CancelToken,
+    ) -> Result<Option<Duration>> {
+        self.trig.set_value(1)?;
+        thread::sleep(timing.trigger_pulse);
+        self.trig.set_value(0)?;
+
+        // Wait for the **ECHO** line to go high, then low.
+        let origin = Instant::now();
+        let mut capture = EchoCapture::new(timing, Duration::ZERO);
+        loop {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            let high = self.echo.get_value()? != 0;
+            match capture.poll(origin.elapsed(), high) {
+                Some(Echo::Pulse(width)) => return Ok(Some(width)),
+                Some(Echo::OutOfRange) => return Ok(None),
+                Some(Echo::NoStart) => return Err(Error::EchoStartTimeout),
+                None => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+use super::{Backend, Timing};
+use crate::{
+    error::Error,
+    portable::{Echo, EchoCapture},
+    CancelToken, Result,
+};
+use embedded_hal::{
+    delay::DelayNs,
+    digital::{self, InputPin, OutputPin},
+};
+use std::time::{Duration, Instant};
+
+/// Monotonic time source used to measure the **ECHO** pulse width.
+pub trait Clock {
+    /// Time elapsed since an arbitrary, fixed point in time.
+    fn now(&mut self) -> Duration;
+}
+
+/// [`Clock`] based on [`std::time::Instant`].
+#[derive(Debug, Clone, Copy)]
+pub struct StdClock {
+    origin: Instant,
+}
+
+impl Default for StdClock {
+    fn default() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Clock for StdClock {
+    fn now(&mut self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// [`Backend`] for any [embedded-hal](https://docs.rs/embedded-hal) pins, busy polling the
+/// **ECHO** pin level.
+///
+/// # Fields
+///
+/// - `trig`: **TRIGGER** output pin
+/// - `echo`: **ECHO** input pin
+/// - `delay`: delay provider timing the **TRIGGER** pulse
+/// - `clock`: time source timing the **ECHO** pulse
+///
+/// # Example
+///
+/// ```rust
+/// use embedded_hal::{
+///     delay::DelayNs,
+///     digital::{InputPin, OutputPin},
+/// };
+/// use hc_sr04::{
+///     backend::{EmbeddedHal, StdClock},
+///     Float, HcSr04Builder, Unit,
+/// };
+///
+/// fn distance<T, E, D>(trig: T, echo: E, delay: D) -> hc_sr04::Result<Option<Float>>
+/// where
+///     T: OutputPin,
+///     E: InputPin,
+///     D: DelayNs,
+/// {
+///     let backend = EmbeddedHal::new(trig, echo, delay, StdClock::default());
+///     let mut ultrasonic = HcSr04Builder::with_backend(backend)
+///         .temperature(23.)
+///         .build();
+///
+///     ultrasonic.measure_distance(Unit::Meters)
+/// }
+/// ```
+#[derive(Debug)]
+pub struct EmbeddedHal<T, E, D, C> {
+    trig: T,
+    echo: E,
+    delay: D,
+    clock: C,
+}
+
+impl<T, E, D, C> EmbeddedHal<T, E, D, C>
+where
+    T: OutputPin,
+    E: InputPin,
+    D: DelayNs,
+    C: Clock,
+{
+    /// Create the backend from `trig` (**TRIGGER**) and `echo` (**ECHO**) pins, a `delay`
+    /// provider and a `clock`.
+    pub fn new(trig: T, echo: E, delay: D, clock: C) -> Self {
+        Self {
+            trig,
+            echo,
+            delay,
+            clock,
+        }
+    }
+
+    /// Release the pins, delay provider and clock.
+    pub fn release(self) -> (T, E, D, C) {
+        (self.trig, self.echo, self.delay, self.clock)
+    }
+}
+
+/// Map embedded-hal pin errors to [`Error::Hal`].
+fn hal_error<E: digital::Error>(error: E) -> Error {
+    Error::Hal(error.kind())
+}
+
+impl<T, E, D, C> Backend for EmbeddedHal<T, E, D, C>
+where
+    T: OutputPin,
+    E: InputPin,
+    D: DelayNs,
+    C: Clock,
+{
+    fn ping(&mut self, timing: &Timing) -> Result<Option<Duration>> {
+        self.ping_cancellable(timing, &CancelToken::new())
+    }
+
+    fn ping_cancellable(
+        &mut self,
+        timing: &Timing,
+        cancel: &CancelToken,
+    ) -> Result<Option<Duration>> {
+        self.trig.set_high().map_err(hal_error)?;
+        self.delay
+            .delay_ns(u32::try_from(timing.trigger_pulse.as_nanos()).unwrap_or(u32::MAX));
+        self.trig.set_low().map_err(hal_error)?;
+
+        // Wait for the **ECHO** pin to go high, then low.
+        let mut capture = EchoCapture::new(timing, self.clock.now());
+        loop {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            let high = self.echo.is_high().map_err(hal_error)?;
+            match capture.poll(self.clock.now(), high) {
+                Some(Echo::Pulse(width)) => return Ok(Some(width)),
+                Some(Echo::OutOfRange) => return Ok(None),
+                Some(Echo::NoStart) => return Err(Error::EchoStartTimeout),
+                None => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,285 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+use super::{Backend, Timing};
+use crate::{cancel::CHECK_INTERVAL, error::Error, CancelToken, Pull, Result};
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+/// Extra time allowed for level change reports to travel from the daemon, on top of the timing
+/// timeouts.
+const LATENCY_MARGIN: Duration = Duration::from_millis(100);
+
+/// pigpio socket interface command codes.
+mod command {
+    pub(super) const MODES: u32 = 0;
+    pub(super) const PUD: u32 = 2;
+    pub(super) const WRITE: u32 = 4;
+    pub(super) const TICK: u32 = 16;
+    pub(super) const NB: u32 = 19;
+    pub(super) const TRIG: u32 = 37;
+    pub(super) const NOIB: u32 = 99;
+}
+
+/// GPIO mode and pull resistor values of the pigpio socket interface.
+const MODE_INPUT: u32 = 0;
+const MODE_OUTPUT: u32 = 1;
+const PUD_OFF: u32 = 0;
+const PUD_DOWN: u32 = 1;
+const PUD_UP: u32 = 2;
+
+/// Size of a level change report in bytes.
+const REPORT_SIZE: usize = 12;
+
+/// [`Backend`] driving *Raspberry Pi* GPIO through the [pigpio](https://abyz.me.uk/rpi/pigpio/)
+/// daemon socket interface, either locally or over the network.
+///
+/// The **TRIGGER** pulse is generated by the daemon and the **ECHO** pulse is timed from the
+/// daemon's level change reports, so that network latency doesn't affect measurements (only
+/// the time it takes for them to complete).
+///
+/// # Fields
+///
+/// - `commands`: command socket
+/// - `notifications`: level change reports socket
+/// - `received`: bytes read from the reports socket not yet parsed as a complete report
+/// - `trig`: **TRIGGER** output GPIO pin
+/// - `echo`: **ECHO** input GPIO pin
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use hc_sr04::{backend::Pigpio, HcSr04Builder, Unit};
+///
+/// let backend = Pigpio::connect(("raspberrypi.local", Pigpio::DEFAULT_PORT), 24, 23).unwrap();
+/// let mut ultrasonic = HcSr04Builder::with_backend(backend)
+///     .temperature(23.)
+///     .build();
+///
+/// println!("{:?}", ultrasonic.measure_distance(Unit::Meters).unwrap());
+/// ```
+#[derive(Debug)]
+pub struct Pigpio {
+    commands: TcpStream,
+    notifications: TcpStream,
+    received: Vec<u8>,
+    trig: u8,
+    echo: u8,
+}
+
+impl Pigpio {
+    /// Default TCP port of the pigpio daemon.
+    pub const DEFAULT_PORT: u16 = 8888;
+
+    /// Connect to the pigpio daemon listening at `addr` and set up the `trig` (**TRIGGER**) and
+    /// `echo` (**ECHO**) GPIO pins, enabling the **ECHO** pin pull-down resistor.
+    pub fn connect(addr: impl ToSocketAddrs, trig: u8, echo: u8) -> Result<Self> {
+        let addrs: Vec<_> = addr.to_socket_addrs()?.collect();
+        let commands = TcpStream::connect(&addrs[..])?;
+        commands.set_nodelay(true)?;
+        let notifications = TcpStream::connect(&addrs[..])?;
+
+        let mut pigpio = Self {
+            commands,
+            notifications,
+            received: Vec::new(),
+            trig,
+            echo,
+        };
+
+        pigpio.command(command::MODES, trig.into(), MODE_OUTPUT)?;
+        pigpio.command(command::WRITE, trig.into(), 0)?;
+        pigpio.command(command::MODES, echo.into(), MODE_INPUT)?;
+        pigpio.pull(Pull::Down)?;
+
+        // Turn the second socket into a level change reports stream, watching the ECHO pin.
+        let handle = request(&mut pigpio.notifications, command::NOIB, 0, 0, &[])?;
+        pigpio.command(command::NB, handle, 1 << echo)?;
+
+        Ok(pigpio)
+    }
+
+    /// Set the pull resistor configuration of the **ECHO** pin.
+    pub fn pull(&mut self, pull: Pull) -> Result<()> {
+        let pud = match pull {
+            Pull::Down => PUD_DOWN,
+            Pull::Up => PUD_UP,
+            Pull::Off => PUD_OFF,
+        };
+        self.command(command::PUD, self.echo.into(), pud)?;
+        Ok(())
+    }
+
+    /// Send a command with no extension through the command socket.
+    fn command(&mut self, cmd: u32, p1: u32, p2: u32) -> Result<u32> {
+        request(&mut self.commands, cmd, p1, p2, &[])
+    }
+
+    /// Current tick of the daemon in µs.
+    fn tick(&mut self) -> Result<u32> {
+        // Ticks fill all 32 bits of the result, which is never an error.
+        let tick = exchange(&mut self.commands, command::TICK, 0, 0, &[])?;
+        Ok(tick as u32)
+    }
+
+    /// Read the next level change report, returning the daemon's tick in µs and whether the
+    /// **ECHO** pin is high, or `None` if no report arrives before `deadline`.
+    ///
+    /// Reports may arrive split across reads: bytes are buffered until a complete report is
+    /// received, so that timing out midway never misaligns the stream.
+    fn report(&mut self, deadline: Option<Instant>) -> Result<Option<(u32, bool)>> {
+        loop {
+            while self.received.len() >= REPORT_SIZE {
+                let report: Vec<_> = self.received.drain(..REPORT_SIZE).collect();
+
+                // Reports with flags set (keep-alive, watchdog, events) carry no level change.
+                let flags = u16::from_le_bytes([report[2], report[3]]);
+                if flags == 0 {
+                    let tick = u32::from_le_bytes([report[4], report[5], report[6], report[7]]);
+                    let level = u32::from_le_bytes([report[8], report[9], report[10], report[11]]);
+                    return Ok(Some((tick, level & (1 << self.echo) != 0)));
+                }
+            }
+
+            let timeout = match deadline {
+                Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    Some(remaining) if !remaining.is_zero() => Some(remaining),
+                    _ => return Ok(None),
+                },
+                None => None,
+            };
+            self.notifications.set_read_timeout(timeout)?;
+
+            let mut buf = [0; 16 * REPORT_SIZE];
+            match self.notifications.read(&mut buf) {
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                Ok(read) => self.received.extend_from_slice(&buf[..read]),
+                Err(error)
+                    if matches!(
+                        error.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None)
+                }
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    /// Read the next level change report as [`Pigpio::report`] does, failing with
+    /// [`Error::Cancelled`] once `cancel` is cancelled.
+    fn report_cancellable(
+        &mut self,
+        deadline: Option<Instant>,
+        cancel: &CancelToken,
+    ) -> Result<Option<(u32, bool)>> {
+        loop {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            let slice = Instant::now() + CHECK_INTERVAL;
+            let sliced = deadline.map_or(true, |deadline| slice < deadline);
+            match self.report(Some(deadline.map_or(slice, |deadline| deadline.min(slice))))? {
+                None if sliced => continue,
+                report => return Ok(report),
+            }
+        }
+    }
+}
+
+/// Send a command through `stream` and return its result, failing with [`Error::Pigpio`] if the
+/// daemon reports an error.
+fn request(stream: &mut TcpStream, cmd: u32, p1: u32, p2: u32, extension: &[u8]) -> Result<u32> {
+    let result = exchange(stream, cmd, p1, p2, extension)?;
+    u32::try_from(result).map_err(|_| Error::Pigpio(result))
+}
+
+/// Send a command through `stream` and return its raw result.
+fn exchange(stream: &mut TcpStream, cmd: u32, p1: u32, p2: u32, extension: &[u8]) -> Result<i32> {
+    let mut message = Vec::with_capacity(16 + extension.len());
+    for word in [cmd, p1, p2, extension.len() as u32] {
+        message.extend_from_slice(&word.to_le_bytes());
+    }
+    message.extend_from_slice(extension);
+    stream.write_all(&message)?;
+
+    let mut response = [0; 16];
+    stream.read_exact(&mut response)?;
+    let result = i32::from_le_bytes([response[12], response[13], response[14], response[15]]);
+
+    Ok(result)
+}
+
+/// Whether the daemon's `tick` precedes `other`, accounting for ticks wrapping around.
+fn before(tick: u32, other: u32) -> bool {
+    (tick.wrapping_sub(other) as i32) < 0
+}
+
+impl Backend for Pigpio {
+    fn ping(&mut self, timing: &Timing) -> Result<Option<Duration>> {
+        self.ping_cancellable(timing, &CancelToken::new())
+    }
+
+    /// Ping as [`Backend::ping`] does, checking for cancellation at least every few milliseconds
+    /// while waiting for level change reports.
+    fn ping_cancellable(
+        &mut self,
+        timing: &Timing,
+        cancel: &CancelToken,
+    ) -> Result<Option<Duration>> {
+        let width = u32::try_from(timing.trigger_pulse.as_micros()).unwrap_or(u32::MAX);
+        let trigger = self.tick()?;
+        request(
+            &mut self.commands,
+            command::TRIG,
+            self.trig.into(),
+            width,
+            &1_u32.to_le_bytes(),
+        )?;
+
+        // Wait for the **ECHO** pin to go high, skipping late reports of previous pings.
+        let deadline = timing
+            .start_timeout
+            .map(|timeout| Instant::now() + timeout + LATENCY_MARGIN);
+        let start = loop {
+            match self.report_cancellable(deadline, cancel)? {
+                Some((tick, true)) if !before(tick, trigger) => break tick,
+                Some(_) => continue,
+                None => return Err(Error::EchoStartTimeout),
+            }
+        };
+
+        // Wait for the **ECHO** pin to go low.
+        let deadline = Instant::now() + timing.pulse_timeout + LATENCY_MARGIN;
+        let end = loop {
+            match self.report_cancellable(Some(deadline), cancel)? {
+                Some((tick, false)) => break tick,
+                Some((_, true)) => continue,
+                // Timeout reached: object out of range (distance > maximum range).
+                None => return Ok(None),
+            }
+        };
+
+        // Ticks wrap around roughly every 72 minutes.
+        let width = Duration::from_micros(end.wrapping_sub(start).into());
+        Ok(Some(width).filter(|width| *width < timing.pulse_timeout))
+    }
+}
@@ -0,0 +1,433 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+use super::{Backend, Timing};
+use crate::{
+    cancel::CHECK_INTERVAL,
+    error::Error,
+    portable::{Echo, EchoCapture},
+    CancelToken, Result,
+};
+use rppal::gpio::{InputPin, IoPin, Mode, OutputPin, Trigger};
+use std::{
+    hint, thread,
+    time::{Duration, Instant},
+};
+
+/// Timing strategy of the [`Rppal`] backend (defaults to [`Precision::Interrupt`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Precision {
+    /// Sleep for the **TRIGGER** pulse and wait for **ECHO** pin interrupts, leaving the CPU
+    /// idle in the meantime. The pulse width is taken from the kernel timestamps of the edge
+    /// events, so it isn't affected by scheduling latency.
+    #[default]
+    Interrupt,
+    /// Spin for the **TRIGGER** pulse and busy poll the **ECHO** pin level, keeping a CPU core
+    /// busy for the whole measurement in exchange for tighter pulse width resolution.
+    BusyWait,
+    /// Wait for the **ECHO** pin rising edge interrupt, leaving the CPU idle until the pulse
+    /// starts, then busy poll the pin level for the falling edge, keeping a CPU core busy for
+    /// the pulse only (at most the **ECHO** pulse timeout, *~23ms* at maximum range). The pulse
+    /// end isn't delayed by a second interrupt wake-up, which matters most for short distances.
+    Hybrid,
+}
+
+impl Precision {
+    /// Whether **ECHO** pin interrupts are registered.
+    pub(crate) fn uses_interrupt(self) -> bool {
+        self != Self::BusyWait
+    }
+}
+
+/// Current `CLOCK_MONOTONIC` time, which GPIO edge event timestamps are taken from.
+fn monotonic_now() -> Duration {
+    let mut now = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // Cannot fail with a valid clock and pointer.
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now);
+    }
+    Duration::new(now.tv_sec as u64, now.tv_nsec as u32)
+}
+
+/// GPIO pins the sensor is wired to.
+#[derive(Debug)]
+enum Wiring {
+    /// **TRIGGER** output pin and **ECHO** input pin, with interrupt registered for both edges
+    /// unless busy polling.
+    Dual { trig: OutputPin, echo: InputPin },
+    /// Single pin shared by **TRIGGER** and **ECHO**, switched to output for the **TRIGGER**
+    /// pulse and back to input for the **ECHO** pulse.
+    Single(IoPin),
+}
+
+impl Wiring {
+    /// Drive the **TRIGGER** pin high for `width`, spinning rather than sleeping.
+    fn trigger(&mut self, width: Duration) {
+        let spin = || {
+            let trigger = Instant::now();
+            while trigger.elapsed() < width {
+                hint::spin_loop();
+            }
+        };
+        match self {
+            Self::Dual { trig, .. } => {
+                trig.set_high();
+                spin();
+                trig.set_low();
+            }
+            Self::Single(pin) => {
+                pin.set_mode(Mode::Output);
+                pin.set_high();
+                spin();
+                pin.set_low();
+                pin.set_mode(Mode::Input);
+            }
+        }
+    }
+
+    /// Whether the **ECHO** pin level is high.
+    fn is_echo_high(&self) -> bool {
+        match self {
+            Self::Dual { echo, .. } => echo.is_high(),
+            Self::Single(pin) => pin.is_high(),
+        }
+    }
+}
+
+/// Time the sensor is left unpowered while power-cycling it, for its supply to discharge.
+const POWER_OFF: Duration = Duration::from_millis(100);
+
+/// Power-enable output switching the sensor's supply, e.g. through a MOSFET, letting the
+/// [`Rppal`] backend power-cycle the sensor to recover from lock-ups and power it down between
+/// measurements to save battery.
+///
+/// The sensor is left unpowered until the first measurement (or [`Backend::set_powered`]),
+/// which waits for it to warm up first.
+///
+/// # Fields
+///
+/// - `pin`: power-enable output pin
+/// - `active_low`: whether the sensor is powered with the pin driven low rather than high
+/// - `warm_up`: time the sensor takes to become operational after power-on
+/// - `on`: whether the sensor is powered
+#[derive(Debug)]
+pub struct PowerGate {
+    pin: OutputPin,
+    active_low: bool,
+    warm_up: Duration,
+    on: bool,
+}
+
+impl PowerGate {
+    /// Default time the sensor takes to become operational after power-on.
+    pub const WARM_UP: Duration = Duration::from_millis(100);
+
+    /// Switch the sensor's supply through `pin`, powering it with the pin driven high (e.g.
+    /// through an N-channel MOSFET on the low side), leaving the sensor unpowered.
+    pub fn new(mut pin: OutputPin) -> Self {
+        pin.set_low();
+        Self {
+            pin,
+            active_low: false,
+            warm_up: Self::WARM_UP,
+            on: false,
+        }
+    }
+
+    /// Whether the sensor is powered with the pin driven low instead (disabled by default), e.g.
+    /// through a P-channel MOSFET on the high side. Drives the pin to the new off level.
+    pub fn active_low(mut self, active_low: bool) -> Self {
+        self.active_low = active_low;
+        self.drive(false);
+        self
+    }
+
+    /// Time the sensor takes to become operational after power-on (defaults to
+    /// [`PowerGate::WARM_UP`]).
+    pub fn warm_up(mut self, delay: Duration) -> Self {
+        self.warm_up = delay;
+        self
+    }
+
+    /// Whether the sensor is powered.
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    /// Power the sensor on, waiting for it to warm up unless it already was, or off.
+    pub fn set(&mut self, on: bool) {
+        if on == self.on {
+            return;
+        }
+        self.drive(on);
+        if on {
+            thread::sleep(self.warm_up);
+        }
+    }
+
+    /// Drive the pin to the on level if `on`, to the off level otherwise.
+    fn drive(&mut self, on: bool) {
+        if on != self.active_low {
+            self.pin.set_high();
+        } else {
+            self.pin.set_low();
+        }
+        self.on = on;
+    }
+}
+
+/// *Raspberry Pi* [`Backend`] using [rppal](https://docs.rs/rppal) GPIO interrupts, or busy
+/// polling (see [`Precision`]).
+///
+/// Sensors sharing one pin for **TRIGGER** and **ECHO** (e.g. Parallax PING))), SRF05 in mode 2
+/// or Grove Ultrasonic Ranger) are supported as well, always busy polling.
+///
+/// # Fields
+///
+/// - `wiring`: GPIO pins the sensor is wired to
+/// - `precision`: timing strategy
+/// - `power`: power-enable output switching the sensor's supply, if any
+#[derive(Debug)]
+pub struct Rppal {
+    wiring: Wiring,
+    precision: Precision,
+    power: Option<PowerGate>,
+}
+
+impl Rppal {
+    /// Create the backend from `trig` (**TRIGGER**) and `echo` (**ECHO**) pins. Unless busy
+    /// polling, the caller is responsible for registering GPIO interrupt for both edges on
+    /// `echo`.
+    pub(crate) fn new(trig: OutputPin, echo: InputPin, precision: Precision) -> Self {
+        Self {
+            wiring: Wiring::Dual { trig, echo },
+            precision,
+            power: None,
+        }
+    }
+
+    /// Create the backend from the `pin` shared by **TRIGGER** and **ECHO**, already in input
+    /// mode with its output level set low.
+    pub(crate) fn single(pin: IoPin) -> Self {
+        Self {
+            wiring: Wiring::Single(pin),
+            precision: Precision::BusyWait,
+            power: None,
+        }
+    }
+
+    /// Create the backend from already set up `trig` (**TRIGGER**) and `echo` (**ECHO**) pins,
+    /// registering GPIO interrupt for both edges on `echo`.
+    pub fn from_pins(trig: OutputPin, mut echo: InputPin) -> Result<Self> {
+        echo.set_interrupt(Trigger::Both, None)?;
+        Ok(Self::new(trig, echo, Precision::Interrupt))
+    }
+
+    /// Switch the sensor's supply through `power`, power-cycling the sensor on recovery (see
+    /// [`Backend::recover`]) and allowing to power it down (see [`Backend::set_powered`]).
+    pub fn with_power(mut self, power: PowerGate) -> Self {
+        self.power = Some(power);
+        self
+    }
+
+    /// **TRIGGER** and **ECHO** GPIO pin numbers (the same one for single pin sensors).
+    pub fn pins(&self) -> (u8, u8) {
+        match &self.wiring {
+            Wiring::Dual { trig, echo } => (trig.pin(), echo.pin()),
+            Wiring::Single(pin) => (pin.pin(), pin.pin()),
+        }
+    }
+
+    /// Drive the **TRIGGER** pin low and, unless busy polling, clear and re-register the **ECHO**
+    /// pin interrupt, discarding any stale edge. Single pins are switched back to input.
+    fn reset_pins(&mut self) -> Result<()> {
+        match &mut self.wiring {
+            Wiring::Dual { trig, echo } => {
+                trig.set_low();
+                if self.precision.uses_interrupt() {
+                    echo.clear_interrupt()?;
+                    echo.set_interrupt(Trigger::Both, None)?;
+                }
+            }
+            Wiring::Single(pin) => {
+                pin.set_low();
+                pin.set_mode(Mode::Input);
+            }
+        }
+        Ok(())
+    }
+
+    /// Busy poll the **ECHO** pin level, as per [`Precision::BusyWait`].
+    fn ping_busy(&mut self, timing: &Timing, cancel: &CancelToken) -> Result<Option<Duration>> {
+        self.wiring.trigger(timing.trigger_pulse);
+
+        // Wait for the **ECHO** pin to go high, then low.
+        let origin = Instant::now();
+        let mut capture = EchoCapture::new(timing, Duration::ZERO);
+        loop {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            let high = self.wiring.is_echo_high();
+            match capture.poll(origin.elapsed(), high) {
+                Some(Echo::Pulse(width)) => return Ok(Some(width)),
+                Some(Echo::OutOfRange) => return Ok(None),
+                Some(Echo::NoStart) => return Err(Error::EchoStartTimeout),
+                None => {}
+            }
+        }
+    }
+}
+
+impl Drop for Rppal {
+    /// Drive the **TRIGGER** pin low and clear the **ECHO** pin interrupt, before the pins are
+    /// reset to their previous mode.
+    fn drop(&mut self) {
+        match &mut self.wiring {
+            Wiring::Dual { trig, echo } => {
+                trig.set_low();
+                // Nothing can be done on failure, the pin is released anyway.
+                let _ = echo.clear_interrupt();
+            }
+            Wiring::Single(pin) => pin.set_low(),
+        }
+    }
+}
+
+impl Backend for Rppal {
+    fn ping(&mut self, timing: &Timing) -> Result<Option<Duration>> {
+        self.ping_cancellable(timing, &CancelToken::new())
+    }
+
+    /// Ping as [`Backend::ping`] does, checking for cancellation while busy polling, or at least
+    /// every few milliseconds while waiting for interrupts.
+    fn ping_cancellable(
+        &mut self,
+        timing: &Timing,
+        cancel: &CancelToken,
+    ) -> Result<Option<Duration>> {
+        let (trig, echo) = match &mut self.wiring {
+            Wiring::Dual { trig, echo } if self.precision.uses_interrupt() => (trig, echo),
+            _ => return self.ping_busy(timing, cancel),
+        };
+
+        trig.set_high();
+        thread::sleep(timing.trigger_pulse);
+        trig.set_low();
+
+        // Wait for the `RisingEdge`, skipping stale falling edges.
+        let trigger = Instant::now();
+        let rising = loop {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            let remaining = match timing.start_timeout {
+                Some(timeout) => timeout
+                    .checked_sub(trigger.elapsed())
+                    .ok_or(Error::EchoStartTimeout)?,
+                None => CHECK_INTERVAL,
+            };
+            match echo.poll_interrupt(false, Some(remaining.min(CHECK_INTERVAL)))? {
+                Some(event) if event.trigger == Trigger::RisingEdge => break event.timestamp,
+                // Stale falling edge, or no edge yet.
+                _ => continue,
+            }
+        };
+        let start = Instant::now();
+        if self.precision == Precision::Hybrid {
+            // The pulse may already be over by the time this thread got scheduled again.
+            if let Some(event) = echo.poll_interrupt(false, Some(Duration::ZERO))? {
+                if event.trigger == Trigger::FallingEdge {
+                    return Ok(Some(event.timestamp.saturating_sub(rising)));
+                }
+            }
+            // Busy poll the level for the `FallingEdge`, leaving its event to be skipped as stale
+            // on the next ping.
+            while echo.is_high() {
+                if cancel.is_cancelled() {
+                    return Err(Error::Cancelled);
+                }
+                if start.elapsed() >= timing.pulse_timeout {
+                    // Timeout reached: object out of range (distance > maximum range).
+                    return Ok(None);
+                }
+                hint::spin_loop();
+            }
+            return Ok(Some(monotonic_now().saturating_sub(rising)));
+        }
+
+        // Wait for the `FallingEdge`, timing the pulse from the kernel event timestamps rather
+        // than from when this thread gets scheduled again.
+        let falling = loop {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            let remaining = match timing.pulse_timeout.checked_sub(start.elapsed()) {
+                Some(remaining) => remaining,
+                // Timeout reached: object out of range (distance > maximum range).
+                None => return Ok(None),
+            };
+            match echo.poll_interrupt(false, Some(remaining.min(CHECK_INTERVAL)))? {
+                Some(event) if event.trigger == Trigger::FallingEdge => break event.timestamp,
+                Some(_) => return Ok(None),
+                None => continue,
+            }
+        };
+
+        Ok(Some(falling.saturating_sub(rising)))
+    }
+
+    /// Drive the **TRIGGER** pin low and, unless busy polling, clear and re-register the **ECHO**
+    /// pin interrupt, discarding any stale edge. Single pins are switched back to input.
+    ///
+    /// With a [`PowerGate`], the sensor is power-cycled as well, waiting for it to warm up.
+    fn recover(&mut self) -> Result<()> {
+        if let Some(power) = &mut self.power {
+            power.set(false);
+            thread::sleep(POWER_OFF);
+            power.set(true);
+        }
+        self.reset_pins()
+    }
+
+    /// Power the sensor on or off through the [`PowerGate`], if any, discarding the **ECHO**
+    /// edges caused by powering it on.
+    fn set_powered(&mut self, on: bool) -> Result<()> {
+        let power = match &mut self.power {
+            Some(power) if power.is_on() != on => power,
+            _ => return Ok(()),
+        };
+        power.set(on);
+        self.reset_pins()
+    }
+
+    fn is_powered(&self) -> Option<bool> {
+        self.power.as_ref().map(PowerGate::is_on)
+    }
+
+    fn set_warm_up(&mut self, delay: Duration) {
+        if let Some(power) = &mut self.power {
+            power.warm_up = delay;
+        }
+    }
+
+    fn echo_level(&mut self) -> Option<bool> {
+        Some(self.wiring.is_echo_high())
+    }
+}
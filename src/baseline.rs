@@ -0,0 +1,167 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Baseline capture and delta-from-baseline measurement.
+//!
+//! Presence and level applications care about how much the scene changed rather than about the
+//! absolute distance. [`HcSr04::capture_baseline`] measures the empty scene (e.g. the floor below
+//! the sensor, or an empty tank), after which [`HcSr04::measure_delta`] returns the deviation of
+//! each measurement from it.
+//!
+//! ```rust,no_run
+//! use hc_sr04::HcSr04;
+//!
+//! let mut ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//!
+//! // Keep the scene empty while capturing.
+//! let baseline = ultrasonic.capture_baseline(20).unwrap();
+//! println!("Baseline: {:?}", baseline);
+//!
+//! if let Some(delta) = ultrasonic.measure_delta().unwrap() {
+//!     if delta.as_meters() < -0.1 {
+//!         println!("Something is {:.1}cm closer than usual", -delta.as_cm());
+//!     }
+//! }
+//! ```
+//!
+//! The empty-scene distance slowly drifts with the ambient temperature. An [`Adaptation`] lets
+//! the baseline follow the drift: readings within a threshold of the baseline, i.e. with no
+//! object present, nudge it towards them, while readings deviating more leave it frozen, so that
+//! objects lingering in front of the sensor aren't absorbed into the background.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{baseline::Adaptation, Distance, HcSr04, Unit};
+//!
+//! let mut ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//! ultrasonic.capture_baseline(20).unwrap();
+//!
+//! let adaptation = Adaptation::new(0.01, Distance::new(5., Unit::Centimeters));
+//! ultrasonic.set_baseline_adaptation(Some(adaptation));
+//!
+//! loop {
+//!     if let Some(delta) = ultrasonic.measure_delta().unwrap() {
+//!         println!("Present: {}", adaptation.is_present(delta));
+//!     }
+//! }
+//! ```
+
+use crate::{backend::Backend, error::Error, Distance, Float, HcSr04, Result};
+
+/// Settings of the adaptive background model (see [`HcSr04::set_baseline_adaptation`]).
+///
+/// # Fields
+///
+/// - `rate`: fraction of the deviation the baseline moves by on every reading with no object
+///   present, in `[0, 1]`
+/// - `threshold`: deviation from the baseline beyond which an object is present, freezing the
+///   baseline
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Adaptation {
+    pub rate: Float,
+    pub threshold: Distance,
+}
+
+impl Adaptation {
+    /// Create new settings moving the baseline by `rate` (clamped to `[0, 1]`) of the deviation
+    /// of readings within `threshold` of it: the lower `rate`, the slower the baseline follows
+    /// drift.
+    pub fn new(rate: Float, threshold: Distance) -> Self {
+        Self {
+            rate: rate.clamp(0., 1.),
+            threshold,
+        }
+    }
+
+    /// Whether the `delta` from the baseline means an object is present.
+    pub fn is_present(&self, delta: Distance) -> bool {
+        delta.abs_diff(Distance::from_meters(0.)) > self.threshold
+    }
+}
+
+impl<B: Backend> HcSr04<B> {
+    /// Capture the baseline, i.e. the distance measured in the empty scene, as the median of
+    /// `samples` **distance measurements** (see [`HcSr04::measure`]).
+    ///
+    /// Measurements with no object in range are left out. Returns the captured baseline, or
+    /// `None` (clearing the baseline) if no measurement found an object in range. Fails on the
+    /// first measurement returning an error.
+    pub fn capture_baseline(&mut self, samples: usize) -> Result<Option<Distance>> {
+        let mut distances = Vec::with_capacity(samples);
+        for _ in 0..samples {
+            if let Some(distance) = self.measure()? {
+                distances.push(distance.as_meters());
+            }
+        }
+
+        distances.sort_by(Float::total_cmp);
+        let mid = distances.len() / 2;
+        let baseline = match distances.len() {
+            0 => None,
+            len if len % 2 == 0 => Some((distances[mid - 1] + distances[mid]) / 2.),
+            _ => Some(distances[mid]),
+        };
+
+        self.baseline = baseline.map(Distance::from_meters);
+        Ok(self.baseline)
+    }
+
+    /// Baseline distance, if captured (see [`HcSr04::capture_baseline`]).
+    pub fn baseline(&self) -> Option<Distance> {
+        self.baseline
+    }
+
+    /// Set (or clear, if `None`) the baseline distance directly, e.g. restoring a previously
+    /// captured one.
+    pub fn set_baseline(&mut self, baseline: Option<Distance>) {
+        self.baseline = baseline;
+    }
+
+    /// Perform **distance measurement** (see [`HcSr04::measure`]), returning the deviation from
+    /// the baseline: negative when something is closer than the empty scene, positive when
+    /// farther (e.g. a draining tank).
+    ///
+    /// With an adaptation set (see [`HcSr04::set_baseline_adaptation`]), readings with no object
+    /// present move the baseline towards them, after computing the deviation.
+    ///
+    /// Returns `None` if no object is in range, and fails with [`Error::NoBaseline`] if no
+    /// baseline was captured.
+    pub fn measure_delta(&mut self) -> Result<Option<Distance>> {
+        let baseline = self.baseline.ok_or(Error::NoBaseline)?;
+        let delta = match self.measure()? {
+            Some(distance) => distance - baseline,
+            None => return Ok(None),
+        };
+
+        if let Some(adaptation) = self.adaptation {
+            if !adaptation.is_present(delta) {
+                self.baseline = Some(baseline + delta * adaptation.rate);
+            }
+        }
+        Ok(Some(delta))
+    }
+
+    /// Let the baseline slowly adapt to drift in the empty scene as per `adaptation`, or keep it
+    /// fixed if `None` (the default).
+    pub fn set_baseline_adaptation(&mut self, adaptation: Option<Adaptation>) {
+        self.adaptation = adaptation;
+    }
+
+    /// Adaptive background model settings, if set.
+    pub fn baseline_adaptation(&self) -> Option<Adaptation> {
+        self.adaptation
+    }
+}
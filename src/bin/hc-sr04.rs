@@ -0,0 +1,177 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Command line interface to the HC-SR04 sensor, available with the `cli` feature.
+
+use clap::{Parser, Subcommand, ValueEnum};
+use hc_sr04::{calibration::Correction, signal::Shutdown, Distance, Float, HcSr04, Result, Unit};
+use std::{
+    process::ExitCode,
+    time::{Duration, UNIX_EPOCH},
+};
+
+/// Measure distance with the HC-SR04 ultrasonic sensor wired to Raspberry Pi GPIO pins.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Args {
+    /// TRIGGER output GPIO pin.
+    #[arg(long, default_value = "24", global = true)]
+    trig: u8,
+    /// ECHO input GPIO pin.
+    #[arg(long, default_value = "23", global = true)]
+    echo: u8,
+    /// Measuring unit.
+    #[arg(long, value_enum, default_value = "m", global = true)]
+    unit: CliUnit,
+    /// Ambient temperature in Celsius degrees used for calibration.
+    #[arg(long, global = true)]
+    temp: Option<Float>,
+    /// TRIGGER pulse width in µs.
+    #[arg(long, default_value = "10", global = true)]
+    trigger_pulse: u64,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Perform a single measurement, failing if no object is in range.
+    Measure,
+    /// Measure continuously, printing the UNIX timestamp and the distance (`-` if no object is
+    /// in range) on every line.
+    Monitor {
+        /// Interval between measurements in ms.
+        #[arg(long, default_value = "1000")]
+        interval: u64,
+    },
+    /// Print the speed of sound given the ambient temperature and, if a reference distance is
+    /// given, the offset correcting measurements of a target placed at that distance.
+    Calibrate {
+        /// Distance of a reference target, expressed in the measuring unit.
+        #[arg(long)]
+        reference: Option<Float>,
+        /// Number of measurements averaged against the reference distance.
+        #[arg(long, default_value = "10")]
+        samples: u32,
+    },
+}
+
+/// Measuring unit flag values.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum CliUnit {
+    Mm,
+    Cm,
+    Dm,
+    M,
+    In,
+    Ft,
+    Yd,
+}
+
+impl From<CliUnit> for Unit {
+    fn from(unit: CliUnit) -> Self {
+        match unit {
+            CliUnit::Mm => Unit::Millimeters,
+            CliUnit::Cm => Unit::Centimeters,
+            CliUnit::Dm => Unit::Decimeters,
+            CliUnit::M => Unit::Meters,
+            CliUnit::In => Unit::Inches,
+            CliUnit::Ft => Unit::Feet,
+            CliUnit::Yd => Unit::Yards,
+        }
+    }
+}
+
+fn run(args: Args) -> Result<ExitCode> {
+    let unit = Unit::from(args.unit);
+    let builder = HcSr04::builder(args.trig, args.echo)
+        .unit(unit)
+        .trigger_pulse(Duration::from_micros(args.trigger_pulse));
+    let mut ultrasonic = match args.temp {
+        Some(temp) => builder.temperature(temp),
+        None => builder,
+    }
+    .build()?;
+
+    match args.command {
+        Command::Measure => match ultrasonic.measure_distance(unit)? {
+            Some(dist) => println!("{:.3}", dist),
+            None => {
+                eprintln!("object out of range");
+                return Ok(ExitCode::FAILURE);
+            }
+        },
+        Command::Monitor { interval } => {
+            // Stop on SIGINT/SIGTERM, releasing the pins.
+            let mut shutdown = Shutdown::install()?;
+            let interval = Duration::from_millis(interval);
+            let worker = ultrasonic.spawn_continuous(interval, move |measurement| {
+                let measurement = match measurement {
+                    Ok(measurement) => measurement,
+                    Err(_) => return false,
+                };
+                let timestamp = measurement
+                    .timestamp
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs_f64();
+                match measurement.distance {
+                    Some(dist) => println!("{:.3} {:.3}", timestamp, dist.as_unit(unit)),
+                    None => println!("{:.3} -", timestamp),
+                }
+                true
+            });
+
+            if let Some(error) = worker.stop_on(&mut shutdown).last_error {
+                return Err(error);
+            }
+        }
+        Command::Calibrate { reference, samples } => {
+            println!("speed of sound: {:.2}m/s", ultrasonic.sound_speed());
+
+            if let Some(reference) = reference {
+                let mut measured = Vec::new();
+                for _ in 0..samples.max(1) {
+                    if let Some(dist) = ultrasonic.measure_distance(Unit::Meters)? {
+                        measured.push(dist);
+                    }
+                }
+                if measured.is_empty() {
+                    eprintln!("reference target out of range");
+                    return Ok(ExitCode::FAILURE);
+                }
+
+                let reference = Distance::new(reference, unit).as_meters();
+                let mean = measured.iter().sum::<Float>() / measured.len() as Float;
+                if let Some(Correction { offset, .. }) = Correction::fit(&[(mean, reference)]) {
+                    println!("offset: {:.3}", Distance::from_meters(offset).as_unit(unit));
+                }
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
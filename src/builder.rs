@@ -0,0 +1,627 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Builder-style configuration of the [`HcSr04`] sensor.
+
+#[cfg(feature = "profile")]
+use crate::profile::CalibrationProfile;
+use crate::{
+    backend::{Backend, Timing},
+    calibration::{self, Correction},
+    filter::{DistanceFilter, Pipeline},
+    model::Model,
+    portable::echo_timeout,
+    recent::RecentReadings,
+    Float, HcSr04, Unit,
+};
+#[cfg(feature = "rppal")]
+use crate::{
+    backend::{PowerGate, Precision, Rppal},
+    Result,
+};
+#[cfg(feature = "rppal")]
+use rppal::gpio::{Bias, Gpio, Mode, Trigger};
+use std::time::Duration;
+
+/// Default ambient temperature in C.
+pub(crate) const DEFAULT_TEMPERATURE: Float = 20.0;
+
+/// Ensure the maximum measuring `range` is positive and finite.
+pub(crate) fn assert_max_range(range: Float) {
+    assert!(
+        range.is_finite() && range > 0.,
+        "maximum measuring range must be positive and finite, got {}",
+        range
+    );
+}
+
+/// Ensure the minimum measuring `range` is non-negative and finite.
+pub(crate) fn assert_min_range(range: Float) {
+    assert!(
+        range.is_finite() && range >= 0.,
+        "minimum measuring range must be non-negative and finite, got {}",
+        range
+    );
+}
+
+/// Ensure the speed of sound `speed` is positive and finite.
+pub(crate) fn assert_sound_speed(speed: Float) {
+    assert!(
+        speed.is_finite() && speed > 0.,
+        "speed of sound must be positive and finite, got {}",
+        speed
+    );
+}
+
+/// Pull resistor configuration of the **ECHO** input pin (defaults to [`Pull::Down`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pull {
+    /// Enable the built-in pull-down resistor.
+    #[default]
+    Down,
+    /// Enable the built-in pull-up resistor.
+    Up,
+    /// Leave the pin floating (e.g. when an external resistor is used).
+    Off,
+}
+
+/// *Raspberry Pi* GPIO pins the [`Rppal`] backend is built from.
+///
+/// # Fields
+///
+/// - `trig`: **TRIGGER** output GPIO pin
+/// - `echo`: **ECHO** input GPIO pin (the same as `trig` for single pin sensors)
+/// - `pull`: **ECHO** pin pull resistor configuration
+/// - `precision`: timing strategy of the backend
+/// - `power`: power-enable output GPIO pin switching the sensor's supply, if any
+/// - `power_active_low`: whether the sensor is powered with the `power` pin driven low
+/// - `warm_up`: time the sensor takes to become operational after power-on
+#[cfg(feature = "rppal")]
+#[derive(Debug, Clone, Copy)]
+pub struct Pins {
+    trig: u8,
+    echo: u8,
+    pull: Pull,
+    precision: Precision,
+    power: Option<u8>,
+    power_active_low: bool,
+    warm_up: Duration,
+}
+
+/// Builder for [`HcSr04`] sensor, obtained through [`HcSr04::builder`] for *Raspberry Pi* GPIO
+/// pins or through [`HcSr04Builder::with_backend`] for any other [`Backend`].
+///
+/// Every option not explicitly set falls back to the sensor's default.
+#[derive(Debug)]
+pub struct HcSr04Builder<P> {
+    pins: P,
+    trigger_pulse: Duration,
+    echo_start_timeout: Option<Duration>,
+    echo_timeout: Option<Duration>,
+    echo_timeout_error: bool,
+    min_cycle: Duration,
+    min_cycle_error: bool,
+    jitter: Option<Duration>,
+    watchdog: Option<u32>,
+    disconnect_detection: Option<u32>,
+    recent: Option<usize>,
+    max_range: Float,
+    min_range: Float,
+    unit: Unit,
+    filter: Pipeline,
+    temp: Float,
+    relative_humidity: Option<Float>,
+    pressure: Option<Float>,
+    sound_speed: Option<Float>,
+    correction: Correction,
+    #[cfg(feature = "realtime")]
+    realtime: Option<i32>,
+}
+
+impl<P> HcSr04Builder<P> {
+    /// Create a new builder with default options, measuring through `pins`.
+    fn with_pins(pins: P) -> Self {
+        let spec = Model::default().spec();
+        Self {
+            pins,
+            trigger_pulse: spec.trigger_pulse,
+            echo_start_timeout: None,
+            echo_timeout: None,
+            echo_timeout_error: false,
+            min_cycle: spec.min_cycle,
+            min_cycle_error: false,
+            jitter: None,
+            watchdog: None,
+            disconnect_detection: None,
+            recent: None,
+            max_range: spec.max_range,
+            min_range: spec.min_range,
+            unit: Unit::default(),
+            filter: Pipeline::new(),
+            temp: DEFAULT_TEMPERATURE,
+            relative_humidity: None,
+            pressure: None,
+            sound_speed: None,
+            correction: Correction::IDENTITY,
+            #[cfg(feature = "realtime")]
+            realtime: None,
+        }
+    }
+
+    /// Sensor model (defaults to [`Model::HcSr04`]), setting the **TRIGGER** pulse width, the
+    /// minimum measurement cycle and the measuring range as per its specification. Options set
+    /// afterwards override the model's values.
+    ///
+    /// ```rust,no_run
+    /// use hc_sr04::{model::Model, HcSr04};
+    ///
+    /// let mut ultrasonic = HcSr04::builder(24, 23)
+    ///     .model(Model::JsnSr04t)
+    ///     .max_range(4.5)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn model(mut self, model: Model) -> Self {
+        let spec = model.spec();
+        self.trigger_pulse = spec.trigger_pulse;
+        self.min_cycle = spec.min_cycle;
+        self.min_range = spec.min_range;
+        self.max_range = spec.max_range;
+        self
+    }
+
+    /// Width of the **TRIGGER** pulse (defaults to *10µs*, as per HC-SR04 specification).
+    ///
+    /// Some sensor clones and setups with long cables need longer pulses to start measuring.
+    pub fn trigger_pulse(mut self, width: Duration) -> Self {
+        self.trigger_pulse = width;
+        self
+    }
+
+    /// Maximum time to wait for the **ECHO** pulse to start after the **TRIGGER** pulse, after
+    /// which measurement fails with [`Error::EchoStartTimeout`] (waits indefinitely by default).
+    ///
+    /// [`Error::EchoStartTimeout`]: crate::error::Error::EchoStartTimeout
+    pub fn echo_start_timeout(mut self, timeout: Duration) -> Self {
+        self.echo_start_timeout = Some(timeout);
+        self
+    }
+
+    /// Maximum time to wait for the **ECHO** pulse to end after it started. If not set, the
+    /// timeout is computed from the maximum measuring range and the speed of sound given the
+    /// ambient temperature.
+    pub fn echo_timeout(mut self, timeout: Duration) -> Self {
+        self.echo_timeout = Some(timeout);
+        self
+    }
+
+    /// Report **ECHO** pulses not ending within the timeout as [`Error::EchoPulseTimeout`] rather
+    /// than objects out of range (disabled by default).
+    ///
+    /// [`Error::EchoPulseTimeout`]: crate::error::Error::EchoPulseTimeout
+    pub fn echo_timeout_error(mut self, enabled: bool) -> Self {
+        self.echo_timeout_error = enabled;
+        self
+    }
+
+    /// Minimum time between consecutive **TRIGGER** pulses (defaults to *60ms*, as per HC-SR04
+    /// specification), letting residual echoes of the previous measurement fade out.
+    ///
+    /// Measuring sooner waits for the remaining time first, unless reporting it as an error is
+    /// enabled (see [`HcSr04Builder::min_cycle_error`]).
+    pub fn min_cycle(mut self, cycle: Duration) -> Self {
+        self.min_cycle = cycle;
+        self
+    }
+
+    /// Report measurements attempted before the minimum cycle elapsed as
+    /// [`Error::CycleTooShort`] rather than waiting (disabled by default).
+    ///
+    /// [`Error::CycleTooShort`]: crate::error::Error::CycleTooShort
+    pub fn min_cycle_error(mut self, enabled: bool) -> Self {
+        self.min_cycle_error = enabled;
+        self
+    }
+
+    /// Spread the gap between consecutive pings in continuous mode (see the
+    /// [`continuous`](crate::continuous) module) at random by up to `max` either way (disabled by
+    /// default), so that independent devices measuring the same area at the same interval don't
+    /// phase-lock and systematically read each other's echoes.
+    pub fn jitter(mut self, max: Duration) -> Self {
+        self.jitter = Some(max);
+        self
+    }
+
+    /// Attempt to recover the sensor (see [`Backend::recover`]) after `failures` consecutive
+    /// pings timing out or failing (disabled by default), e.g. from the known "**ECHO** stuck
+    /// high" lock-up, without restarting the process.
+    pub fn watchdog(mut self, failures: u32) -> Self {
+        self.watchdog = Some(failures.max(1));
+        self
+    }
+
+    /// Fail with [`Error::SensorNotResponding`] once the **ECHO** pin never left its idle level
+    /// for `pings` consecutive pings (disabled by default), rather than reporting no object in
+    /// range forever, until the sensor responds again.
+    ///
+    /// Both a missing or unpowered sensor, whose pulled down **ECHO** pulse never starts, and an
+    /// **ECHO** pin stuck high are detected: the former requires an **ECHO** start timeout (see
+    /// [`HcSr04Builder::echo_start_timeout`]), the latter a backend able to read the idle
+    /// **ECHO** level (see [`Backend::echo_level`]).
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "mock")]
+    /// # {
+    /// use hc_sr04::{error::Error, MockHcSr04};
+    ///
+    /// let mut ultrasonic = MockHcSr04::mock(None);
+    /// ultrasonic.set_disconnect_detection(Some(3));
+    /// ultrasonic.push_no_echo().push_no_echo().push_no_echo();
+    ///
+    /// assert!(matches!(ultrasonic.measure(), Err(Error::EchoStartTimeout)));
+    /// assert!(matches!(ultrasonic.measure(), Err(Error::EchoStartTimeout)));
+    /// assert!(matches!(ultrasonic.measure(), Err(Error::SensorNotResponding)));
+    ///
+    /// // Stuck high: the echo pulse never ends.
+    /// ultrasonic.set_echo_high(true).push_out_of_range();
+    /// assert!(matches!(ultrasonic.measure(), Err(Error::SensorNotResponding)));
+    /// # }
+    /// ```
+    ///
+    /// [`Error::SensorNotResponding`]: crate::error::Error::SensorNotResponding
+    pub fn disconnect_detection(mut self, pings: u32) -> Self {
+        self.disconnect_detection = Some(pings.max(1));
+        self
+    }
+
+    /// Keep the last `capacity` measurements in a ring buffer (disabled by default), queryable
+    /// through [`HcSr04::recent`] or a shared [`RecentReadings`] handle without consuming the
+    /// measurement stream.
+    pub fn recent(mut self, capacity: usize) -> Self {
+        self.recent = Some(capacity);
+        self
+    }
+
+    /// Maximum measuring range in m (defaults to *4m*, as per HC-SR04 specification).
+    ///
+    /// Unless an explicit **ECHO** timeout is set, the timeout is computed from this range: a
+    /// shorter range makes out of range measurements return sooner, while a longer one allows
+    /// long-range sensor clones to measure farther objects.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is not positive and finite.
+    pub fn max_range(mut self, range: Float) -> Self {
+        assert_max_range(range);
+        self.max_range = range;
+        self
+    }
+
+    /// Minimum measuring range in m (defaults to *2cm*, as per HC-SR04 specification), within
+    /// which the sensor returns implausibly short pulses: shorter distances fail with
+    /// [`Error::TooClose`] rather than being reported. Set to `0.0` to disable the check.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is negative or not finite.
+    ///
+    /// [`Error::TooClose`]: crate::error::Error::TooClose
+    pub fn min_range(mut self, range: Float) -> Self {
+        assert_min_range(range);
+        self.min_range = range;
+        self
+    }
+
+    /// Default measuring unit (defaults to [`Unit::Meters`]).
+    pub fn unit(mut self, unit: Unit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Filter measured distances through `filter` (no filtering by default). Calling this more
+    /// than once chains the filters, in the given order.
+    pub fn filter(mut self, filter: impl DistanceFilter + Send + 'static) -> Self {
+        self.filter.push(filter);
+        self
+    }
+
+    /// Ambient temperature in *Celsius degrees* used for calibration (defaults to `20.0`).
+    pub fn temperature(mut self, temp: Float) -> Self {
+        self.temp = temp;
+        self
+    }
+
+    /// Ambient relative humidity in percent used for calibration along with the temperature (see
+    /// [`HcSr04::calibrate_env`]). If not set, the dry air speed of sound model is used.
+    pub fn relative_humidity(mut self, relative_humidity: Float) -> Self {
+        self.relative_humidity = Some(relative_humidity);
+        self
+    }
+
+    /// Ambient barometric pressure in hPa used for calibration along with the temperature (see
+    /// [`HcSr04::calibrate_atmosphere`]). If not set, standard atmospheric pressure is assumed.
+    pub fn pressure(mut self, pressure: Float) -> Self {
+        self.pressure = Some(pressure);
+        self
+    }
+
+    /// Altitude in m above sea level, used to estimate the barometric pressure (see
+    /// [`calibration::pressure_at_altitude`]) when it is not measured directly.
+    pub fn altitude(self, altitude: Float) -> Self {
+        self.pressure(calibration::pressure_at_altitude(altitude))
+    }
+
+    /// Speed of sound in m/s, overriding the one derived from the ambient conditions (e.g.
+    /// *1480m/s* for underwater measurements with the waterproof JSN-SR04T).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `speed` is not positive and finite.
+    ///
+    /// ```rust,no_run
+    /// use hc_sr04::{model::Model, HcSr04};
+    ///
+    /// let mut ultrasonic = HcSr04::builder(24, 23)
+    ///     .model(Model::JsnSr04t)
+    ///     .sound_speed(1480.)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn sound_speed(mut self, speed: Float) -> Self {
+        assert_sound_speed(speed);
+        self.sound_speed = Some(speed);
+        self
+    }
+
+    /// Linear correction applied to measured distances (none by default), e.g. restoring one
+    /// previously fitted through [`HcSr04::calibrate_reference`].
+    pub fn correction(mut self, correction: Correction) -> Self {
+        self.correction = correction;
+        self
+    }
+
+    /// Raise the measuring thread to the `SCHED_FIFO` real-time scheduling policy with the given
+    /// `priority` (`1..=99` on Linux) while capturing the **ECHO** pulse, reducing timing jitter
+    /// caused by preemption (disabled by default). The previous policy is restored afterwards.
+    ///
+    /// Measurements fail with [`Error::Realtime`] if the policy cannot be changed, e.g. lacking
+    /// the required privileges (see the [`realtime`](crate::realtime) module).
+    ///
+    /// [`Error::Realtime`]: crate::error::Error::Realtime
+    #[cfg(feature = "realtime")]
+    pub fn realtime(mut self, priority: i32) -> Self {
+        self.realtime = Some(priority);
+        self
+    }
+
+    /// Calibrate the sensor and set its correction and filters as described by `profile`,
+    /// replacing any previously set ones.
+    #[cfg(feature = "profile")]
+    pub fn profile(mut self, profile: &CalibrationProfile) -> Self {
+        self.temp = profile.temperature;
+        self.relative_humidity = profile.relative_humidity;
+        self.pressure = profile.pressure;
+        self.sound_speed = None;
+        self.correction = profile.correction;
+        self.filter = profile.pipeline();
+        self
+    }
+
+    /// Build the sensor on top of the backend obtained from `pins`.
+    fn finish<B: Backend>(self, backend: impl FnOnce(P) -> B) -> HcSr04<B> {
+        let sound_speed = self.sound_speed.unwrap_or_else(|| {
+            calibration::sound_speed_env(self.temp, self.relative_humidity, self.pressure)
+        });
+
+        HcSr04 {
+            backend: backend(self.pins),
+            timing: Timing {
+                trigger_pulse: self.trigger_pulse,
+                start_timeout: self.echo_start_timeout,
+                pulse_timeout: self
+                    .echo_timeout
+                    .unwrap_or_else(|| echo_timeout(self.max_range, sound_speed)),
+            },
+            max_range: self.max_range,
+            min_range: self.min_range,
+            echo_timeout: self.echo_timeout,
+            echo_timeout_error: self.echo_timeout_error,
+            min_cycle: self.min_cycle,
+            min_cycle_error: self.min_cycle_error,
+            jitter: self.jitter,
+            last_ping: None,
+            watchdog: self.watchdog,
+            failures: 0,
+            disconnect_detection: self.disconnect_detection,
+            silent_pings: 0,
+            counters: Default::default(),
+            recent: self.recent.map(RecentReadings::new),
+            history: Default::default(),
+            unit: self.unit,
+            filter: self.filter,
+            sound_speed,
+            temperature: self.sound_speed.is_none().then_some(self.temp),
+            relative_humidity: self
+                .relative_humidity
+                .filter(|_| self.sound_speed.is_none()),
+            pressure: self.pressure.filter(|_| self.sound_speed.is_none()),
+            auto_calibration: None,
+            correction: self.correction,
+            baseline: None,
+            adaptation: None,
+            cancel: Default::default(),
+            #[cfg(feature = "realtime")]
+            realtime: self.realtime,
+        }
+    }
+}
+
+#[cfg(feature = "rppal")]
+impl HcSr04Builder<Pins> {
+    /// Create a new builder for the sensor wired to `trig` (**TRIGGER**) and `echo` (**ECHO**)
+    /// GPIO pins.
+    pub fn new(trig: u8, echo: u8) -> Self {
+        Self::with_pins(Pins {
+            trig,
+            echo,
+            pull: Pull::default(),
+            precision: Precision::default(),
+            power: None,
+            power_active_low: false,
+            warm_up: PowerGate::WARM_UP,
+        })
+    }
+
+    /// Create a new builder for a sensor sharing the single `pin` between **TRIGGER** and
+    /// **ECHO** (e.g. Parallax PING))), SRF05 in mode 2 or Grove Ultrasonic Ranger), switching it
+    /// to output for each **TRIGGER** pulse and back to input for the **ECHO** pulse.
+    ///
+    /// Single pin sensors are always busy polled, as per [`Precision::BusyWait`].
+    ///
+    /// ```rust,no_run
+    /// use hc_sr04::{HcSr04Builder, Unit};
+    ///
+    /// let mut ultrasonic = HcSr04Builder::single_pin(17).build().unwrap();
+    /// match ultrasonic.measure_distance(Unit::Centimeters).unwrap() {
+    ///     Some(dist) => println!("Distance: {:.1}cm", dist),
+    ///     None => println!("Object out of range"),
+    /// }
+    /// ```
+    pub fn single_pin(pin: u8) -> Self {
+        Self::new(pin, pin)
+    }
+
+    /// Pull resistor configuration of the **ECHO** pin (defaults to [`Pull::Down`]).
+    pub fn pull(mut self, pull: Pull) -> Self {
+        self.pins.pull = pull;
+        self
+    }
+
+    /// Timing strategy of the backend (defaults to [`Precision::Interrupt`]).
+    ///
+    /// [`Precision::BusyWait`] trades a fully busy CPU core during measurements for tighter
+    /// pulse width resolution, e.g. on setups where interrupt latency adds too much noise, while
+    /// [`Precision::Hybrid`] only keeps it busy for the **ECHO** pulse.
+    pub fn precision(mut self, precision: Precision) -> Self {
+        self.pins.precision = precision;
+        self
+    }
+
+    /// Switch the sensor's supply through the `pin` power-enable output (e.g. driving a MOSFET),
+    /// powered with the pin driven high unless [`HcSr04Builder::power_active_low`].
+    ///
+    /// The watchdog (see [`HcSr04Builder::watchdog`]) then power-cycles the sensor to recover
+    /// from lock-ups, and the sensor can be powered down between measurements to save battery
+    /// (see [`HcSr04::set_powered`]): measuring powers it on again, waiting for it to warm up.
+    ///
+    /// ```rust,no_run
+    /// use hc_sr04::HcSr04;
+    /// use std::{thread, time::Duration};
+    ///
+    /// let mut ultrasonic = HcSr04::builder(24, 23)
+    ///     .power_pin(25)
+    ///     .warm_up(Duration::from_millis(50))
+    ///     .watchdog(3)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// loop {
+    ///     println!("{:?}", ultrasonic.measure().unwrap());
+    ///     ultrasonic.set_powered(false).unwrap();
+    ///     thread::sleep(Duration::from_secs(60));
+    /// }
+    /// ```
+    pub fn power_pin(mut self, pin: u8) -> Self {
+        self.pins.power = Some(pin);
+        self
+    }
+
+    /// Whether the sensor is powered with the power-enable pin (see
+    /// [`HcSr04Builder::power_pin`]) driven low instead (disabled by default), e.g. through a
+    /// P-channel MOSFET on the high side.
+    pub fn power_active_low(mut self, active_low: bool) -> Self {
+        self.pins.power_active_low = active_low;
+        self
+    }
+
+    /// Time the sensor takes to become operational after power-on, when switching its supply
+    /// (see [`HcSr04Builder::power_pin`]; defaults to [`PowerGate::WARM_UP`]).
+    pub fn warm_up(mut self, delay: Duration) -> Self {
+        self.pins.warm_up = delay;
+        self
+    }
+
+    /// Initialize HC-SR04 sensor and, unless busy polling, register GPIO interrupt on `echo` pin
+    /// for RisingEdge events in order to poll it for bouncing UltraSonic waves detection.
+    pub fn build(self) -> Result<HcSr04> {
+        self.build_with(&Gpio::new()?)
+    }
+
+    /// Initialize HC-SR04 sensor as [`HcSr04Builder::build`] does, getting the pins from the
+    /// application's `gpio` instance rather than opening a new one.
+    pub fn build_with(self, gpio: &Gpio) -> Result<HcSr04> {
+        let power = match self.pins.power {
+            Some(pin) => Some(
+                PowerGate::new(gpio.get(pin)?.into_output_low())
+                    .active_low(self.pins.power_active_low)
+                    .warm_up(self.pins.warm_up),
+            ),
+            None => None,
+        };
+        let with_power = |backend: Rppal| match power {
+            Some(power) => backend.with_power(power),
+            None => backend,
+        };
+
+        if self.pins.trig == self.pins.echo {
+            let mut pin = gpio.get(self.pins.trig)?.into_io(Mode::Input);
+            pin.set_bias(match self.pins.pull {
+                Pull::Down => Bias::PullDown,
+                Pull::Up => Bias::PullUp,
+                Pull::Off => Bias::Off,
+            });
+            pin.set_low();
+            return Ok(self.finish(|_| with_power(Rppal::single(pin))));
+        }
+
+        let echo = gpio.get(self.pins.echo)?;
+        let mut echo = match self.pins.pull {
+            Pull::Down => echo.into_input_pulldown(),
+            Pull::Up => echo.into_input_pullup(),
+            Pull::Off => echo.into_input(),
+        };
+        if self.pins.precision.uses_interrupt() {
+            echo.set_interrupt(Trigger::Both, None)?;
+        }
+        let trig = gpio.get(self.pins.trig)?.into_output_low();
+        let precision = self.pins.precision;
+
+        Ok(self.finish(|_| with_power(Rppal::new(trig, echo, precision))))
+    }
+}
+
+impl<B: Backend> HcSr04Builder<B> {
+    /// Create a new builder for the sensor driven by `backend`.
+    pub fn with_backend(backend: B) -> Self {
+        Self::with_pins(backend)
+    }
+
+    /// Initialize HC-SR04 sensor driven by the backend.
+    pub fn build(self) -> HcSr04<B> {
+        self.finish(|backend| backend)
+    }
+}
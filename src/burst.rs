@@ -0,0 +1,147 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Burst measurement, aggregating several pings into a single distance.
+//!
+//! A single ping can be disturbed by spurious echoes: [`HcSr04::measure_n`] performs several
+//! pings in a row, spaced by the sensor's minimum measurement cycle (see
+//! [`HcSr04Builder::min_cycle`](crate::HcSr04Builder::min_cycle)), and aggregates them as per
+//! the given [`Aggregation`] strategy.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{burst::Aggregation, HcSr04, Unit};
+//!
+//! let mut ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//!
+//! let burst = ultrasonic
+//!     .measure_n(5, Aggregation::Median, Unit::Centimeters)
+//!     .unwrap();
+//! match burst.value {
+//!     Some(dist) => println!("Distance: {:.1}cm (±{:.1}cm)", dist, burst.spread.unwrap()),
+//!     None => println!("Object out of range"),
+//! }
+//! ```
+
+use crate::{backend::Backend, portable, quality, Float, HcSr04, Result, Unit};
+
+/// Strategy aggregating the distances of a burst into a single one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Aggregation {
+    /// Mean distance.
+    Mean,
+    /// Median distance, robust to spurious readings.
+    Median,
+    /// Shortest distance, e.g. detecting the nearest obstacle.
+    Min,
+    /// Longest distance.
+    Max,
+    /// Mean distance discarding the given fraction (clamped to `0..0.5`) of the shortest and of
+    /// the longest distances.
+    TrimmedMean(Float),
+}
+
+impl Aggregation {
+    /// Aggregate `distances`, sorted in ascending order and not empty.
+    fn apply(self, distances: &[Float]) -> Float {
+        let mid = distances.len() / 2;
+
+        match self {
+            Self::Mean => mean(distances),
+            Self::Median => match distances.len() % 2 {
+                0 => (distances[mid - 1] + distances[mid]) / 2.,
+                _ => distances[mid],
+            },
+            Self::Min => distances[0],
+            Self::Max => distances[distances.len() - 1],
+            Self::TrimmedMean(fraction) => {
+                // Always keep at least the median distance(s).
+                let trim = (distances.len() as Float * fraction.clamp(0., 0.5)) as usize;
+                let trim = trim.min((distances.len() - 1) / 2);
+                mean(&distances[trim..distances.len() - trim])
+            }
+        }
+    }
+}
+
+/// Mean of `distances`, not empty.
+fn mean(distances: &[Float]) -> Float {
+    distances.iter().sum::<Float>() / distances.len() as Float
+}
+
+/// Outcome of a burst measurement, expressed in the requested unit.
+///
+/// # Fields
+///
+/// - `value`: aggregated distance (`None` if no ping detected an object within maximum measuring
+///   range)
+/// - `spread`: population standard deviation of the distances (`None` as `value`)
+/// - `samples`: number of pings detecting an object, out of the requested ones
+/// - `confidence`: quality score in `0.0..=1.0` (see the [`quality`] module)
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Burst {
+    pub value: Option<Float>,
+    pub spread: Option<Float>,
+    pub samples: usize,
+    pub confidence: Float,
+}
+
+impl<B: Backend> HcSr04<B> {
+    /// Perform `n` **distance measurements** (see [`HcSr04::measure_distance`]) in a row and
+    /// aggregate the distances as per `strategy`, expressed as `unit`.
+    ///
+    /// Pings with no object in range are left out of the aggregation. Fails on the first
+    /// measurement returning an error.
+    pub fn measure_n(&mut self, n: usize, strategy: Aggregation, unit: Unit) -> Result<Burst> {
+        let mut distances = Vec::with_capacity(n);
+        for _ in 0..n {
+            if let Some(distance) = self.measure_meters()? {
+                distances.push(portable::to_unit(distance, unit));
+            }
+        }
+
+        if distances.is_empty() {
+            return Ok(Burst {
+                value: None,
+                spread: None,
+                samples: 0,
+                confidence: self.reliability(),
+            });
+        }
+
+        distances.sort_by(Float::total_cmp);
+        let mean = mean(&distances);
+        let variance = distances
+            .iter()
+            .map(|distance| (distance - mean) * (distance - mean))
+            .sum::<Float>()
+            / distances.len() as Float;
+
+        // Spread expressed as `unit`, converted to m for the confidence.
+        let spread = variance.sqrt();
+        let confidence = self.reliability()
+            * (distances.len() as Float / n as Float)
+            * quality::spread_factor(spread / unit.per_meter());
+
+        Ok(Burst {
+            value: Some(strategy.apply(&distances)),
+            spread: Some(spread),
+            samples: distances.len(),
+            confidence,
+        })
+    }
+}
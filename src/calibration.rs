@@ -0,0 +1,293 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Speed of sound models used to calibrate the sensor.
+//!
+//! The sensor measures the round-trip time of an ultrasonic burst: converting it to distance
+//! requires the speed of sound, which mostly depends on air temperature and, to a lesser extent,
+//! on humidity and barometric pressure.
+//!
+//! - [`sound_speed`]: linear approximation for dry air, depending on temperature only
+//! - [`sound_speed_humid`]: humid air at standard atmospheric pressure
+//! - [`sound_speed_at`]: humid air at any barometric pressure (see [`pressure_at_altitude`] when
+//!   only the altitude is known)
+//!
+//! ```rust
+//! use hc_sr04::calibration;
+//!
+//! let dry = calibration::sound_speed(20.);
+//! let humid = calibration::sound_speed_humid(20., 80.);
+//! assert!(humid > dry);
+//! assert!((humid / dry - 1.) < 0.005);
+//!
+//! // Humidity affects sound speed more in thinner air.
+//! let pressure = calibration::pressure_at_altitude(3000.);
+//! assert!((pressure - 701.).abs() < 1.);
+//! assert!(calibration::sound_speed_at(20., 80., pressure) > humid);
+//! ```
+//!
+//! Rather than calibrating the sensor manually, a [`TemperatureSource`] (e.g. a temperature
+//! sensor driver) can be wired in so that the sensor periodically recalibrates itself (see
+//! [`HcSr04::auto_calibrate`](crate::HcSr04::auto_calibrate)).
+//!
+//! Mounting offsets and unit-to-unit sensor variation can be compensated as well, fitting a
+//! linear [`Correction`] against known reference distances (see
+//! [`HcSr04::calibrate_reference`](crate::HcSr04::calibrate_reference)).
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+pub use crate::portable::sound_speed;
+use crate::Float;
+
+/// Absolute temperature of 0C in K.
+const ZERO_CELSIUS: Float = 273.15;
+/// Carbon dioxide mole fraction in air.
+const CO2_FRACTION: Float = 0.0004;
+
+/// Standard atmospheric pressure at sea level in hPa.
+pub const STANDARD_PRESSURE: Float = 1013.25;
+
+/// Speed of sound in m/s given the ambient temperature `temp` in *Celsius degrees* and the
+/// `relative_humidity` in percent (clamped to `0..=100`), at standard atmospheric pressure.
+pub fn sound_speed_humid(temp: Float, relative_humidity: Float) -> Float {
+    sound_speed_at(temp, relative_humidity, STANDARD_PRESSURE)
+}
+
+/// Speed of sound in m/s given the ambient temperature `temp` in *Celsius degrees*, the
+/// `relative_humidity` in percent (clamped to `0..=100`) and the barometric `pressure` in hPa.
+///
+/// Uses Cramer's model (*J. Acoust. Soc. Am. 93, 1993*), accounting for water vapour and carbon
+/// dioxide content of the air, valid between *0C* and *30C* and between *600hPa* and *1100hPa*.
+pub fn sound_speed_at(temp: Float, relative_humidity: Float, pressure: Float) -> Float {
+    /// Cramer's model coefficients.
+    const A: [Float; 16] = [
+        331.5024, 0.603055, -0.000528, 51.471935, 0.1495874, -0.000782, -1.82e-7, 3.73e-8,
+        -2.93e-10, -85.20931, -0.228525, 5.91e-5, -2.835149, -2.15e-13, 29.179762, 0.000486,
+    ];
+
+    let t = temp;
+    // Pressure in Pa.
+    let p = pressure * 100.;
+    let xw = vapour_fraction(temp, relative_humidity, pressure);
+    let xc = CO2_FRACTION;
+
+    A[0] + A[1] * t
+        + A[2] * t * t
+        + (A[3] + A[4] * t + A[5] * t * t) * xw
+        + (A[6] + A[7] * t + A[8] * t * t) * p
+        + (A[9] + A[10] * t + A[11] * t * t) * xc
+        + A[12] * xw * xw
+        + A[13] * p * p
+        + A[14] * xc * xc
+        + A[15] * xw * p * xc
+}
+
+/// Speed of sound in m/s given the ambient temperature `temp` in *Celsius degrees* and, if known,
+/// the `relative_humidity` in percent and the barometric `pressure` in hPa, using the most
+/// complete model the known conditions allow.
+pub(crate) fn sound_speed_env(
+    temp: Float,
+    relative_humidity: Option<Float>,
+    pressure: Option<Float>,
+) -> Float {
+    match (relative_humidity, pressure) {
+        (None, None) => sound_speed(temp),
+        (relative_humidity, pressure) => sound_speed_at(
+            temp,
+            relative_humidity.unwrap_or(0.),
+            pressure.unwrap_or(STANDARD_PRESSURE),
+        ),
+    }
+}
+
+/// Barometric pressure in hPa at the given `altitude` in m above sea level, according to the
+/// International Standard Atmosphere.
+pub fn pressure_at_altitude(altitude: Float) -> Float {
+    STANDARD_PRESSURE * (1. - 2.25577e-5 * altitude).powf(5.25588)
+}
+
+/// Water vapour mole fraction in air given the ambient temperature `temp` in *Celsius degrees*,
+/// the `relative_humidity` in percent and the barometric `pressure` in hPa.
+fn vapour_fraction(temp: Float, relative_humidity: Float, pressure: Float) -> Float {
+    let kelvin = temp + ZERO_CELSIUS;
+    // Pressure in Pa.
+    let p = pressure * 100.;
+    // Saturation vapour pressure in Pa (Giacomo equation).
+    let saturation = (1.2378847e-5 * kelvin * kelvin - 1.9121316e-2 * kelvin + 33.937_11
+        - 6.3431645e3 / kelvin)
+        .exp();
+    // Enhancement factor of water vapour in air.
+    let enhancement = 1.00062 + 3.14e-8 * p + 5.6e-7 * temp * temp;
+
+    relative_humidity.clamp(0., 100.) / 100. * enhancement * saturation / p
+}
+
+/// Linear correction applied to measured distances: `scale * distance + offset`.
+///
+/// # Fields
+///
+/// - `scale`: correction scale factor
+/// - `offset`: correction offset in m
+///
+/// ```rust
+/// use hc_sr04::calibration::Correction;
+///
+/// // Sensor reading 5% short and mounted 2cm behind the reference point.
+/// let correction = Correction::fit(&[(0.5, 0.505), (1.5, 1.555)]).unwrap();
+/// assert!((correction.scale - 1.05).abs() < 1e-4);
+/// assert!((correction.offset + 0.02).abs() < 1e-4);
+/// assert!((correction.apply(1.) - 1.03).abs() < 1e-4);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Correction {
+    pub scale: Float,
+    pub offset: Float,
+}
+
+impl Correction {
+    /// Correction leaving distances untouched.
+    pub const IDENTITY: Self = Self {
+        scale: 1.,
+        offset: 0.,
+    };
+
+    /// Fit the correction mapping measured distances onto known reference distances, given
+    /// `(measured, reference)` pairs expressed in m, through least squares.
+    ///
+    /// A single pair only fits the offset. Returns `None` if no pairs are given, or if the
+    /// measured distances of several pairs are all equal.
+    pub fn fit(pairs: &[(Float, Float)]) -> Option<Self> {
+        if pairs.is_empty() {
+            return None;
+        }
+
+        let n = pairs.len() as Float;
+        let mean_measured = pairs.iter().map(|(measured, _)| measured).sum::<Float>() / n;
+        let mean_reference = pairs.iter().map(|(_, reference)| reference).sum::<Float>() / n;
+
+        let scale = if pairs.len() == 1 {
+            1.
+        } else {
+            let mut covariance = 0.;
+            let mut variance = 0.;
+            for (measured, reference) in pairs {
+                let deviation = measured - mean_measured;
+                covariance += deviation * (reference - mean_reference);
+                variance += deviation * deviation;
+            }
+
+            if variance <= Float::EPSILON {
+                return None;
+            }
+            covariance / variance
+        };
+
+        Some(Self {
+            scale,
+            offset: mean_reference - scale * mean_measured,
+        })
+    }
+
+    /// Apply the correction to `distance` expressed in m.
+    pub fn apply(&self, distance: Float) -> Float {
+        self.scale * distance + self.offset
+    }
+
+    /// Correction applying `self` after `inner`.
+    pub(crate) fn compose(&self, inner: &Self) -> Self {
+        Self {
+            scale: self.scale * inner.scale,
+            offset: self.apply(inner.offset),
+        }
+    }
+}
+
+impl Default for Correction {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// Source of ambient temperature readings used for automatic calibration.
+///
+/// Implemented for closures returning the temperature, in order to wire in any temperature
+/// sensor driver:
+///
+/// ```rust
+/// use hc_sr04::calibration::TemperatureSource;
+///
+/// let mut source = || Some(21.5);
+/// assert_eq!(source.temperature(), Some(21.5));
+/// ```
+pub trait TemperatureSource {
+    /// Read the ambient temperature in *Celsius degrees*, `None` if it is not available (the
+    /// sensor keeps its current calibration).
+    fn temperature(&mut self) -> Option<Float>;
+}
+
+impl<F: FnMut() -> Option<Float>> TemperatureSource for F {
+    fn temperature(&mut self) -> Option<Float> {
+        self()
+    }
+}
+
+/// Periodic reading of a [`TemperatureSource`].
+///
+/// # Fields
+///
+/// - `source`: ambient temperature source
+/// - `interval`: minimum time between consecutive readings
+/// - `last`: time of the latest reading
+pub(crate) struct AutoCalibration {
+    source: Box<dyn TemperatureSource + Send>,
+    interval: Duration,
+    last: Option<Instant>,
+}
+
+impl AutoCalibration {
+    /// Read `source` at most once per `interval`.
+    pub(crate) fn new(source: impl TemperatureSource + Send + 'static, interval: Duration) -> Self {
+        Self {
+            source: Box::new(source),
+            interval,
+            last: None,
+        }
+    }
+
+    /// Read the ambient temperature if `interval` elapsed since the latest reading.
+    pub(crate) fn poll(&mut self) -> Option<Float> {
+        let now = Instant::now();
+        if matches!(self.last, Some(last) if now.duration_since(last) < self.interval) {
+            return None;
+        }
+
+        self.last = Some(now);
+        self.source.temperature()
+    }
+}
+
+impl fmt::Debug for AutoCalibration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AutoCalibration")
+            .field("interval", &self.interval)
+            .field("last", &self.last)
+            .finish()
+    }
+}
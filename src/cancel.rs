@@ -0,0 +1,84 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Cancellation of in-flight measurements.
+//!
+//! A measurement may block for a long time, e.g. waiting for an **ECHO** pulse which never
+//! starts when no start timeout is set. Cancelling the sensor's [`CancelToken`] (see
+//! [`HcSr04::cancel_token`](crate::HcSr04::cancel_token)) from another thread interrupts it
+//! promptly, failing with [`Error::Cancelled`](crate::error::Error::Cancelled), e.g. during
+//! application shutdown.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{HcSr04, Unit};
+//! use std::{thread, time::Duration};
+//!
+//! let mut ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//! let cancel = ultrasonic.cancel_token();
+//!
+//! let worker = thread::spawn(move || {
+//!     while let Ok(dist) = ultrasonic.measure_distance(Unit::Meters) {
+//!         println!("Distance: {:?}", dist);
+//!     }
+//! });
+//!
+//! thread::sleep(Duration::from_secs(10));
+//! // Shut down: the worker stops without waiting out the pending measurement.
+//! cancel.cancel();
+//! worker.join().unwrap();
+//! ```
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+#[cfg(any(feature = "pigpio", feature = "rppal"))]
+use std::time::Duration;
+
+/// Longest time a backend waits without checking for cancellation.
+#[cfg(any(feature = "pigpio", feature = "rppal"))]
+pub(crate) const CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Shared flag cancelling the measurements of a sensor.
+///
+/// Cloning yields a new handle to the same flag. Once cancelled, the pending measurement and all
+/// the subsequent ones fail, until the token is reset.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Create a new token, not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the pending measurement, if any, and the subsequent ones.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Let measurements run again after cancelling them.
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::Relaxed);
+    }
+
+    /// Whether measurements are cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
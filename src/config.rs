@@ -0,0 +1,416 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Sensors configured from TOML files, available with the `config` feature.
+//!
+//! A [`Config`] describes one or more sensors: pins, ambient temperature, measuring unit, filters
+//! and the sinks their measurements are delivered to, so that deployments can be reconfigured
+//! without recompiling the program. Every option but the pins falls back to the sensor's default.
+//!
+//! ```rust
+//! use hc_sr04::{config::Config, Unit};
+//!
+//! let config: Config = r#"
+//!     [[sensor]]
+//!     name = "front"
+//!     trig = 24
+//!     echo = 23
+//!     temperature = 23.0
+//!     unit = "Centimeters"
+//!     filters = [{ type = "median", size = 5 }]
+//!
+//!     [[sensor]]
+//!     name = "rear"
+//!     trig = 17
+//!     echo = 27
+//!     interval_ms = 500
+//! "#
+//! .parse()
+//! .unwrap();
+//!
+//! assert_eq!(config.sensors.len(), 2);
+//! assert_eq!(config.sensors[0].unit, Unit::Centimeters);
+//! ```
+//!
+//! Sinks are listed per sensor, each available with the feature of the same name:
+//!
+//! ```toml
+//! [[sensor.sinks]]
+//! type = "http"
+//! listen = "0.0.0.0:8080"
+//!
+//! [[sensor.sinks]]
+//! type = "mqtt"
+//! host = "localhost"
+//! topic = "home/garage/distance"
+//!
+//! [[sensor.sinks]]
+//! type = "prometheus"
+//! listen = "0.0.0.0:9184"
+//!
+//! [[sensor.sinks]]
+//! type = "recorder"
+//! path = "/var/log/hc-sr04/garage.csv"
+//! format = "csv"
+//!
+//! [[sensor.sinks]]
+//! type = "websocket"
+//! listen = "0.0.0.0:9001"
+//! ```
+//!
+//! [`Config::start`] builds every sensor and measures it continuously on a worker thread,
+//! delivering measurements to its sinks until stopped:
+//!
+//! ```rust,no_run
+//! use hc_sr04::config::Config;
+//! use std::{thread, time::Duration};
+//!
+//! let workers = Config::load("/etc/hc-sr04.toml").unwrap().start().unwrap();
+//!
+//! thread::sleep(Duration::from_secs(3600));
+//! for worker in workers {
+//!     println!("{:?}", worker.stop());
+//! }
+//! ```
+
+#[cfg(feature = "recorder")]
+use crate::recorder::{Format, Recorder};
+#[cfg(feature = "http")]
+use crate::sinks::http::DistanceEndpoint;
+#[cfg(feature = "mqtt")]
+use crate::sinks::mqtt::{MqttOptions, MqttSink};
+#[cfg(feature = "prometheus")]
+use crate::sinks::prometheus::Metrics;
+#[cfg(feature = "websocket")]
+use crate::sinks::websocket::WebSocketServer;
+use crate::{
+    builder::{Pins, Pull},
+    continuous::ContinuousWorker,
+    error::Error,
+    model::Model,
+    profile::FilterSettings,
+    Float, HcSr04, HcSr04Builder, Measurement, Result, Unit,
+};
+use rppal::gpio::Gpio;
+use serde::{de::Error as _, Deserialize, Serialize};
+#[cfg(feature = "recorder")]
+use std::path::PathBuf;
+use std::{fs, path::Path, str::FromStr, time::Duration};
+
+/// Default interval between measurements in ms.
+const DEFAULT_INTERVAL_MS: u64 = 1000;
+/// Default MQTT broker port.
+#[cfg(feature = "mqtt")]
+const DEFAULT_MQTT_PORT: u16 = 1883;
+
+/// Configuration of the sensors of a deployment.
+///
+/// # Fields
+///
+/// - `sensors`: configured sensors, listed as `[[sensor]]` tables
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(rename = "sensor", default)]
+    pub sensors: Vec<SensorConfig>,
+}
+
+impl Config {
+    /// Load the configuration from the TOML file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Initialize every configured sensor, in order, getting the pins from one GPIO instance.
+    pub fn build(&self) -> Result<Vec<HcSr04>> {
+        let gpio = Gpio::new()?;
+        self.sensors
+            .iter()
+            .map(|sensor| sensor.builder().build_with(&gpio))
+            .collect()
+    }
+
+    /// Initialize every configured sensor and start measuring it continuously (see
+    /// [`SensorConfig::start`]), returning the workers in order.
+    pub fn start(&self) -> Result<Vec<ContinuousWorker>> {
+        let gpio = Gpio::new()?;
+        self.sensors
+            .iter()
+            .map(|sensor| sensor.start_with(&gpio))
+            .collect()
+    }
+}
+
+impl FromStr for Config {
+    type Err = Error;
+
+    /// Parse the configuration from TOML, failing with [`Error::Config`] if it's malformed or
+    /// holds invalid settings.
+    fn from_str(s: &str) -> Result<Self> {
+        let config: Self = toml::from_str(s)?;
+        for (index, sensor) in config.sensors.iter().enumerate() {
+            sensor.validate().map_err(|message| {
+                toml::de::Error::custom(format!("sensor {}: {}", index, message))
+            })?;
+        }
+        Ok(config)
+    }
+}
+
+/// Configuration of a sensor.
+///
+/// # Fields
+///
+/// - `name`: name of the sensor, e.g. for logging
+/// - `trig`: **TRIGGER** output GPIO pin
+/// - `echo`: **ECHO** input GPIO pin
+/// - `model`: sensor model, setting timing and range defaults
+/// - `pull`: **ECHO** pin pull resistor configuration
+/// - `temperature`: ambient temperature in *Celsius degrees* used for calibration
+/// - `unit`: default measuring unit
+/// - `max_range`: maximum measuring range in m
+/// - `min_range`: minimum measuring range in m
+/// - `filters`: filter stages applied to measured distances, in order
+/// - `interval_ms`: interval between continuous measurements in ms (defaults to *1s*)
+/// - `sinks`: sinks continuous measurements are delivered to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SensorConfig {
+    pub name: Option<String>,
+    pub trig: u8,
+    pub echo: u8,
+    #[serde(default)]
+    pub model: Model,
+    #[serde(default)]
+    pub pull: Pull,
+    pub temperature: Option<Float>,
+    #[serde(default)]
+    pub unit: Unit,
+    pub max_range: Option<Float>,
+    pub min_range: Option<Float>,
+    #[serde(default)]
+    pub filters: Vec<FilterSettings>,
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+    #[serde(default)]
+    pub sinks: Vec<SinkConfig>,
+}
+
+fn default_interval_ms() -> u64 {
+    DEFAULT_INTERVAL_MS
+}
+
+impl SensorConfig {
+    /// Builder for the configured sensor, e.g. to set further options before building it.
+    pub fn builder(&self) -> HcSr04Builder<Pins> {
+        let mut builder = HcSr04::builder(self.trig, self.echo)
+            .model(self.model)
+            .pull(self.pull)
+            .unit(self.unit);
+        if let Some(temp) = self.temperature {
+            builder = builder.temperature(temp);
+        }
+        if let Some(range) = self.max_range {
+            builder = builder.max_range(range);
+        }
+        if let Some(range) = self.min_range {
+            builder = builder.min_range(range);
+        }
+        if !self.filters.is_empty() {
+            builder = builder.filter(FilterSettings::pipeline(&self.filters));
+        }
+        builder
+    }
+
+    /// Initialize the configured sensor.
+    pub fn build(&self) -> Result<HcSr04> {
+        self.builder().build()
+    }
+
+    /// Interval between continuous measurements.
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms)
+    }
+
+    /// Initialize the configured sensor and its sinks, and start measuring distance every
+    /// [`SensorConfig::interval`] on a worker thread (see [`HcSr04::spawn_continuous`]),
+    /// delivering every measurement to the sinks.
+    ///
+    /// Failed deliveries are skipped; stopping the worker flushes recorders.
+    pub fn start(&self) -> Result<ContinuousWorker> {
+        self.start_with(&Gpio::new()?)
+    }
+
+    /// Start measuring as [`SensorConfig::start`] does, getting the pins from the application's
+    /// `gpio` instance rather than opening a new one.
+    pub fn start_with(&self, gpio: &Gpio) -> Result<ContinuousWorker> {
+        let mut sinks = self
+            .sinks
+            .iter()
+            .map(|sink| sink.open(self.unit))
+            .collect::<Result<Vec<_>>>()?;
+        let sensor = self.builder().build_with(gpio)?;
+
+        Ok(
+            sensor.spawn_worker(self.interval(), move |measurement, latency| {
+                for sink in &mut sinks {
+                    sink.deliver(measurement, latency);
+                }
+                true
+            }),
+        )
+    }
+
+    /// Check the settings the builder would otherwise panic on.
+    fn validate(&self) -> std::result::Result<(), &'static str> {
+        if matches!(self.max_range, Some(range) if !(range.is_finite() && range > 0.)) {
+            return Err("maximum measuring range must be positive and finite");
+        }
+        if matches!(self.min_range, Some(range) if !(range.is_finite() && range >= 0.)) {
+            return Err("minimum measuring range must be non-negative and finite");
+        }
+        Ok(())
+    }
+}
+
+/// Configuration of a sink measurements are delivered to, tagged by `type`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SinkConfig {
+    /// Serve the current distance, expressed in the sensor's unit, over HTTP on `listen` (see
+    /// [`DistanceEndpoint`]), available with the `http` feature.
+    #[cfg(feature = "http")]
+    Http { listen: String },
+    /// Publish measurements to `topic` of the MQTT broker at `host` (see [`MqttSink`]),
+    /// available with the `mqtt` feature.
+    #[cfg(feature = "mqtt")]
+    Mqtt {
+        host: String,
+        #[serde(default = "default_mqtt_port")]
+        port: u16,
+        client_id: Option<String>,
+        topic: String,
+        #[serde(default)]
+        retain: bool,
+    },
+    /// Serve metrics over HTTP on `listen` (see [`Metrics`]), available with the `prometheus`
+    /// feature.
+    #[cfg(feature = "prometheus")]
+    Prometheus { listen: String },
+    /// Record measurements to the file at `path` (see [`Recorder`]), available with the
+    /// `recorder` feature.
+    #[cfg(feature = "recorder")]
+    Recorder { path: PathBuf, format: Format },
+    /// Push measurements to the WebSocket clients connected on `listen` (see
+    /// [`WebSocketServer`]), available with the `websocket` feature.
+    #[cfg(feature = "websocket")]
+    Websocket { listen: String },
+}
+
+#[cfg(feature = "mqtt")]
+fn default_mqtt_port() -> u16 {
+    DEFAULT_MQTT_PORT
+}
+
+impl SinkConfig {
+    /// Connect, serve or open the configured sink, for a sensor measuring in `unit`.
+    fn open(&self, unit: Unit) -> Result<Sink> {
+        // Not every sink cares about the unit.
+        let _ = unit;
+        match *self {
+            #[cfg(feature = "http")]
+            Self::Http { ref listen } => {
+                let endpoint = DistanceEndpoint::new(unit);
+                endpoint.serve(listen.as_str())?;
+                Ok(Sink::Http(endpoint))
+            }
+            #[cfg(feature = "mqtt")]
+            Self::Mqtt {
+                ref host,
+                port,
+                ref client_id,
+                ref topic,
+                retain,
+            } => {
+                let client_id = client_id.as_deref().unwrap_or("hc-sr04");
+                let options = MqttOptions::new(client_id, host, port);
+                Ok(Sink::Mqtt(MqttSink::connect(options, topic).retain(retain)))
+            }
+            #[cfg(feature = "prometheus")]
+            Self::Prometheus { ref listen } => {
+                let metrics = Metrics::new();
+                metrics.serve(listen.as_str())?;
+                Ok(Sink::Prometheus(metrics))
+            }
+            #[cfg(feature = "recorder")]
+            Self::Recorder { ref path, format } => {
+                Ok(Sink::Recorder(Recorder::create(path, format)?))
+            }
+            #[cfg(feature = "websocket")]
+            Self::Websocket { ref listen } => {
+                let server = WebSocketServer::new();
+                server.serve(listen.as_str())?;
+                Ok(Sink::Websocket(server))
+            }
+        }
+    }
+}
+
+/// Sink opened from its [`SinkConfig`].
+enum Sink {
+    #[cfg(feature = "http")]
+    Http(DistanceEndpoint),
+    #[cfg(feature = "mqtt")]
+    Mqtt(MqttSink),
+    #[cfg(feature = "prometheus")]
+    Prometheus(Metrics),
+    #[cfg(feature = "recorder")]
+    Recorder(Recorder),
+    #[cfg(feature = "websocket")]
+    Websocket(WebSocketServer),
+}
+
+impl Sink {
+    /// Deliver `measurement`, or the error it failed with, which took `latency` to perform.
+    fn deliver(&mut self, measurement: &Result<Measurement>, latency: Duration) {
+        // Not every sink cares about errors and latency.
+        let _ = (measurement, latency);
+        match *self {
+            #[cfg(feature = "http")]
+            Self::Http(ref endpoint) => endpoint.record(measurement),
+            #[cfg(feature = "mqtt")]
+            Self::Mqtt(ref sink) => {
+                if let Ok(measurement) = measurement {
+                    let _ = sink.publish(measurement);
+                }
+            }
+            #[cfg(feature = "prometheus")]
+            Self::Prometheus(ref metrics) => metrics.record(measurement, latency),
+            #[cfg(feature = "recorder")]
+            Self::Recorder(ref mut recorder) => {
+                if let Ok(measurement) = measurement {
+                    let _ = recorder.record(measurement);
+                }
+            }
+            #[cfg(feature = "websocket")]
+            Self::Websocket(ref server) => {
+                if let Ok(measurement) = measurement {
+                    let _ = server.broadcast(measurement);
+                }
+            }
+        }
+    }
+}
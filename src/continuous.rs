@@ -0,0 +1,638 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Continuous background sampling.
+//!
+//! [`HcSr04::start_continuous`] moves the sensor to a worker thread, which measures distance at
+//! a fixed interval and streams readings over a channel, until the receiver is dropped.
+//! Alternatively, [`HcSr04::start_continuous_with`] hands readings to a closure running on the
+//! worker thread.
+//!
+//! [`HcSr04::start_continuous_bounded`] caps the number of queued readings instead, letting the
+//! worker block, drop the oldest or drop the newest reading when the consumer lags behind (see
+//! [`Backpressure`]), and counting the dropped ones.
+//!
+//! Workers handing readings to closures, including the sinks and event watchers built on them,
+//! return a [`ContinuousWorker`] (see [`HcSr04::spawn_continuous`]) which can be stopped
+//! gracefully: the in-flight ping completes, the handler (and the sinks or recorders it owns) is
+//! dropped, flushing them, the pins are released and error statistics are returned.
+//!
+//! Other sensors implementing [`DistanceSensor`] are sampled in the background through
+//! [`start_with`].
+//!
+//! ```rust,no_run
+//! use hc_sr04::{HcSr04, Unit};
+//! use std::{fs::File, io::BufWriter, io::Write, thread, time::Duration};
+//!
+//! let ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//! let mut log = BufWriter::new(File::create("distances.txt").unwrap());
+//!
+//! let worker = ultrasonic.spawn_continuous(Duration::from_millis(100), move |measurement| {
+//!     if let Ok(Some(distance)) = measurement.as_ref().map(|m| m.distance) {
+//!         writeln!(log, "{:.3}", distance.as_unit(Unit::Meters)).unwrap();
+//!     }
+//!     true
+//! });
+//!
+//! thread::sleep(Duration::from_secs(60));
+//! let stats = worker.stop();
+//! println!("{} measurements, {} errors", stats.measurements, stats.errors);
+//! ```
+//!
+//! ```rust,no_run
+//! use hc_sr04::{continuous::Backpressure, HcSr04};
+//! use std::time::Duration;
+//!
+//! let ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//! let interval = Duration::from_millis(60);
+//! let receiver = ultrasonic.start_continuous_bounded(interval, 16, Backpressure::DropOldest);
+//!
+//! while let Some(measurement) = receiver.recv() {
+//!     println!("{:?} ({} dropped)", measurement.unwrap().distance, receiver.dropped());
+//! }
+//! ```
+//!
+//! ```rust,no_run
+//! use hc_sr04::HcSr04;
+//! use std::time::Duration;
+//!
+//! let ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//!
+//! for measurement in ultrasonic.start_continuous(Duration::from_millis(100)) {
+//!     match measurement.unwrap().distance {
+//!         Some(dist) => println!("Distance: {:.1}cm", dist.as_cm()),
+//!         None => println!("Object out of range"),
+//!     }
+//! }
+//! ```
+
+use crate::{backend::Backend, error::Error, DistanceSensor, HcSr04, Measurement, Result};
+use std::{
+    collections::{hash_map::RandomState, VecDeque},
+    hash::{BuildHasher, Hasher},
+    panic,
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Condvar, Mutex, MutexGuard, PoisonError,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime},
+};
+#[cfg(feature = "tokio")]
+use tokio::sync::watch;
+
+impl<B: Backend + Send + 'static> HcSr04<B> {
+    /// Start measuring distance every `interval` on a worker thread.
+    ///
+    /// As in every continuous mode, the gaps between pings are spread at random if enabled (see
+    /// [`HcSr04Builder::jitter`](crate::HcSr04Builder::jitter)).
+    ///
+    /// Every measurement, or the error it failed with, is sent over the returned channel: the
+    /// worker keeps measuring after errors and stops as soon as the receiver is dropped.
+    ///
+    /// If a measurement takes longer than `interval`, the next one starts right away.
+    pub fn start_continuous(self, interval: Duration) -> Receiver<Result<Measurement>> {
+        let (sender, receiver) = mpsc::channel();
+
+        // Stop measuring as soon as the receiver is dropped.
+        self.start_continuous_with(interval, move |measurement| {
+            sender.send(measurement).is_ok()
+        })
+        .detach();
+
+        receiver
+    }
+
+    /// Start measuring distance every `interval` on a worker thread, queuing at most `capacity`
+    /// measurements and applying `policy` when the queue is full.
+    ///
+    /// Unlike [`HcSr04::start_continuous`], a slow consumer neither grows the queue unboundedly
+    /// nor silently stalls measuring: dropped measurements are counted (see
+    /// [`BoundedReceiver::dropped`]). The worker stops as soon as the receiver is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn start_continuous_bounded(
+        self,
+        interval: Duration,
+        capacity: usize,
+        policy: Backpressure,
+    ) -> BoundedReceiver {
+        assert!(capacity > 0, "bounded queue capacity must be positive");
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                queue: VecDeque::with_capacity(capacity),
+                dropped: 0,
+                receiver: true,
+                sender: true,
+            }),
+            changed: Condvar::new(),
+        });
+        let sender = BoundedSender {
+            shared: shared.clone(),
+            capacity,
+            policy,
+        };
+
+        self.start_continuous_with(interval, move |measurement| sender.send(measurement))
+            .detach();
+
+        BoundedReceiver { shared }
+    }
+
+    /// Start measuring distance every `interval` on a worker thread, handing every measurement,
+    /// or the error it failed with, to `handler` on the worker thread, until stopped through the
+    /// returned [`ContinuousWorker`] or `handler` returns `false`.
+    ///
+    /// Once stopped, the worker drops `handler`, flushing the recorders and sinks it owns, and
+    /// the sensor, releasing the pins. If a measurement takes longer than `interval`, the next
+    /// one starts right away.
+    pub fn spawn_continuous<F>(self, interval: Duration, mut handler: F) -> ContinuousWorker
+    where
+        F: FnMut(&Result<Measurement>) -> bool + Send + 'static,
+    {
+        self.spawn_worker(interval, move |measurement, _| handler(measurement))
+    }
+
+    /// Start the worker of [`HcSr04::spawn_continuous`], handing `handler` the time taken by
+    /// every measurement as well.
+    pub(crate) fn spawn_worker<F>(self, interval: Duration, mut handler: F) -> ContinuousWorker
+    where
+        F: FnMut(&Result<Measurement>, Duration) -> bool + Send + 'static,
+    {
+        self.spawn_ticks(interval, move |sensor, stats| {
+            let start = Instant::now();
+            let measurement = sensor.measurement();
+            let keep = handler(&measurement, start.elapsed());
+            stats.record(measurement);
+            keep
+        })
+    }
+
+    /// Start a stoppable worker running `tick` on the sensor every `interval`, until stopped
+    /// through the returned [`ContinuousWorker`] or `tick` returns `false`, letting it record
+    /// its measurements into the statistics.
+    pub(crate) fn spawn_ticks<F>(self, interval: Duration, tick: F) -> ContinuousWorker
+    where
+        F: FnMut(&mut Self, &mut ContinuousStats) -> bool + Send + 'static,
+    {
+        let jitter = self.jitter;
+        spawn_loop(self, interval, jitter, tick)
+    }
+
+    /// Start measuring distance every `interval` on a worker thread, exposing the most recent
+    /// measurement through a tokio `watch` channel, available with the `tokio` feature (see
+    /// [`HcSr04Async::watch`](crate::HcSr04Async::watch)).
+    ///
+    /// The channel holds `None` until the first measurement succeeds; failed measurements leave
+    /// the latest value untouched. The worker stops as soon as all receivers are dropped.
+    #[cfg(feature = "tokio")]
+    pub fn start_watch(self, interval: Duration) -> watch::Receiver<Option<Measurement>> {
+        let (sender, receiver) = watch::channel(None);
+
+        self.start_continuous_with(interval, move |measurement| {
+            if let Ok(measurement) = measurement {
+                sender.send_replace(Some(measurement));
+            }
+            // Stop measuring as soon as all receivers are dropped.
+            !sender.is_closed()
+        })
+        .detach();
+
+        receiver
+    }
+
+    /// Start measuring distance every `interval` on a worker thread, handing every measurement,
+    /// or the error it failed with, to `handler` on the worker thread, until stopped through the
+    /// returned [`ContinuousWorker`] or `handler` returns `false`.
+    ///
+    /// Unlike [`HcSr04::spawn_continuous`], `handler` takes ownership of the measurements (e.g.
+    /// to send them over a channel), so the statistics of the worker count errors without keeping
+    /// the latest one. If a measurement takes longer than `interval`, the next one starts right
+    /// away.
+    pub fn start_continuous_with<F>(self, interval: Duration, mut handler: F) -> ContinuousWorker
+    where
+        F: FnMut(Result<Measurement>) -> bool + Send + 'static,
+    {
+        self.spawn_ticks(interval, move |sensor, stats| {
+            let measurement = sensor.measurement();
+            stats.count(&measurement);
+            handler(measurement)
+        })
+    }
+}
+
+/// Start measuring distance through any `sensor` every `interval` on a worker thread, handing
+/// every measurement, or the error it failed with, to `handler` on the worker thread, like
+/// [`HcSr04::start_continuous_with`] does for the HC-SR04.
+///
+/// Measurements only carry the distance and the time they completed at (see
+/// [`Measurement::new`]). The worker keeps measuring after errors, until stopped through the
+/// returned [`ContinuousWorker`] or `handler` returns `false`.
+///
+/// ```rust
+/// use hc_sr04::{continuous::{self, ContinuousWorker}, DistanceSensor};
+/// use std::time::Duration;
+///
+/// fn log(sensor: impl DistanceSensor + Send + 'static) -> ContinuousWorker {
+///     continuous::start_with(sensor, Duration::from_millis(200), |measurement| {
+///         println!("{:?}", measurement.map(|m| m.distance));
+///         true
+///     })
+/// }
+/// ```
+pub fn start_with<S, F>(sensor: S, interval: Duration, mut handler: F) -> ContinuousWorker
+where
+    S: DistanceSensor + Send + 'static,
+    F: FnMut(Result<Measurement>) -> bool + Send + 'static,
+{
+    spawn_loop(sensor, interval, None, move |sensor, stats| {
+        let measurement = sensor
+            .measure()
+            .map(|distance| Measurement::new(distance, SystemTime::now()));
+        stats.count(&measurement);
+        handler(measurement)
+    })
+}
+
+/// Start a stoppable worker owning `state` and running `tick` on it every `interval`, spread at
+/// random by up to `jitter` either way, until stopped through the returned [`ContinuousWorker`]
+/// or `tick` returns `false`.
+///
+/// If `tick` takes longer than the gap, the next one starts right away. Once stopped, the worker
+/// drops `tick` and `state` before returning the statistics `tick` recorded.
+pub(crate) fn spawn_loop<S, F>(
+    mut state: S,
+    interval: Duration,
+    jitter: Option<Duration>,
+    mut tick: F,
+) -> ContinuousWorker
+where
+    S: Send + 'static,
+    F: FnMut(&mut S, &mut ContinuousStats) -> bool + Send + 'static,
+{
+    let signal = Arc::new(StopSignal::default());
+    let stop = signal.clone();
+    let mut gaps = Gaps::new(interval, jitter);
+
+    let thread = thread::spawn(move || {
+        let mut stats = ContinuousStats::default();
+        let mut next = Instant::now();
+        while !stop.is_stopped() {
+            if !tick(&mut state, &mut stats) {
+                break;
+            }
+
+            next += gaps.next();
+            match next.checked_duration_since(Instant::now()) {
+                Some(wait) => stop.wait(wait),
+                None => next = Instant::now(),
+            }
+        }
+
+        // Flush sinks and release the pins before reporting.
+        drop(tick);
+        drop(state);
+        stats
+    });
+
+    ContinuousWorker {
+        signal,
+        thread: Some(thread),
+    }
+}
+
+/// Gaps between consecutive ticks: `interval`, spread at random by up to `jitter` either way.
+///
+/// Random offsets are drawn from a xorshift generator, seeded from the process' random hashing
+/// keys so that devices don't share the same sequence.
+#[derive(Debug)]
+struct Gaps {
+    interval: Duration,
+    jitter: Option<Duration>,
+    state: u64,
+}
+
+impl Gaps {
+    fn new(interval: Duration, jitter: Option<Duration>) -> Self {
+        Self {
+            interval,
+            jitter,
+            // Xorshift state must not be zero.
+            state: RandomState::new().build_hasher().finish() | 1,
+        }
+    }
+
+    fn next(&mut self) -> Duration {
+        let jitter = match self.jitter {
+            Some(jitter) if !jitter.is_zero() => jitter,
+            _ => return self.interval,
+        };
+
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        // Keep the span of offsets (twice the jitter) within range.
+        let span =
+            u64::try_from(jitter.as_nanos()).map_or(u64::MAX / 2, |span| span.min(u64::MAX / 2));
+        let offset = Duration::from_nanos(self.state % (2 * span + 1));
+        self.interval.saturating_add(offset).saturating_sub(jitter)
+    }
+}
+
+/// What the worker of [`HcSr04::start_continuous_bounded`] does with a new measurement when the
+/// queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backpressure {
+    /// Wait for the consumer to make room, delaying the next measurements.
+    #[default]
+    Block,
+    /// Discard the oldest queued measurement, keeping the most recent ones.
+    DropOldest,
+    /// Discard the new measurement, keeping the queued ones.
+    DropNewest,
+}
+
+/// Queue shared between the worker and the [`BoundedReceiver`].
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    /// Signaled whenever a measurement is queued or taken, or either side goes away.
+    changed: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait<'a>(&self, state: MutexGuard<'a, State>) -> MutexGuard<'a, State> {
+        self.changed
+            .wait(state)
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    queue: VecDeque<Result<Measurement>>,
+    dropped: u64,
+    /// Whether the receiver is still alive.
+    receiver: bool,
+    /// Whether the worker is still alive.
+    sender: bool,
+}
+
+/// Worker side of the bounded queue.
+#[derive(Debug)]
+struct BoundedSender {
+    shared: Arc<Shared>,
+    capacity: usize,
+    policy: Backpressure,
+}
+
+impl BoundedSender {
+    /// Queue `measurement` as per the backpressure policy, returning `false` once the receiver is
+    /// dropped.
+    fn send(&self, measurement: Result<Measurement>) -> bool {
+        let mut state = self.shared.lock();
+        if state.queue.len() >= self.capacity {
+            match self.policy {
+                Backpressure::Block => {
+                    while state.receiver && state.queue.len() >= self.capacity {
+                        state = self.shared.wait(state);
+                    }
+                }
+                Backpressure::DropOldest => {
+                    state.queue.pop_front();
+                    state.dropped += 1;
+                }
+                Backpressure::DropNewest => {
+                    state.dropped += 1;
+                    return state.receiver;
+                }
+            }
+        }
+        if !state.receiver {
+            return false;
+        }
+
+        state.queue.push_back(measurement);
+        self.shared.changed.notify_all();
+        true
+    }
+}
+
+impl Drop for BoundedSender {
+    /// Wake up the receiver once the worker stops, e.g. on panic.
+    fn drop(&mut self) {
+        self.shared.lock().sender = false;
+        self.shared.changed.notify_all();
+    }
+}
+
+/// Receiving side of the bounded measurement queue, obtained through
+/// [`HcSr04::start_continuous_bounded`].
+///
+/// Iterating blocks until the next measurement is available.
+#[derive(Debug)]
+pub struct BoundedReceiver {
+    shared: Arc<Shared>,
+}
+
+impl BoundedReceiver {
+    /// Wait for the next measurement, or `None` if the worker stopped.
+    pub fn recv(&self) -> Option<Result<Measurement>> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(measurement) = state.queue.pop_front() {
+                self.shared.changed.notify_all();
+                return Some(measurement);
+            }
+            if !state.sender {
+                return None;
+            }
+            state = self.shared.wait(state);
+        }
+    }
+
+    /// Take the next measurement if one is queued, without waiting.
+    pub fn try_recv(&self) -> Option<Result<Measurement>> {
+        let measurement = self.shared.lock().queue.pop_front();
+        if measurement.is_some() {
+            self.shared.changed.notify_all();
+        }
+        measurement
+    }
+
+    /// Number of measurements currently queued.
+    pub fn len(&self) -> usize {
+        self.shared.lock().queue.len()
+    }
+
+    /// Whether no measurement is currently queued.
+    pub fn is_empty(&self) -> bool {
+        self.shared.lock().queue.is_empty()
+    }
+
+    /// Number of measurements dropped so far because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.shared.lock().dropped
+    }
+}
+
+impl Iterator for BoundedReceiver {
+    type Item = Result<Measurement>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+}
+
+impl Drop for BoundedReceiver {
+    /// Stop the worker, waking it up if blocked on a full queue.
+    fn drop(&mut self) {
+        self.shared.lock().receiver = false;
+        self.shared.changed.notify_all();
+    }
+}
+
+/// Statistics collected by the worker of [`HcSr04::spawn_continuous`].
+///
+/// # Fields
+///
+/// - `measurements`: number of measurements performed
+/// - `out_of_range`: number of measurements finding no object in range
+/// - `errors`: number of failed measurements
+/// - `last_error`: error the latest failed measurement failed with, unless handed over to the
+///   handler (see [`HcSr04::start_continuous_with`])
+#[derive(Debug, Default)]
+pub struct ContinuousStats {
+    pub measurements: u64,
+    pub out_of_range: u64,
+    pub errors: u64,
+    pub last_error: Option<Error>,
+}
+
+impl ContinuousStats {
+    pub(crate) fn record(&mut self, measurement: Result<Measurement>) {
+        self.count(&measurement);
+        if let Err(err) = measurement {
+            self.last_error = Some(err);
+        }
+    }
+
+    /// Count `measurement`, without keeping the error it failed with.
+    pub(crate) fn count(&mut self, measurement: &Result<Measurement>) {
+        self.measurements += 1;
+        match measurement {
+            Ok(Measurement { distance: None, .. }) => self.out_of_range += 1,
+            Ok(_) => {}
+            Err(_) => self.errors += 1,
+        }
+    }
+}
+
+/// Stop request shared with the worker of [`HcSr04::spawn_continuous`], waking it up between
+/// measurements.
+#[derive(Debug, Default)]
+struct StopSignal {
+    stopped: Mutex<bool>,
+    changed: Condvar,
+}
+
+impl StopSignal {
+    fn stop(&self) {
+        *self.stopped.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        self.changed.notify_all();
+    }
+
+    fn is_stopped(&self) -> bool {
+        *self.stopped.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Wait for `timeout`, returning early if stopped.
+    fn wait(&self, timeout: Duration) {
+        let stopped = self.stopped.lock().unwrap_or_else(PoisonError::into_inner);
+        // Whether stopped or timed out, the caller checks the flag again.
+        let _ = self
+            .changed
+            .wait_timeout_while(stopped, timeout, |stopped| !*stopped);
+    }
+}
+
+/// Handle to the worker thread of [`HcSr04::spawn_continuous`].
+///
+/// Dropping the handle asks the worker to stop, without waiting for it, unless detached (see
+/// [`ContinuousWorker::detach`]).
+#[derive(Debug)]
+#[must_use = "dropping the worker stops it; call .detach() to keep it running"]
+pub struct ContinuousWorker {
+    signal: Arc<StopSignal>,
+    thread: Option<JoinHandle<ContinuousStats>>,
+}
+
+impl ContinuousWorker {
+    /// Stop the worker gracefully: wait for the in-flight measurement to complete and for the
+    /// handler and the sensor to be dropped, returning the collected statistics.
+    ///
+    /// # Panics
+    ///
+    /// Resumes the panic of the worker thread, if it panicked.
+    pub fn stop(mut self) -> ContinuousStats {
+        self.signal.stop();
+        self.join_thread()
+    }
+
+    /// Wait for the worker to stop on its own, i.e. once the handler returns `false`, returning
+    /// the collected statistics.
+    ///
+    /// # Panics
+    ///
+    /// Resumes the panic of the worker thread, if it panicked.
+    pub fn join(mut self) -> ContinuousStats {
+        self.join_thread()
+    }
+
+    /// Whether the worker stopped.
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().map_or(true, JoinHandle::is_finished)
+    }
+
+    /// Let the worker run on its own, until its handler returns `false` or the program exits,
+    /// giving up on stopping it and collecting its statistics.
+    pub fn detach(mut self) {
+        self.thread = None;
+    }
+
+    fn join_thread(&mut self) -> ContinuousStats {
+        let thread = self.thread.take().expect("worker thread joined once");
+        match thread.join() {
+            Ok(stats) => stats,
+            Err(panic) => panic::resume_unwind(panic),
+        }
+    }
+}
+
+impl Drop for ContinuousWorker {
+    fn drop(&mut self) {
+        // Detached workers keep running.
+        if self.thread.is_some() {
+            self.signal.stop();
+        }
+    }
+}
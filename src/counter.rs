@@ -0,0 +1,203 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! Doorway people counting.
+//!
+//! [`PeopleCounter`] coordinates two sensors placed side by side across a doorway, one on the
+//! outer and one on the inner side, each detecting people crossing its beam as a
+//! [`PresenceDetector`] does. The sensor broken first tells the direction of each passage, which
+//! is counted once both beams are clear again, provided both have been broken: people stepping in
+//! the doorway and turning back are not counted. The sensors are pinged one at a time, as an
+//! [`HcSr04Array`] does, so that they don't pick up each other's bursts.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{
+//!     counter::{Crossing, PeopleCounter},
+//!     Distance, HcSr04, Unit,
+//! };
+//!
+//! let mut counter = PeopleCounter::new(
+//!     HcSr04::new(24, 23, None).unwrap(), // outer
+//!     HcSr04::new(17, 27, None).unwrap(), // inner
+//!     Distance::new(80., Unit::Centimeters),
+//!     Distance::new(10., Unit::Centimeters),
+//! );
+//!
+//! loop {
+//!     match counter.poll().unwrap() {
+//!         Some(Crossing::In) => println!("Entered, {} inside", counter.occupancy()),
+//!         Some(Crossing::Out) => println!("Exited, {} inside", counter.occupancy()),
+//!         None => {}
+//!     }
+//! }
+//! ```
+
+use crate::{
+    backend::Backend,
+    presence::{Presence, PresenceDetector},
+    DefaultBackend, Distance, Float, HcSr04, HcSr04Array, Result, Unit,
+};
+use std::time::Duration;
+
+/// Direction of a doorway passage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Crossing {
+    /// Passage from the outer to the inner side.
+    In,
+    /// Passage from the inner to the outer side.
+    Out,
+}
+
+/// Two-sensor doorway people counter.
+///
+/// # Fields
+///
+/// - `sensors`: outer and inner sensors, measuring one at a time
+/// - `outer`: presence detector of the outer sensor
+/// - `inner`: presence detector of the inner sensor
+/// - `first`: passage in progress, with the side whose beam broke first, if any
+/// - `crossed`: whether the other beam has been broken as well during the passage in progress
+/// - `count_in`: number of passages from the outer to the inner side
+/// - `count_out`: number of passages from the inner to the outer side
+#[derive(Debug)]
+pub struct PeopleCounter<B = DefaultBackend> {
+    sensors: HcSr04Array<B>,
+    outer: PresenceDetector,
+    inner: PresenceDetector,
+    first: Option<Crossing>,
+    crossed: bool,
+    count_in: u64,
+    count_out: u64,
+}
+
+impl<B: Backend> PeopleCounter<B> {
+    /// Create a new counter from the `outer` and `inner` sensors, whose beams are broken by
+    /// people closer than `threshold`, and clear again once farther than
+    /// `threshold + hysteresis`.
+    pub fn new(
+        outer: HcSr04<B>,
+        inner: HcSr04<B>,
+        threshold: Distance,
+        hysteresis: Distance,
+    ) -> Self {
+        let detector = PresenceDetector::new(threshold, hysteresis);
+        Self {
+            sensors: HcSr04Array::new(vec![outer, inner]),
+            outer: detector,
+            inner: detector,
+            first: None,
+            crossed: false,
+            count_in: 0,
+            count_out: 0,
+        }
+    }
+
+    /// Require `readings` consecutive readings (at least one, the default) to confirm a beam
+    /// being broken or clear again.
+    pub fn debounce(mut self, readings: u32) -> Self {
+        self.outer = self.outer.debounce(readings);
+        self.inner = self.inner.debounce(readings);
+        self
+    }
+
+    /// Minimum delay between the end of a measurement and the next ping (defaults to *60ms*, see
+    /// [`HcSr04Array::with_delay`]).
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.sensors = self.sensors.with_delay(delay);
+        self
+    }
+
+    /// Measure distance through both sensors, one at a time, returning the direction of the
+    /// passage once completed.
+    pub fn poll(&mut self) -> Result<Option<Crossing>> {
+        let distances = self.sensors.measure_distance(Unit::Meters)?;
+        let distance = |index: usize| distances[index].map(Distance::from_meters);
+        self.outer.update(distance(0));
+        self.inner.update(distance(1));
+
+        Ok(self.update())
+    }
+
+    /// Advance the passage state machine given the current beam states.
+    fn update(&mut self) -> Option<Crossing> {
+        let outer = self.outer.state() == Presence::Present;
+        let inner = self.inner.state() == Presence::Present;
+
+        let first = match self.first {
+            Some(first) => first,
+            None => {
+                self.first = match (outer, inner) {
+                    (true, false) => Some(Crossing::In),
+                    (false, true) => Some(Crossing::Out),
+                    // Both beams broken at once: direction unknown, wait for them to clear.
+                    _ => None,
+                };
+                return None;
+            }
+        };
+
+        match first {
+            Crossing::In => self.crossed |= inner,
+            Crossing::Out => self.crossed |= outer,
+        }
+        if outer || inner {
+            return None;
+        }
+
+        let crossed = self.crossed;
+        self.first = None;
+        self.crossed = false;
+        if !crossed {
+            return None;
+        }
+        match first {
+            Crossing::In => self.count_in += 1,
+            Crossing::Out => self.count_out += 1,
+        }
+        Some(first)
+    }
+
+    /// Number of passages from the outer to the inner side.
+    pub fn count_in(&self) -> u64 {
+        self.count_in
+    }
+
+    /// Number of passages from the inner to the outer side.
+    pub fn count_out(&self) -> u64 {
+        self.count_out
+    }
+
+    /// Number of people inside: passages in minus passages out.
+    pub fn occupancy(&self) -> i64 {
+        self.count_in as i64 - self.count_out as i64
+    }
+
+    /// Calibrate both sensors with the given **ambient temperature** (`temp`) expressed as
+    /// *Celsius degrees*.
+    pub fn calibrate(&mut self, temp: Float) {
+        self.sensors.calibrate(temp);
+    }
+
+    /// Reset the counts to zero, discarding any passage in progress.
+    pub fn reset(&mut self) {
+        self.outer.reset();
+        self.inner.reset();
+        self.first = None;
+        self.crossed = false;
+        self.count_in = 0;
+        self.count_out = 0;
+    }
+}
@@ -0,0 +1,196 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! D-Bus service, available with the `dbus` feature.
+//!
+//! [`DbusService`] exposes the sensor on the system (or session) bus as the
+//! `/io/github/marcoradocchia/HcSr04` object, owning the `io.github.marcoradocchia.HcSr04` name,
+//! so that desktop and home-automation processes on the same machine can consume it. The object
+//! implements the `io.github.marcoradocchia.HcSr04` interface:
+//!
+//! - method `Measure() -> d`: measure distance in m (`NaN` if no object is in range), failing with
+//!   `org.freedesktop.DBus.Error.Failed` if the measurement fails
+//! - signal `DistanceChanged(d)`: distance in m (`NaN` if no object is in range), emitted by
+//!   [`DbusService::watch`] whenever it changes
+//!
+//! ```rust,no_run
+//! use hc_sr04::{dbus::DbusService, Distance, HcSr04Handle, Unit};
+//! use std::time::Duration;
+//!
+//! let ultrasonic = HcSr04Handle::new(24, 23, None).unwrap();
+//!
+//! DbusService::system(ultrasonic)
+//!     .unwrap()
+//!     .watch(Duration::from_millis(500), Distance::new(1., Unit::Centimeters))
+//!     .join();
+//! ```
+//!
+//! ```sh
+//! busctl call io.github.marcoradocchia.HcSr04 /io/github/marcoradocchia/HcSr04 \
+//!     io.github.marcoradocchia.HcSr04 Measure
+//! ```
+//!
+//! Owning a name on the system bus requires a D-Bus policy allowing it, e.g. installed in
+//! `/etc/dbus-1/system.d/`.
+
+use crate::{
+    backend::Backend,
+    continuous::{self, ContinuousWorker},
+    Distance, HcSr04Handle, Result,
+};
+use std::{fmt, sync::Arc, time::Duration};
+use zbus::{
+    blocking::{connection, Connection},
+    fdo, interface,
+    object_server::SignalEmitter,
+};
+
+/// Well-known bus name owned by the service.
+pub const BUS_NAME: &str = "io.github.marcoradocchia.HcSr04";
+/// Path of the sensor object.
+pub const OBJECT_PATH: &str = "/io/github/marcoradocchia/HcSr04";
+/// Name of the interface implemented by the sensor object.
+pub const INTERFACE: &str = "io.github.marcoradocchia.HcSr04";
+
+/// Distance measurement on the shared sensor, regardless of its backend.
+type Measure = Arc<dyn Fn() -> Result<Option<Distance>> + Send + Sync>;
+
+/// Sensor object exported on the bus.
+struct Sensor {
+    measure: Measure,
+}
+
+#[interface(name = "io.github.marcoradocchia.HcSr04")]
+impl Sensor {
+    /// Measure distance in m (`NaN` if no object is in range).
+    #[zbus(out_args("distance"))]
+    fn measure(&self) -> fdo::Result<f64> {
+        (self.measure)()
+            .map(meters)
+            .map_err(|error| fdo::Error::Failed(error.to_string()))
+    }
+
+    /// Distance in m changed (`NaN` if no object is in range).
+    #[zbus(signal)]
+    async fn distance_changed(emitter: &SignalEmitter<'_>, distance: f64) -> zbus::Result<()>;
+}
+
+/// Distance in m as sent over the bus, `NaN` if no object is in range.
+// `Float` is already `f64` with the `f64` feature.
+#[allow(clippy::useless_conversion)]
+fn meters(distance: Option<Distance>) -> f64 {
+    distance.map_or(f64::NAN, |distance| f64::from(distance.as_meters()))
+}
+
+/// Sensor exported on D-Bus, obtained through [`DbusService::system`] or
+/// [`DbusService::session`].
+///
+/// The service is served as long as it isn't dropped.
+///
+/// # Fields
+///
+/// - `connection`: bus connection the sensor object is served on
+/// - `measure`: distance measurement on the shared sensor
+/// - `jitter`: maximum random spread of the gap between pings of [`DbusService::watch`]
+pub struct DbusService {
+    connection: Connection,
+    measure: Measure,
+    jitter: Option<Duration>,
+}
+
+impl DbusService {
+    /// Export `sensor` on the system bus.
+    pub fn system<B: Backend + Send + 'static>(sensor: HcSr04Handle<B>) -> Result<Self> {
+        Self::serve(connection::Builder::system()?, sensor)
+    }
+
+    /// Export `sensor` on the session bus, e.g. for testing without a system bus policy.
+    pub fn session<B: Backend + Send + 'static>(sensor: HcSr04Handle<B>) -> Result<Self> {
+        Self::serve(connection::Builder::session()?, sensor)
+    }
+
+    fn serve<B: Backend + Send + 'static>(
+        builder: connection::Builder<'_>,
+        sensor: HcSr04Handle<B>,
+    ) -> Result<Self> {
+        let jitter = sensor.with(|sensor| sensor.jitter());
+        let measure: Measure = Arc::new(move || sensor.measure());
+        let connection = builder
+            .name(BUS_NAME)?
+            .serve_at(
+                OBJECT_PATH,
+                Sensor {
+                    measure: measure.clone(),
+                },
+            )?
+            .build()?;
+
+        Ok(Self {
+            connection,
+            measure,
+            jitter,
+        })
+    }
+
+    /// Bus connection the sensor object is served on, e.g. to export further objects.
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Emit the `DistanceChanged` signal for `distance` (`None` if no object is in range).
+    pub fn distance_changed(&self, distance: Option<Distance>) -> Result<()> {
+        Ok(self.connection.emit_signal(
+            None::<()>,
+            OBJECT_PATH,
+            INTERFACE,
+            "DistanceChanged",
+            &meters(distance),
+        )?)
+    }
+
+    /// Start measuring distance every `interval` on a worker thread, emitting `DistanceChanged`
+    /// whenever the distance moves by at least `threshold` since the last emitted one, or an
+    /// object enters or leaves the measuring range.
+    ///
+    /// Failed measurements are skipped. The worker keeps serving the sensor until stopped
+    /// through the returned [`ContinuousWorker`], which drops the service.
+    pub fn watch(self, interval: Duration, threshold: Distance) -> ContinuousWorker {
+        let jitter = self.jitter;
+        let mut emitted: Option<Option<Distance>> = None;
+        continuous::spawn_loop(self, interval, jitter, move |service, _| {
+            if let Ok(distance) = (service.measure)() {
+                let changed = match (emitted, distance) {
+                    (Some(Some(last)), Some(distance)) => last.abs_diff(distance) >= threshold,
+                    (Some(last), distance) => last.is_some() != distance.is_some(),
+                    (None, _) => true,
+                };
+                if changed && service.distance_changed(distance).is_ok() {
+                    emitted = Some(distance);
+                }
+            }
+            true
+        })
+    }
+}
+
+impl fmt::Debug for DbusService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DbusService")
+            .field("connection", &self.connection)
+            .field("jitter", &self.jitter)
+            .finish()
+    }
+}
@@ -0,0 +1,201 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! Self-test and wiring diagnostics.
+//!
+//! [`HcSr04::self_test`] pings the sensor a few times and reports a [`SelfTest`]: how the
+//! **ECHO** pin responded, the noise of the measured distances and a [`Diagnosis`] telling
+//! wiring faults (no echo ever, **ECHO** pin stuck high, implausible pulse timing) apart from a
+//! healthy sensor, before suspecting the software.
+//!
+//! ```rust
+//! # #[cfg(feature = "mock")]
+//! # {
+//! use hc_sr04::{diagnostics::Diagnosis, MockHcSr04, Unit};
+//!
+//! let mut ultrasonic = MockHcSr04::mock(None);
+//! for _ in 0..10 {
+//!     ultrasonic.push_distance(1., Unit::Meters);
+//! }
+//! let report = ultrasonic.self_test().unwrap();
+//! assert_eq!(report.diagnosis, Diagnosis::Healthy);
+//! assert_eq!(report.echoes, 10);
+//! assert!(report.noise.unwrap().as_meters() < 0.001);
+//!
+//! // Unpowered sensor, or TRIGGER and ECHO swapped.
+//! for _ in 0..10 {
+//!     ultrasonic.push_no_echo();
+//! }
+//! let report = ultrasonic.self_test().unwrap();
+//! assert_eq!(report.diagnosis, Diagnosis::NoEcho);
+//! println!("{}", report.diagnosis);
+//!
+//! ultrasonic.set_echo_high(true);
+//! let report = ultrasonic.self_test().unwrap();
+//! assert_eq!(report.diagnosis, Diagnosis::EchoStuckHigh);
+//! # }
+//! ```
+
+use crate::{backend::Backend, error::Error, stats::Stats, Distance, HcSr04, Result};
+use std::{fmt, time::Duration};
+
+/// Number of pings performed by the self-test.
+const PINGS: u32 = 10;
+/// **ECHO** pulse start timeout during the self-test, unless one is configured.
+const START_TIMEOUT: Duration = Duration::from_millis(20);
+
+/// Outcome of the self-test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diagnosis {
+    /// The **ECHO** pin responded to every ping with plausible pulses (or no object was in
+    /// range).
+    Healthy,
+    /// The **ECHO** pin never responded to the **TRIGGER** pulse.
+    NoEcho,
+    /// The **ECHO** pin is high while idle.
+    EchoStuckHigh,
+    /// The **ECHO** pin responded to some pings only.
+    Intermittent,
+    /// Most **ECHO** pulses are shorter than the minimum measuring range allows.
+    ImplausibleTiming,
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Healthy => write!(f, "sensor responding normally"),
+            Self::NoEcho => write!(
+                f,
+                "no ECHO pulse: check power, ground and that TRIGGER and ECHO aren't swapped"
+            ),
+            Self::EchoStuckHigh => write!(
+                f,
+                "ECHO pin stuck high: check for a short to VCC or a locked-up sensor"
+            ),
+            Self::Intermittent => write!(f, "ECHO pulse missing at times: check for loose wiring"),
+            Self::ImplausibleTiming => write!(
+                f,
+                "implausibly short ECHO pulses: check for crosstalk between TRIGGER and ECHO or a \
+                 missing level shifter"
+            ),
+        }
+    }
+}
+
+/// Result of [`HcSr04::self_test`].
+///
+/// # Fields
+///
+/// - `diagnosis`: outcome of the self-test
+/// - `pings`: number of pings performed
+/// - `echoes`: number of pings answered by an **ECHO** pulse within the maximum range
+/// - `out_of_range`: number of pings with no object in range
+/// - `no_echo`: number of pings whose **ECHO** pulse never started
+/// - `too_short`: number of **ECHO** pulses shorter than the minimum measuring range allows
+/// - `mean`: mean measured distance, `None` without echoes
+/// - `noise`: standard deviation of the measured distances, `None` without echoes
+/// - `echo_high`: whether the **ECHO** pin was high while idle, `None` if the backend cannot
+///   read it (see [`Backend::echo_level`])
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelfTest {
+    pub diagnosis: Diagnosis,
+    pub pings: u32,
+    pub echoes: u32,
+    pub out_of_range: u32,
+    pub no_echo: u32,
+    pub too_short: u32,
+    pub mean: Option<Distance>,
+    pub noise: Option<Distance>,
+    pub echo_high: Option<bool>,
+}
+
+impl<B: Backend> HcSr04<B> {
+    /// Check the sensor wiring: read the idle **ECHO** pin level, ping the sensor 10 times
+    /// (waiting at most *20ms* for every **ECHO** pulse to start, unless a start timeout is
+    /// configured) and diagnose the responses, measuring the noise of the distances, filters
+    /// aside.
+    ///
+    /// Fails as [`HcSr04::measure_echo`] does, except for **ECHO** pulse timeouts, which are
+    /// diagnosed instead. Keep the scene still while testing, so that noise reflects the sensor
+    /// only.
+    pub fn self_test(&mut self) -> Result<SelfTest> {
+        let idle_high = self.backend.echo_level();
+
+        let start_timeout = self.timing.start_timeout.unwrap_or(START_TIMEOUT);
+        let configured = self.timing.start_timeout.replace(start_timeout);
+        // Diagnose silent pings rather than failing on them.
+        let detection = self.disconnect_detection.take();
+        let pings = self.self_test_pings();
+        self.timing.start_timeout = configured;
+        self.disconnect_detection = detection;
+        let (mut report, stats) = pings?;
+
+        // Stuck either before or after pinging.
+        report.echo_high = match (idle_high, self.backend.echo_level()) {
+            (Some(before), Some(after)) => Some(before || after),
+            (before, after) => before.or(after),
+        };
+        report.mean = stats.mean();
+        report.noise = stats.std_dev();
+        report.diagnosis = if report.echo_high == Some(true) {
+            Diagnosis::EchoStuckHigh
+        } else if report.no_echo == report.pings {
+            Diagnosis::NoEcho
+        } else if report.no_echo > 0 {
+            Diagnosis::Intermittent
+        } else if report.too_short * 2 > report.echoes + report.too_short {
+            Diagnosis::ImplausibleTiming
+        } else {
+            Diagnosis::Healthy
+        };
+
+        Ok(report)
+    }
+
+    /// Ping the sensor for [`HcSr04::self_test`], counting the responses.
+    fn self_test_pings(&mut self) -> Result<(SelfTest, Stats)> {
+        let mut report = SelfTest {
+            diagnosis: Diagnosis::Healthy,
+            pings: PINGS,
+            echoes: 0,
+            out_of_range: 0,
+            no_echo: 0,
+            too_short: 0,
+            mean: None,
+            noise: None,
+            echo_high: None,
+        };
+        let mut stats = Stats::new(PINGS as usize);
+
+        for _ in 0..PINGS {
+            match self.measure_echo() {
+                Ok(Some(echo)) => {
+                    let meters = self.corrected_meters(echo);
+                    if meters < self.min_range() {
+                        report.too_short += 1;
+                    } else {
+                        report.echoes += 1;
+                        stats.push(Distance::from_meters(meters));
+                    }
+                }
+                Ok(None) | Err(Error::EchoPulseTimeout) => report.out_of_range += 1,
+                Err(Error::EchoStartTimeout) => report.no_echo += 1,
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok((report, stats))
+    }
+}
@@ -0,0 +1,251 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Unit-safe distance value.
+
+use crate::{portable::abs, Float, Unit};
+use core::{
+    fmt::{self, Display, Formatter},
+    iter::Sum,
+    ops::{Add, AddAssign, Div, Mul, Sub, SubAssign},
+};
+
+/// Distance, stored in meters and converted to any [`Unit`] on demand.
+///
+/// Distances can be compared, added and subtracted with each other, and scaled by `Float`
+/// factors, so that threshold logic never mixes up units. With the `serde` feature enabled,
+/// distances are serialized as plain numbers of meters.
+///
+/// ```rust
+/// use hc_sr04::{Distance, Unit};
+///
+/// let threshold = Distance::new(120., Unit::Centimeters);
+/// let measured = Distance::from_meters(1.) + Distance::new(5., Unit::Millimeters);
+///
+/// assert!(measured < threshold);
+/// assert!((measured.as_cm() - 100.5).abs() < 1e-4);
+///
+/// let margin = threshold - measured;
+/// assert!((margin.as_mm() - 195.).abs() < 0.01);
+/// ```
+///
+/// With the `uom` feature enabled, distances convert to and from `Length` quantities, so that
+/// the crate plugs into dimensional analysis codebases without manual conversion factors. With
+/// the `measurements` feature enabled, they convert to and from `measurements::Length` as well.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Distance(Float);
+
+/// [uom](https://docs.rs/uom) length quantity of the crate's [`Float`] precision, available with
+/// the `uom` feature.
+#[cfg(all(feature = "uom", not(feature = "f64")))]
+pub type Length = uom::si::f32::Length;
+/// [uom](https://docs.rs/uom) length quantity of the crate's [`Float`] precision, available with
+/// the `uom` feature.
+#[cfg(all(feature = "uom", feature = "f64"))]
+pub type Length = uom::si::f64::Length;
+
+impl Distance {
+    /// Zero distance.
+    pub const ZERO: Self = Self(0.);
+
+    /// Create a distance of `value` expressed as `unit`.
+    pub fn new(value: Float, unit: Unit) -> Self {
+        Self(value / unit.per_meter())
+    }
+
+    /// Create a distance of `meters`.
+    pub const fn from_meters(meters: Float) -> Self {
+        Self(meters)
+    }
+
+    /// Distance expressed as `unit`.
+    pub fn as_unit(self, unit: Unit) -> Float {
+        self.0 * unit.per_meter()
+    }
+
+    /// Distance in millimeters.
+    pub fn as_mm(self) -> Float {
+        self.as_unit(Unit::Millimeters)
+    }
+
+    /// Distance in centimeters.
+    pub fn as_cm(self) -> Float {
+        self.as_unit(Unit::Centimeters)
+    }
+
+    /// Distance in decimeters.
+    pub fn as_dm(self) -> Float {
+        self.as_unit(Unit::Decimeters)
+    }
+
+    /// Distance in meters.
+    pub const fn as_meters(self) -> Float {
+        self.0
+    }
+
+    /// Distance in inches.
+    pub fn as_inches(self) -> Float {
+        self.as_unit(Unit::Inches)
+    }
+
+    /// Distance in feet.
+    pub fn as_feet(self) -> Float {
+        self.as_unit(Unit::Feet)
+    }
+
+    /// Distance in yards.
+    pub fn as_yards(self) -> Float {
+        self.as_unit(Unit::Yards)
+    }
+
+    /// Absolute difference between two distances.
+    pub fn abs_diff(self, other: Self) -> Self {
+        Self(abs(self.0 - other.0))
+    }
+}
+
+/// Formats the distance in meters, honoring the requested precision (e.g. `{:.2}`).
+impl Display for Distance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*}m", precision, self.0),
+            None => write!(f, "{}m", self.0),
+        }
+    }
+}
+
+impl Add for Distance {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for Distance {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sub for Distance {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl SubAssign for Distance {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Mul<Float> for Distance {
+    type Output = Self;
+
+    fn mul(self, rhs: Float) -> Self {
+        Self(self.0 * rhs)
+    }
+}
+
+impl Mul<Distance> for Float {
+    type Output = Distance;
+
+    fn mul(self, rhs: Distance) -> Distance {
+        Distance(self * rhs.0)
+    }
+}
+
+impl Div<Float> for Distance {
+    type Output = Self;
+
+    fn div(self, rhs: Float) -> Self {
+        Self(self.0 / rhs)
+    }
+}
+
+/// Ratio between two distances.
+impl Div for Distance {
+    type Output = Float;
+
+    fn div(self, rhs: Self) -> Float {
+        self.0 / rhs.0
+    }
+}
+
+impl Sum for Distance {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        Self(iter.map(|distance| distance.0).sum())
+    }
+}
+
+/// ```rust
+/// # #[cfg(feature = "uom")]
+/// # {
+/// use hc_sr04::{distance::Length, Distance};
+/// use uom::si::length::centimeter;
+///
+/// let length = Length::from(Distance::from_meters(1.5));
+/// assert!((length.get::<centimeter>() - 150.).abs() < 1e-3);
+/// assert_eq!(Distance::from(length), Distance::from_meters(1.5));
+/// # }
+/// ```
+#[cfg(feature = "uom")]
+impl From<Distance> for Length {
+    fn from(distance: Distance) -> Self {
+        Self::new::<uom::si::length::meter>(distance.0)
+    }
+}
+
+#[cfg(feature = "uom")]
+impl From<Length> for Distance {
+    fn from(length: Length) -> Self {
+        Self(length.get::<uom::si::length::meter>())
+    }
+}
+
+/// ```rust
+/// # #[cfg(feature = "measurements")]
+/// # {
+/// use hc_sr04::Distance;
+/// use measurements::Length;
+///
+/// let length = Length::from(Distance::from_meters(1.5));
+/// assert!((length.as_centimeters() - 150.).abs() < 1e-3);
+/// assert_eq!(Distance::from(length), Distance::from_meters(1.5));
+/// # }
+/// ```
+#[cfg(feature = "measurements")]
+impl From<Distance> for measurements::Length {
+    // `Float` is already `f64` with the `f64` feature.
+    #[allow(clippy::useless_conversion)]
+    fn from(distance: Distance) -> Self {
+        Self::from_meters(f64::from(distance.0))
+    }
+}
+
+#[cfg(feature = "measurements")]
+impl From<measurements::Length> for Distance {
+    // `Float` is already `f64` with the `f64` feature.
+    #[allow(clippy::unnecessary_cast)]
+    fn from(length: measurements::Length) -> Self {
+        Self(length.as_meters() as Float)
+    }
+}
@@ -0,0 +1,173 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! Door open/close detection.
+//!
+//! [`DoorSensor`] wraps an [`HcSr04`] placed alongside a door, with its action line parallel to
+//! the wall housing the door, so that opening the door creates an obstacle closer than a
+//! threshold distance. Readings go through a [`PresenceDetector`], with hysteresis and
+//! debouncing, and transitions are reported as [`DoorEvent`]s, either polling the sensor or
+//! iterating over [`DoorSensor::events`].
+//!
+//! ```rust,no_run
+//! use hc_sr04::{
+//!     door::{DoorEvent, DoorSensor},
+//!     Distance, HcSr04, Unit,
+//! };
+//!
+//! let ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//! let mut door = DoorSensor::new(
+//!     ultrasonic,
+//!     Distance::new(1.2, Unit::Meters),
+//!     Distance::new(10., Unit::Centimeters),
+//! )
+//! .debounce(2);
+//!
+//! for event in door.events() {
+//!     match event.unwrap() {
+//!         DoorEvent::Opened => println!("Door opened!"),
+//!         DoorEvent::Closed => println!("Door closed!"),
+//!     }
+//! }
+//! ```
+
+use crate::{
+    backend::Backend,
+    presence::{Presence, PresenceDetector},
+    DefaultBackend, Distance, HcSr04, Result,
+};
+use std::{thread, time::Duration};
+
+/// Default time between consecutive readings of [`DoorSensor::events`].
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Door state transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DoorEvent {
+    /// The door has been opened.
+    Opened,
+    /// The door has been closed.
+    Closed,
+}
+
+/// Door sensor, reporting door openings and closings.
+///
+/// # Fields
+///
+/// - `sensor`: underlying HC-SR04 sensor
+/// - `detector`: presence detector the readings go through
+/// - `inverted`: whether an object closer than the threshold means the door is closed
+/// - `interval`: time between consecutive readings of [`DoorSensor::events`]
+#[derive(Debug)]
+pub struct DoorSensor<B = DefaultBackend> {
+    sensor: HcSr04<B>,
+    detector: PresenceDetector,
+    inverted: bool,
+    interval: Duration,
+}
+
+impl<B: Backend> DoorSensor<B> {
+    /// Create a new door sensor on top of `sensor`, reporting the door as opened when an object
+    /// is closer than `threshold`, and as closed again once farther than
+    /// `threshold + hysteresis`. Starts with the door closed.
+    pub fn new(sensor: HcSr04<B>, threshold: Distance, hysteresis: Distance) -> Self {
+        Self {
+            sensor,
+            detector: PresenceDetector::new(threshold, hysteresis),
+            inverted: false,
+            interval: DEFAULT_INTERVAL,
+        }
+    }
+
+    /// Require `readings` consecutive readings (at least one, the default) to confirm a
+    /// transition.
+    pub fn debounce(mut self, readings: u32) -> Self {
+        self.detector = self.detector.debounce(readings);
+        self
+    }
+
+    /// Report the door as closed, rather than opened, while an object is closer than the
+    /// threshold (disabled by default), e.g. with the sensor facing the door leaf. Starts with
+    /// the door opened.
+    pub fn inverted(mut self, inverted: bool) -> Self {
+        self.inverted = inverted;
+        self
+    }
+
+    /// Time between consecutive readings of [`DoorSensor::events`] (defaults to *100ms*).
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Whether the door is currently open.
+    pub fn is_open(&self) -> bool {
+        (self.detector.state() == Presence::Present) != self.inverted
+    }
+
+    /// Measure distance through the sensor, returning the door event on transitions.
+    pub fn poll(&mut self) -> Result<Option<DoorEvent>> {
+        Ok(self.detector.poll(&mut self.sensor)?.map(|_| {
+            if self.is_open() {
+                DoorEvent::Opened
+            } else {
+                DoorEvent::Closed
+            }
+        }))
+    }
+
+    /// Endless iterator over door events, polling the sensor every interval (see
+    /// [`DoorSensor::interval`]) until a transition occurs. Failed measurements are yielded as
+    /// errors, the iteration can go on afterwards.
+    pub fn events(&mut self) -> Events<'_, B> {
+        Events { door: self }
+    }
+
+    /// Underlying sensor, e.g. for calibration.
+    pub fn sensor_mut(&mut self) -> &mut HcSr04<B> {
+        &mut self.sensor
+    }
+
+    /// Return the underlying sensor.
+    pub fn into_inner(self) -> HcSr04<B> {
+        self.sensor
+    }
+
+    /// Discard the detector state, back to the door closed (or opened, if inverted).
+    pub fn reset(&mut self) {
+        self.detector.reset();
+    }
+}
+
+/// Endless iterator over [`DoorEvent`]s, obtained through [`DoorSensor::events`].
+#[derive(Debug)]
+pub struct Events<'a, B = DefaultBackend> {
+    door: &'a mut DoorSensor<B>,
+}
+
+impl<B: Backend> Iterator for Events<'_, B> {
+    type Item = Result<DoorEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.door.poll() {
+                Ok(Some(event)) => return Some(Ok(event)),
+                Ok(None) => thread::sleep(self.door.interval),
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
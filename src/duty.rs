@@ -0,0 +1,151 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! Duty-cycled low-power sampling, for battery or solar powered deployments.
+//!
+//! [`HcSr04::spawn_duty_cycled`] measures distance on a worker thread as
+//! [`HcSr04::spawn_continuous`] does, but keeps the sensor powered only around the scheduled
+//! pings: every [`DutyCycle`] interval, the sensor is powered on, left to warm up, pinged a few
+//! times and powered off again, the worker sleeping in the meantime.
+//!
+//! The sensor's supply is switched through the power-enable pin (see
+//! [`HcSr04Builder::power_pin`](crate::HcSr04Builder::power_pin)): with backends not switching
+//! it, the sensor is simply pinged on schedule.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{duty::DutyCycle, HcSr04};
+//! use std::time::Duration;
+//!
+//! let ultrasonic = HcSr04::builder(24, 23).power_pin(25).build().unwrap();
+//! let schedule = DutyCycle::new(Duration::from_secs(300))
+//!     .pings(3)
+//!     .warm_up(Duration::from_millis(50));
+//!
+//! let worker = ultrasonic.spawn_duty_cycled(schedule, |measurement| {
+//!     println!("{:?}", measurement.as_ref().map(|m| m.distance));
+//!     true
+//! });
+//! worker.join();
+//! ```
+
+use crate::{backend::Backend, continuous::ContinuousWorker, HcSr04, Measurement, Result};
+use std::time::Duration;
+
+/// Schedule of [`HcSr04::spawn_duty_cycled`]: how often the sensor is woken up, how many times
+/// it is pinged while powered and how long it takes to warm up.
+///
+/// # Fields
+///
+/// - `interval`: time between consecutive wake-ups
+/// - `pings`: number of pings performed at every wake-up
+/// - `warm_up`: time the sensor takes to become operational after power-on, overriding the
+///   sensor's one, if set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DutyCycle {
+    interval: Duration,
+    pings: u32,
+    warm_up: Option<Duration>,
+}
+
+impl DutyCycle {
+    /// Wake the sensor up every `interval`, pinging it once.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            pings: 1,
+            warm_up: None,
+        }
+    }
+
+    /// Number of pings performed at every wake-up (at least 1, the default), e.g. to filter
+    /// them, spaced by the sensor's minimum measurement cycle.
+    pub fn pings(mut self, pings: u32) -> Self {
+        self.pings = pings.max(1);
+        self
+    }
+
+    /// Time the sensor takes to become operational after power-on, overriding the one set on
+    /// the sensor (see [`HcSr04::set_warm_up`]).
+    pub fn warm_up(mut self, delay: Duration) -> Self {
+        self.warm_up = Some(delay);
+        self
+    }
+}
+
+impl<B: Backend + Send + 'static> HcSr04<B> {
+    /// Start measuring distance on a worker thread as per `schedule`, powering the sensor on
+    /// before the pings of every wake-up and off right after them, and handing every
+    /// measurement, or the error it failed with, to `handler` on the worker thread, until stopped
+    /// through the returned [`ContinuousWorker`] or `handler` returns `false`.
+    ///
+    /// Failing to power the sensor off is handed to `handler` as well, counted as an error but
+    /// not as a measurement. Wake-ups are spread at
+    /// random if enabled (see [`HcSr04Builder::jitter`](crate::HcSr04Builder::jitter)).
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "mock")]
+    /// # {
+    /// use hc_sr04::{duty::DutyCycle, MockHcSr04, Unit};
+    /// use std::time::Duration;
+    ///
+    /// let mut ultrasonic = MockHcSr04::mock(None);
+    /// ultrasonic.push_distance(1., Unit::Meters).push_distance(1.1, Unit::Meters);
+    ///
+    /// let schedule = DutyCycle::new(Duration::from_millis(10)).pings(2);
+    /// let mut remaining = 4;
+    /// let worker = ultrasonic.spawn_duty_cycled(schedule, move |_| {
+    ///     remaining -= 1;
+    ///     remaining > 0
+    /// });
+    ///
+    /// let stats = worker.join();
+    /// assert_eq!(stats.measurements, 4);
+    /// assert_eq!(stats.out_of_range, 2);
+    /// # }
+    /// ```
+    pub fn spawn_duty_cycled<F>(mut self, schedule: DutyCycle, mut handler: F) -> ContinuousWorker
+    where
+        F: FnMut(&Result<Measurement>) -> bool + Send + 'static,
+    {
+        if let Some(delay) = schedule.warm_up {
+            self.set_warm_up(delay);
+        }
+
+        self.spawn_ticks(schedule.interval, move |sensor, stats| {
+            // Measuring powers the sensor on, waiting for it to warm up.
+            for _ in 0..schedule.pings {
+                let measurement = sensor.measurement();
+                let keep = handler(&measurement);
+                stats.record(measurement);
+                if !keep {
+                    // Leave the sensor unpowered anyway.
+                    let _ = sensor.set_powered(false);
+                    return false;
+                }
+            }
+
+            match sensor.set_powered(false) {
+                Ok(()) => true,
+                Err(error) => {
+                    let failure = Err(error);
+                    let keep = handler(&failure);
+                    stats.errors += 1;
+                    stats.last_error = failure.err();
+                    keep
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,247 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! Dwell-time conditions.
+//!
+//! A [`Dwell`] tracks a distance [`Condition`] (an object within a distance, or no object within
+//! it) over timestamped [`Measurement`]s, firing once the condition has held for at least a
+//! given time, so that trigger logic doesn't need hand-rolled timers. The condition is re-armed
+//! as soon as it stops holding.
+//!
+//! ```rust
+//! use hc_sr04::{
+//!     dwell::{Condition, Dwell},
+//!     Distance, Measurement,
+//! };
+//! use std::time::{Duration, SystemTime};
+//!
+//! let start = SystemTime::now();
+//! let measurement = |meters, secs| {
+//!     Measurement::new(
+//!         Some(Distance::from_meters(meters)),
+//!         start + Duration::from_secs(secs),
+//!     )
+//! };
+//!
+//! let mut dwell = Dwell::new(
+//!     Condition::Within(Distance::from_meters(1.)),
+//!     Duration::from_secs(2),
+//! );
+//! assert!(!dwell.update(&measurement(0.5, 0)));
+//! assert!(!dwell.update(&measurement(0.5, 1)));
+//! assert!(dwell.update(&measurement(0.5, 2)));
+//! // Fired once per dwell.
+//! assert!(!dwell.update(&measurement(0.5, 3)));
+//! // Re-armed as soon as the object moves away.
+//! assert!(!dwell.update(&measurement(1.5, 4)));
+//! assert!(!dwell.update(&measurement(0.5, 5)));
+//! ```
+//!
+//! Reactive applications can register callbacks fired on the continuous sampling thread through
+//! [`DwellEvents`] (see [`HcSr04::watch_dwell`]):
+//!
+//! ```rust,no_run
+//! use hc_sr04::{
+//!     dwell::{Condition, DwellEvents},
+//!     Distance, HcSr04,
+//! };
+//! use std::time::Duration;
+//!
+//! let ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//!
+//! let near = Distance::from_meters(0.5);
+//! let events = DwellEvents::new()
+//!     .on(Condition::Within(near), Duration::from_secs(5), |_| {
+//!         println!("Someone is waiting")
+//!     })
+//!     .on(Condition::Cleared(near), Duration::from_secs(30), |_| {
+//!         println!("Nobody around")
+//!     });
+//!
+//! ultrasonic
+//!     .watch_dwell(Duration::from_millis(100), events)
+//!     .join();
+//! ```
+
+use crate::{backend::Backend, continuous::ContinuousWorker, Distance, HcSr04, Measurement};
+use std::{
+    fmt,
+    time::{Duration, SystemTime},
+};
+
+/// Distance condition, evaluated on every measurement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Condition {
+    /// An object is closer than the contained distance.
+    Within(Distance),
+    /// No object is closer than the contained distance (including no object in range).
+    Cleared(Distance),
+}
+
+impl Condition {
+    /// Whether the condition holds given the measured `distance` (`None` if no object is in
+    /// range).
+    pub fn holds(&self, distance: Option<Distance>) -> bool {
+        match (*self, distance) {
+            (Self::Within(limit), Some(distance)) => distance < limit,
+            (Self::Within(_), None) => false,
+            (Self::Cleared(limit), distance) => !Self::Within(limit).holds(distance),
+        }
+    }
+}
+
+/// Dwell-time tracker, firing once its condition has held for at least the dwell time.
+///
+/// # Fields
+///
+/// - `condition`: distance condition
+/// - `duration`: time the condition must hold for before firing
+/// - `since`: timestamp of the first measurement the condition held for, if holding
+/// - `fired`: whether the tracker fired since the condition started holding
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dwell {
+    condition: Condition,
+    duration: Duration,
+    since: Option<SystemTime>,
+    fired: bool,
+}
+
+impl Dwell {
+    /// Create a new tracker firing once `condition` has held for at least `duration`.
+    pub fn new(condition: Condition, duration: Duration) -> Self {
+        Self {
+            condition,
+            duration,
+            since: None,
+            fired: false,
+        }
+    }
+
+    /// Condition being tracked.
+    pub fn condition(&self) -> Condition {
+        self.condition
+    }
+
+    /// Update the tracker with `measurement`, returning `true` if the condition has just held for
+    /// the dwell time.
+    pub fn update(&mut self, measurement: &Measurement) -> bool {
+        if !self.condition.holds(measurement.distance) {
+            self.reset();
+            return false;
+        }
+
+        let since = *self.since.get_or_insert(measurement.timestamp);
+        let held = match measurement.timestamp.duration_since(since) {
+            Ok(held) => held,
+            // The clock went backwards: start over.
+            Err(_) => {
+                self.since = Some(measurement.timestamp);
+                Duration::ZERO
+            }
+        };
+        if self.fired || held < self.duration {
+            return false;
+        }
+
+        self.fired = true;
+        true
+    }
+
+    /// Time the condition has held for as of `now`, `None` if it doesn't currently hold.
+    pub fn held(&self, now: SystemTime) -> Option<Duration> {
+        self.since
+            .map(|since| now.duration_since(since).unwrap_or_default())
+    }
+
+    /// Discard the tracker state, re-arming it.
+    pub fn reset(&mut self) {
+        self.since = None;
+        self.fired = false;
+    }
+}
+
+/// Callback fired once a dwell condition is met.
+type Callback = Box<dyn FnMut(&Measurement) + Send>;
+
+/// Callbacks fired once distance conditions have held for their dwell time.
+///
+/// # Fields
+///
+/// - `dwells`: dwell trackers, along with their callbacks
+pub struct DwellEvents {
+    dwells: Vec<(Dwell, Callback)>,
+}
+
+impl DwellEvents {
+    /// Create a new set of callbacks, with no callback registered.
+    pub fn new() -> Self {
+        Self { dwells: Vec::new() }
+    }
+
+    /// Register `callback`, fired with the triggering measurement once `condition` has held for
+    /// at least `duration`, and again every time it holds as long after having stopped holding.
+    pub fn on(
+        mut self,
+        condition: Condition,
+        duration: Duration,
+        callback: impl FnMut(&Measurement) + Send + 'static,
+    ) -> Self {
+        self.dwells
+            .push((Dwell::new(condition, duration), Box::new(callback)));
+        self
+    }
+
+    /// Update the dwell trackers with `measurement`, firing the callbacks of those whose
+    /// condition has just held for their dwell time, in registration order.
+    pub fn handle(&mut self, measurement: &Measurement) {
+        for (dwell, callback) in &mut self.dwells {
+            if dwell.update(measurement) {
+                callback(measurement);
+            }
+        }
+    }
+}
+
+impl Default for DwellEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for DwellEvents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DwellEvents")
+            .field("dwells", &self.dwells.len())
+            .finish()
+    }
+}
+
+impl<B: Backend + Send + 'static> HcSr04<B> {
+    /// Start measuring distance every `interval` on a worker thread (see
+    /// [`HcSr04::spawn_continuous`]), firing `events` callbacks once their conditions have
+    /// held for their dwell time.
+    ///
+    /// Failed measurements are skipped. The worker runs until stopped through the returned
+    /// [`ContinuousWorker`].
+    pub fn watch_dwell(self, interval: Duration, mut events: DwellEvents) -> ContinuousWorker {
+        self.spawn_continuous(interval, move |measurement| {
+            if let Ok(measurement) = measurement {
+                events.handle(measurement);
+            }
+            true
+        })
+    }
+}
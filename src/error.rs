@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License along with
 // this program. If not, see https://www.gnu.org/licenses/.
 
+#[cfg(feature = "rppal")]
 use rppal::gpio;
 use std::{
     self,
@@ -22,23 +23,233 @@ use std::{
 
 #[derive(Debug)]
 /// HC-SR04 runtime errors.
+#[non_exhaustive]
 pub enum Error {
     /// Occurs on Raspberry Pi GPIO error.
+    #[cfg(feature = "rppal")]
     Gpio(gpio::Error),
+    /// Occurs on embedded-hal pin error.
+    #[cfg(feature = "embedded-hal")]
+    Hal(embedded_hal::digital::ErrorKind),
+    /// Occurs on Raspberry Pi I2C bus error.
+    #[cfg(feature = "i2c")]
+    I2c(rppal::i2c::Error),
+    /// Occurs on Linux GPIO character device error.
+    #[cfg(feature = "gpio-cdev")]
+    Cdev(gpio_cdev::Error),
+    /// Occurs when the **ECHO** pulse doesn't start within the configured timeout after the
+    /// **TRIGGER** pulse.
+    EchoStartTimeout,
+    /// Occurs when the **ECHO** pulse doesn't end within the configured timeout, if reporting
+    /// it as an error is enabled (otherwise the object is considered out of range).
+    EchoPulseTimeout,
+    /// Occurs when the measured distance is shorter than the minimum measuring range, where the
+    /// sensor returns implausibly short pulses.
+    TooClose,
+    /// Occurs when a measurement is attempted before the minimum measurement cycle elapsed since
+    /// the previous one, if reporting it as an error is enabled, with the contained time left.
+    CycleTooShort(std::time::Duration),
+    /// Occurs when measuring the deviation from the baseline before capturing it.
+    NoBaseline,
+    /// Occurs when the measurement is cancelled through the sensor's cancel token.
+    Cancelled,
+    /// Occurs when the **ECHO** pin never left its idle level for the configured number of
+    /// consecutive pings, e.g. with the sensor missing, unpowered or locked up.
+    SensorNotResponding,
+    /// Occurs when a measurement request exceeds the rate limit and the limiter rejects it.
+    RateLimited,
+    /// Scripted error returned by the mock sensor.
+    #[cfg(feature = "mock")]
+    Mock(String),
+    /// Occurs when reading a configuration file, reading or writing a calibration profile or a
+    /// recording file, binding the metrics exporter, HTTP endpoint or WebSocket server socket,
+    /// writing to InfluxDB, catching signals or communicating with the pigpio daemon fails.
+    #[cfg(any(
+        feature = "config",
+        feature = "http",
+        feature = "influxdb",
+        feature = "pigpio",
+        feature = "profile",
+        feature = "prometheus",
+        feature = "recorder",
+        feature = "signal",
+        feature = "websocket"
+    ))]
+    Io(std::io::Error),
+    /// Occurs when a calibration profile file is malformed.
+    #[cfg(feature = "profile")]
+    Profile(serde_json::Error),
+    /// Occurs when a configuration file is malformed or holds invalid settings.
+    #[cfg(feature = "config")]
+    Config(toml::de::Error),
+    /// Occurs when a measurement cannot be serialized for publishing or recording.
+    #[cfg(any(feature = "mqtt", feature = "recorder", feature = "websocket"))]
+    Serialize(serde_json::Error),
+    /// Occurs when connecting to the D-Bus bus, owning the service name or emitting a signal
+    /// fails.
+    #[cfg(feature = "dbus")]
+    Dbus(zbus::Error),
+    /// Occurs when the InfluxDB server rejects a write, with the contained HTTP status code.
+    #[cfg(feature = "influxdb")]
+    Influx(u16),
+    /// Occurs when a message cannot be queued for publishing to the MQTT broker.
+    #[cfg(feature = "mqtt")]
+    Mqtt(rumqttc::ClientError),
+    /// Occurs when the pigpio daemon fails a command, with the contained error code.
+    #[cfg(feature = "pigpio")]
+    Pigpio(i32),
+    /// Occurs when the measuring thread cannot be raised to real-time scheduling.
+    #[cfg(feature = "realtime")]
+    Realtime(std::io::Error),
+    /// Occurs when creating the ROS 2 topic or publisher or publishing a message fails, with the
+    /// contained description.
+    #[cfg(feature = "ros2")]
+    Ros2(String),
+    /// Occurs on Raspberry Pi UART error.
+    #[cfg(feature = "us100")]
+    Uart(rppal::uart::Error),
+    /// Occurs when the US-100 doesn't reply to a serial command within the timeout.
+    #[cfg(feature = "us100")]
+    NoReply,
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
+            #[cfg(feature = "rppal")]
             Self::Gpio(error) => write!(f, "GPIO error: {}", error),
+            #[cfg(feature = "embedded-hal")]
+            Self::Hal(kind) => write!(f, "embedded-hal pin error: {}", kind),
+            #[cfg(feature = "i2c")]
+            Self::I2c(error) => write!(f, "I2C error: {}", error),
+            #[cfg(feature = "gpio-cdev")]
+            Self::Cdev(error) => write!(f, "GPIO character device error: {}", error),
+            Self::EchoStartTimeout => write!(f, "timed out waiting for ECHO pulse to start"),
+            Self::EchoPulseTimeout => write!(f, "timed out waiting for ECHO pulse to end"),
+            Self::TooClose => write!(f, "object closer than the minimum measuring range"),
+            Self::CycleTooShort(remaining) => {
+                write!(f, "measurement attempted {:?} too soon", remaining)
+            }
+            Self::NoBaseline => write!(f, "no baseline captured"),
+            Self::Cancelled => write!(f, "measurement cancelled"),
+            Self::SensorNotResponding => {
+                write!(
+                    f,
+                    "sensor not responding: ECHO pin never left its idle level"
+                )
+            }
+            Self::RateLimited => write!(f, "measurement request rejected by rate limit"),
+            #[cfg(feature = "mock")]
+            Self::Mock(message) => write!(f, "mock error: {}", message),
+            #[cfg(any(
+                feature = "config",
+                feature = "http",
+                feature = "influxdb",
+                feature = "pigpio",
+                feature = "profile",
+                feature = "prometheus",
+                feature = "recorder",
+                feature = "signal",
+                feature = "websocket"
+            ))]
+            Self::Io(error) => write!(f, "I/O error: {}", error),
+            #[cfg(feature = "profile")]
+            Self::Profile(error) => write!(f, "calibration profile error: {}", error),
+            #[cfg(feature = "config")]
+            Self::Config(error) => write!(f, "configuration error: {}", error),
+            #[cfg(any(feature = "mqtt", feature = "recorder", feature = "websocket"))]
+            Self::Serialize(error) => write!(f, "serialization error: {}", error),
+            #[cfg(feature = "dbus")]
+            Self::Dbus(error) => write!(f, "D-Bus error: {}", error),
+            #[cfg(feature = "influxdb")]
+            Self::Influx(code) => write!(f, "InfluxDB write rejected with HTTP status {}", code),
+            #[cfg(feature = "mqtt")]
+            Self::Mqtt(error) => write!(f, "MQTT client error: {}", error),
+            #[cfg(feature = "pigpio")]
+            Self::Pigpio(code) => write!(f, "pigpio daemon error: {}", code),
+            #[cfg(feature = "realtime")]
+            Self::Realtime(error) => write!(f, "real-time scheduling error: {}", error),
+            #[cfg(feature = "ros2")]
+            Self::Ros2(message) => write!(f, "ROS 2 error: {}", message),
+            #[cfg(feature = "us100")]
+            Self::Uart(error) => write!(f, "UART error: {}", error),
+            #[cfg(feature = "us100")]
+            Self::NoReply => write!(f, "timed out waiting for US-100 reply"),
         }
     }
 }
 
 impl std::error::Error for Error {}
 
+#[cfg(feature = "rppal")]
 impl From<gpio::Error> for Error {
     fn from(error: gpio::Error) -> Self {
         Self::Gpio(error)
     }
 }
+
+#[cfg(feature = "us100")]
+impl From<rppal::uart::Error> for Error {
+    fn from(error: rppal::uart::Error) -> Self {
+        Self::Uart(error)
+    }
+}
+
+#[cfg(feature = "i2c")]
+impl From<rppal::i2c::Error> for Error {
+    fn from(error: rppal::i2c::Error) -> Self {
+        Self::I2c(error)
+    }
+}
+
+#[cfg(feature = "gpio-cdev")]
+impl From<gpio_cdev::Error> for Error {
+    fn from(error: gpio_cdev::Error) -> Self {
+        Self::Cdev(error)
+    }
+}
+
+#[cfg(any(
+    feature = "config",
+    feature = "http",
+    feature = "influxdb",
+    feature = "pigpio",
+    feature = "profile",
+    feature = "prometheus",
+    feature = "recorder",
+    feature = "signal",
+    feature = "websocket"
+))]
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+#[cfg(feature = "profile")]
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Profile(error)
+    }
+}
+
+#[cfg(feature = "config")]
+impl From<toml::de::Error> for Error {
+    fn from(error: toml::de::Error) -> Self {
+        Self::Config(error)
+    }
+}
+
+#[cfg(feature = "dbus")]
+impl From<zbus::Error> for Error {
+    fn from(error: zbus::Error) -> Self {
+        Self::Dbus(error)
+    }
+}
+
+#[cfg(feature = "mqtt")]
+impl From<rumqttc::ClientError> for Error {
+    fn from(error: rumqttc::ClientError) -> Self {
+        Self::Mqtt(error)
+    }
+}
@@ -0,0 +1,490 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Filtering of distance measurements.
+//!
+//! Filters implement the [`DistanceFilter`] trait and operate on distances expressed in meters.
+//! They only ever see successful readings: a measurement with no object in range leaves the
+//! filter state untouched. Filters can be chained into a [`Pipeline`], each stage feeding the
+//! next one.
+//!
+//! Once filters are set on the sensor (see
+//! [`HcSr04Builder::filter`](crate::HcSr04Builder::filter)), every measurement it performs,
+//! including continuous and streamed ones, returns the filtered distance. Filters can also be
+//! selected for a single measurement call (see
+//! [`HcSr04::measure_distance_with`](crate::HcSr04::measure_distance_with)) or on a stream.
+//!
+//! Available filters:
+//!
+//! - [`Median`]: median over a sliding window, removing spikes
+//! - [`Percentile`]: percentile over a sliding window, e.g. keeping long-timeout spikes out
+//! - [`OutlierReject`]: rejection of readings jumping too far from the previous one
+//! - [`Ema`]: cheap exponential moving average
+//! - [`Kalman`]: smoothed estimate plus its variance
+//!
+//! ```rust
+//! use hc_sr04::filter::{DistanceFilter, Ema, Median, OutlierReject, Pipeline};
+//!
+//! let mut pipeline = Pipeline::new()
+//!     .then(Median::new(3))
+//!     .then(OutlierReject::new(0.5, 3))
+//!     .then(Ema::new(0.5));
+//!
+//! assert_eq!(pipeline.apply(1.), Some(1.));
+//! assert_eq!(pipeline.apply(1.), Some(1.));
+//! // Spike removed by the median.
+//! assert_eq!(pipeline.apply(4.), Some(1.));
+//! // Jump rejected.
+//! assert_eq!(pipeline.apply(4.), None);
+//! ```
+
+use crate::{portable::abs, Float};
+#[cfg(any(feature = "stream", feature = "async-std"))]
+use crate::{Distance, Measurement, Result};
+#[cfg(feature = "std")]
+use std::{collections::VecDeque, fmt};
+
+/// Filter applied to distance readings expressed in meters.
+pub trait DistanceFilter {
+    /// Filter the `raw` distance reading (in m), returning the filtered distance or `None` if the
+    /// reading is rejected.
+    fn apply(&mut self, raw: Float) -> Option<Float>;
+
+    /// Variance of the latest filtered distance in m², if the filter tracks it.
+    fn variance(&self) -> Option<Float> {
+        None
+    }
+
+    /// Discard the filter state, e.g. after the sensor has been moved.
+    fn reset(&mut self) {}
+}
+
+/// Chain of filters, each stage feeding the next one. A reading rejected by any stage is
+/// rejected by the whole pipeline.
+///
+/// An empty pipeline leaves readings untouched.
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<Box<dyn DistanceFilter + Send>>,
+}
+
+#[cfg(feature = "std")]
+impl Pipeline {
+    /// Create an empty pipeline.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `filter` as the last stage of the pipeline.
+    pub fn then(mut self, filter: impl DistanceFilter + Send + 'static) -> Self {
+        self.push(filter);
+        self
+    }
+
+    /// Append `filter` as the last stage of the pipeline.
+    pub fn push(&mut self, filter: impl DistanceFilter + Send + 'static) {
+        self.stages.push(Box::new(filter));
+    }
+
+    /// Number of stages in the pipeline.
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// Returns `true` if the pipeline has no stages.
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Filter the distance of `measurement` on top of the filters it already went through,
+    /// updating its variance estimate and marking it as filtered, e.g. for filters set on a
+    /// stream. Failed measurements are passed through, while readings rejected by the pipeline
+    /// are left with no distance.
+    #[cfg(any(feature = "stream", feature = "async-std"))]
+    pub(crate) fn filter_measurement(
+        &mut self,
+        measurement: Result<Measurement>,
+    ) -> Result<Measurement> {
+        let mut measurement = measurement?;
+        if self.is_empty() {
+            return Ok(measurement);
+        }
+
+        if let Some(distance) = measurement.distance {
+            measurement.distance = self.apply(distance.as_meters()).map(Distance::from_meters);
+        }
+        // Stages not tracking the variance leave the estimate of the sensor filters in place.
+        measurement.variance = measurement
+            .distance
+            .and(self.variance().or(measurement.variance));
+        measurement.filtered = true;
+        Ok(measurement)
+    }
+}
+
+#[cfg(feature = "std")]
+impl DistanceFilter for Pipeline {
+    fn apply(&mut self, raw: Float) -> Option<Float> {
+        self.stages
+            .iter_mut()
+            .try_fold(raw, |distance, stage| stage.apply(distance))
+    }
+
+    /// Variance reported by the last stage tracking it.
+    fn variance(&self) -> Option<Float> {
+        self.stages.iter().rev().find_map(|stage| stage.variance())
+    }
+
+    fn reset(&mut self) {
+        for stage in &mut self.stages {
+            stage.reset();
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("stages", &self.stages.len())
+            .finish()
+    }
+}
+
+/// Median over a sliding window of the latest readings.
+///
+/// # Fields
+///
+/// - `size`: window size
+/// - `window`: latest readings in m
+///
+/// ```rust
+/// use hc_sr04::filter::{DistanceFilter, Median};
+///
+/// let mut median = Median::new(3);
+/// assert_eq!(median.apply(1.), Some(1.));
+/// assert_eq!(median.apply(9.), Some(5.));
+/// assert_eq!(median.apply(2.), Some(2.));
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Median {
+    size: usize,
+    window: VecDeque<Float>,
+}
+
+#[cfg(feature = "std")]
+impl Median {
+    /// Create a new filter over the latest `size` readings (at least one).
+    pub fn new(size: usize) -> Self {
+        let size = size.max(1);
+        Self {
+            size,
+            window: VecDeque::with_capacity(size),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl DistanceFilter for Median {
+    fn apply(&mut self, raw: Float) -> Option<Float> {
+        if self.window.len() == self.size {
+            self.window.pop_front();
+        }
+        self.window.push_back(raw);
+
+        let mut sorted: Vec<Float> = self.window.iter().copied().collect();
+        sorted.sort_by(Float::total_cmp);
+        let mid = sorted.len() / 2;
+
+        Some(match sorted.len() % 2 {
+            0 => (sorted[mid - 1] + sorted[mid]) / 2.,
+            _ => sorted[mid],
+        })
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+}
+
+/// Percentile over a sliding window of the latest readings, linearly interpolated between the
+/// closest ranks: a robust smoother which, biased towards shorter distances (e.g. the 25th
+/// percentile), never lets occasional spikes of long-timeout readings leak into the output.
+///
+/// The 50th percentile is the [`Median`].
+///
+/// # Fields
+///
+/// - `size`: window size
+/// - `percentile`: percentile reported, in `[0, 100]`
+/// - `window`: latest readings in m
+///
+/// ```rust
+/// use hc_sr04::filter::{DistanceFilter, Percentile};
+///
+/// let mut percentile = Percentile::new(5, 25.);
+/// assert_eq!(percentile.apply(1.), Some(1.));
+/// assert_eq!(percentile.apply(4.), Some(1.75));
+/// assert_eq!(percentile.apply(1.), Some(1.));
+/// assert_eq!(percentile.apply(1.), Some(1.));
+/// assert_eq!(percentile.apply(4.), Some(1.));
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Percentile {
+    size: usize,
+    percentile: Float,
+    window: VecDeque<Float>,
+}
+
+#[cfg(feature = "std")]
+impl Percentile {
+    /// Create a new filter reporting the `percentile` (clamped to `[0, 100]`) of the latest
+    /// `size` readings (at least one).
+    pub fn new(size: usize, percentile: Float) -> Self {
+        let size = size.max(1);
+        Self {
+            size,
+            percentile: percentile.clamp(0., 100.),
+            window: VecDeque::with_capacity(size),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl DistanceFilter for Percentile {
+    fn apply(&mut self, raw: Float) -> Option<Float> {
+        if self.window.len() == self.size {
+            self.window.pop_front();
+        }
+        self.window.push_back(raw);
+
+        let mut sorted: Vec<Float> = self.window.iter().copied().collect();
+        sorted.sort_by(Float::total_cmp);
+        let rank = self.percentile / 100. * (sorted.len() - 1) as Float;
+        let low = rank as usize;
+        let high = (low + 1).min(sorted.len() - 1);
+
+        Some(sorted[low] + (sorted[high] - sorted[low]) * (rank - low as Float))
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+}
+
+/// Rejection of readings deviating more than a threshold from the last accepted one.
+///
+/// After a number of consecutive rejections, the reading is accepted anyway: the object actually
+/// moved rather than the sensor glitching.
+///
+/// # Fields
+///
+/// - `max_deviation`: maximum deviation from the last accepted reading in m
+/// - `max_rejections`: maximum number of consecutive rejected readings
+/// - `last`: last accepted reading in m
+/// - `rejections`: number of consecutive rejected readings
+///
+/// ```rust
+/// use hc_sr04::filter::{DistanceFilter, OutlierReject};
+///
+/// let mut reject = OutlierReject::new(0.1, 1);
+/// assert_eq!(reject.apply(1.), Some(1.));
+/// assert_eq!(reject.apply(2.), None);
+/// assert_eq!(reject.apply(2.), Some(2.));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OutlierReject {
+    max_deviation: Float,
+    max_rejections: u32,
+    last: Option<Float>,
+    rejections: u32,
+}
+
+impl OutlierReject {
+    /// Create a new filter rejecting readings deviating more than `max_deviation` (in m) from the
+    /// last accepted one, at most `max_rejections` times in a row.
+    pub fn new(max_deviation: Float, max_rejections: u32) -> Self {
+        Self {
+            max_deviation,
+            max_rejections,
+            last: None,
+            rejections: 0,
+        }
+    }
+}
+
+impl DistanceFilter for OutlierReject {
+    fn apply(&mut self, raw: Float) -> Option<Float> {
+        if let Some(last) = self.last {
+            if abs(raw - last) > self.max_deviation && self.rejections < self.max_rejections {
+                self.rejections += 1;
+                return None;
+            }
+        }
+
+        self.last = Some(raw);
+        self.rejections = 0;
+        Some(raw)
+    }
+
+    fn reset(&mut self) {
+        self.last = None;
+        self.rejections = 0;
+    }
+}
+
+/// Exponential moving average filter.
+///
+/// # Fields
+///
+/// - `alpha`: weight of the latest reading, in `(0, 1]`
+/// - `estimate`: current average in m (`None` until the first update)
+///
+/// ```rust
+/// use hc_sr04::filter::{DistanceFilter, Ema};
+///
+/// let mut ema = Ema::new(0.5);
+/// assert_eq!(ema.apply(1.), Some(1.));
+/// assert_eq!(ema.apply(2.), Some(1.5));
+/// assert_eq!(ema.apply(2.), Some(1.75));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ema {
+    alpha: Float,
+    estimate: Option<Float>,
+}
+
+impl Ema {
+    /// Create a new filter weighting the latest reading by `alpha` (clamped to `(0, 1]`): the
+    /// lower `alpha`, the smoother (and slower to react) the average.
+    pub fn new(alpha: Float) -> Self {
+        Self {
+            alpha: alpha.clamp(Float::EPSILON, 1.),
+            estimate: None,
+        }
+    }
+
+    /// Current average in m, `None` if no reading has been filtered yet.
+    pub fn estimate(&self) -> Option<Float> {
+        self.estimate
+    }
+}
+
+impl DistanceFilter for Ema {
+    fn apply(&mut self, raw: Float) -> Option<Float> {
+        let estimate = match self.estimate {
+            None => raw,
+            Some(estimate) => estimate + self.alpha * (raw - estimate),
+        };
+
+        self.estimate = Some(estimate);
+        Some(estimate)
+    }
+
+    fn reset(&mut self) {
+        self.estimate = None;
+    }
+}
+
+/// One-dimensional Kalman filter, modelling the distance as constant between measurements.
+///
+/// # Fields
+///
+/// - `process_noise`: variance of the actual distance change between measurements (m²)
+/// - `measurement_noise`: variance of the sensor readings (m²)
+/// - `estimate`: current distance estimate in m (`None` until the first update)
+/// - `variance`: variance of the current estimate (m²)
+///
+/// ```rust
+/// use hc_sr04::filter::{DistanceFilter, Kalman};
+///
+/// let mut kalman = Kalman::new(1e-4, 1e-2);
+/// for raw in [1.02, 0.97, 1.05, 0.99, 1.01] {
+///     kalman.apply(raw);
+/// }
+///
+/// let estimate = kalman.estimate().unwrap();
+/// assert!((estimate - 1.).abs() < 0.02);
+/// assert!(kalman.variance().unwrap() < 1e-2);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Kalman {
+    process_noise: Float,
+    measurement_noise: Float,
+    estimate: Option<Float>,
+    variance: Float,
+}
+
+impl Kalman {
+    /// Create a new filter given `process_noise` and `measurement_noise` variances, expressed in
+    /// m².
+    ///
+    /// The lower the process noise with respect to the measurement noise, the smoother (and
+    /// slower to react) the estimate.
+    pub fn new(process_noise: Float, measurement_noise: Float) -> Self {
+        Self {
+            process_noise,
+            measurement_noise,
+            estimate: None,
+            variance: measurement_noise,
+        }
+    }
+
+    /// Current distance estimate in m, `None` if no reading has been filtered yet.
+    pub fn estimate(&self) -> Option<Float> {
+        self.estimate
+    }
+}
+
+impl DistanceFilter for Kalman {
+    fn apply(&mut self, raw: Float) -> Option<Float> {
+        let estimate = match self.estimate {
+            // First reading: trust the sensor.
+            None => {
+                self.variance = self.measurement_noise;
+                raw
+            }
+            Some(estimate) => {
+                // Predict: the distance is constant, but uncertainty grows.
+                let variance = self.variance + self.process_noise;
+                // Correct: weight prediction and reading by their uncertainty.
+                let gain = variance / (variance + self.measurement_noise);
+                self.variance = (1. - gain) * variance;
+                estimate + gain * (raw - estimate)
+            }
+        };
+
+        self.estimate = Some(estimate);
+        Some(estimate)
+    }
+
+    fn variance(&self) -> Option<Float> {
+        self.estimate.map(|_| self.variance)
+    }
+
+    fn reset(&mut self) {
+        self.estimate = None;
+        self.variance = self.measurement_noise;
+    }
+}
@@ -0,0 +1,206 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Thread-safe shared handle to the sensor.
+//!
+//! [`HcSr04Handle`] can be cloned and sent to as many threads as needed (e.g. web request
+//! handlers), all of them taking measurements from the same sensor: concurrent measurements are
+//! serialized.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{HcSr04Handle, Unit};
+//! use std::thread;
+//!
+//! let ultrasonic = HcSr04Handle::new(24, 23, None).unwrap();
+//!
+//! let workers: Vec<_> = (0..4)
+//!     .map(|i| {
+//!         let ultrasonic = ultrasonic.clone();
+//!         thread::spawn(move || {
+//!             let dist = ultrasonic.measure_distance(Unit::Meters).unwrap();
+//!             println!("Worker {i} distance: {:?}", dist);
+//!         })
+//!     })
+//!     .collect();
+//!
+//! for worker in workers {
+//!     worker.join().unwrap();
+//! }
+//! ```
+
+use crate::{
+    backend::Backend,
+    limiter::{Overflow, RateLimiter},
+    CancelToken, DefaultBackend, Distance, Float, HcSr04, Measurement, Outcome, Result, Unit,
+};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Cloneable, thread-safe handle to [`HcSr04`] sensor.
+///
+/// Cloning the handle yields a new handle to the same sensor. A panic while a handle is
+/// measuring doesn't make the sensor unusable for the others.
+///
+/// # Fields
+///
+/// - `sensor`: shared sensor
+/// - `cancel`: the sensor's cancel token, usable without waiting for the sensor lock
+/// - `limiter`: rate limiter measurement requests go through, if any
+#[derive(Debug)]
+pub struct HcSr04Handle<B = DefaultBackend> {
+    sensor: Arc<Mutex<HcSr04<B>>>,
+    cancel: CancelToken,
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+#[cfg(feature = "rppal")]
+impl HcSr04Handle {
+    /// Initialize HC-SR04 sensor (see [`HcSr04::new`]).
+    pub fn new(trig: u8, echo: u8, temp: Option<Float>) -> Result<Self> {
+        Ok(Self::from(HcSr04::new(trig, echo, temp)?))
+    }
+}
+
+impl<B> Clone for HcSr04Handle<B> {
+    fn clone(&self) -> Self {
+        Self {
+            sensor: self.sensor.clone(),
+            cancel: self.cancel.clone(),
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+impl<B: Backend> HcSr04Handle<B> {
+    /// Admit measurement requests through `limiter` (see the [`limiter`](crate::limiter)
+    /// module), shared by the handles cloned from this one afterwards.
+    ///
+    /// Requests rejected by the limiter fail with
+    /// [`Error::RateLimited`](crate::error::Error::RateLimited).
+    pub fn with_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.limiter = Some(Arc::new(limiter));
+        self
+    }
+
+    /// Space measurement requests by the sensor's minimum measurement cycle (see
+    /// [`HcSr04::min_cycle`]), treating excess requests as per `overflow` (see
+    /// [`HcSr04Handle::with_limiter`]).
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "mock")]
+    /// # {
+    /// use hc_sr04::{limiter::Overflow, HcSr04Handle, MockHcSr04};
+    /// use std::time::Duration;
+    ///
+    /// let mut ultrasonic = MockHcSr04::mock(None);
+    /// ultrasonic.set_min_cycle(Duration::from_secs(1));
+    /// let handle = HcSr04Handle::from(ultrasonic).rate_limited(Overflow::Reject);
+    ///
+    /// assert!(handle.measure().is_ok());
+    /// assert!(handle.clone().measure().is_err());
+    /// # }
+    /// ```
+    pub fn rate_limited(self, overflow: Overflow) -> Self {
+        let cycle = self.lock().min_cycle();
+        self.with_limiter(RateLimiter::new(cycle).overflow(overflow))
+    }
+
+    /// Rate limiter measurement requests go through, if any.
+    pub fn limiter(&self) -> Option<&RateLimiter> {
+        self.limiter.as_deref()
+    }
+
+    /// Calibrate the sensor with the given **ambient temperature** (`temp`) expressed as *Celsius
+    /// degrees*.
+    pub fn calibrate(&self, temp: Float) {
+        self.lock().calibrate(temp);
+    }
+
+    /// Perform **distance measurement** (see [`HcSr04::measure_distance`]).
+    pub fn measure_distance(&self, unit: Unit) -> Result<Option<Float>> {
+        self.admit()?;
+        self.lock().measure_distance(unit)
+    }
+
+    /// Perform **distance measurement** in the sensor's default measuring unit (see
+    /// [`HcSr04::measure_default`]).
+    pub fn measure_default(&self) -> Result<Option<Float>> {
+        self.admit()?;
+        self.lock().measure_default()
+    }
+
+    /// Perform **distance measurement**, returning a unit-safe [`Distance`] (see
+    /// [`HcSr04::measure`]).
+    pub fn measure(&self) -> Result<Option<Distance>> {
+        self.admit()?;
+        self.lock().measure()
+    }
+
+    /// Perform **distance measurement**, telling apart why no distance is available, if so (see
+    /// [`HcSr04::measure_outcome`]).
+    pub fn measure_outcome(&self) -> Result<Outcome> {
+        self.admit()?;
+        self.lock().measure_outcome()
+    }
+
+    /// Perform **distance measurement**, timestamping the result.
+    pub fn measurement(&self) -> Result<Measurement> {
+        self.admit()?;
+        self.lock().measurement()
+    }
+
+    /// Abort the in-flight measurement, if any, without waiting for it to time out, e.g. during
+    /// application shutdown (see the [`cancel`](crate::cancel) module).
+    ///
+    /// The pending measurement and all the subsequent ones, from any handle, fail with
+    /// [`Error::Cancelled`](crate::error::Error::Cancelled) until [`HcSr04Handle::resume`] is
+    /// called.
+    pub fn abort(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Let measurements run again after aborting them (see [`HcSr04Handle::abort`]).
+    pub fn resume(&self) {
+        self.cancel.reset();
+    }
+
+    /// Run `f` with exclusive access to the sensor, e.g. to change its configuration or to
+    /// perform several measurements in a row without other handles interleaving.
+    pub fn with<T>(&self, f: impl FnOnce(&mut HcSr04<B>) -> T) -> T {
+        f(&mut self.lock())
+    }
+
+    /// Take a token from the rate limiter, if any, before measuring.
+    fn admit(&self) -> Result<()> {
+        self.limiter
+            .as_ref()
+            .map_or(Ok(()), |limiter| limiter.acquire())
+    }
+
+    /// Lock the sensor, recovering it if a thread panicked while holding it.
+    fn lock(&self) -> MutexGuard<'_, HcSr04<B>> {
+        self.sensor.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<B> From<HcSr04<B>> for HcSr04Handle<B> {
+    fn from(sensor: HcSr04<B>) -> Self {
+        Self {
+            cancel: sensor.cancel.clone(),
+            sensor: Arc::new(Mutex::new(sensor)),
+            limiter: None,
+        }
+    }
+}
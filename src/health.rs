@@ -0,0 +1,329 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Sensor health monitoring.
+//!
+//! A wedged **ECHO** line, or a sensor whose transducer got covered, keeps returning the very
+//! same distance, which otherwise looks like a perfectly stable measurement. [`StuckDetector`]
+//! raises [`HealthEvent::SensorStuck`] once readings stayed within a tolerance of each other for
+//! an abnormally long period, and [`HealthEvent::Recovered`] as soon as they change again.
+//!
+//! ```rust
+//! use hc_sr04::{
+//!     health::{HealthEvent, StuckDetector},
+//!     Distance, Measurement,
+//! };
+//! use std::time::{Duration, SystemTime};
+//!
+//! let start = SystemTime::now();
+//! let measurement = |meters, secs| {
+//!     Measurement::new(
+//!         Some(Distance::from_meters(meters)),
+//!         start + Duration::from_secs(secs),
+//!     )
+//! };
+//!
+//! let mut detector = StuckDetector::new(Duration::from_secs(60));
+//! assert_eq!(detector.update(&measurement(1.5, 0)), None);
+//! assert_eq!(detector.update(&measurement(1.5, 30)), None);
+//! assert_eq!(
+//!     detector.update(&measurement(1.5, 60)),
+//!     Some(HealthEvent::SensorStuck {
+//!         distance: Distance::from_meters(1.5),
+//!         since: start,
+//!     })
+//! );
+//! assert_eq!(detector.update(&measurement(1.4, 61)), Some(HealthEvent::Recovered));
+//! ```
+//!
+//! Every sensor also counts its pings and their outcomes: [`HcSr04::health`] returns a
+//! [`HealthMetrics`] snapshot (timeouts, errors, retries, consecutive failures, last success and
+//! ping latency percentiles) for fleet monitoring to report.
+//!
+//! ```rust
+//! # #[cfg(feature = "mock")]
+//! # {
+//! use hc_sr04::{MockHcSr04, Unit};
+//!
+//! let mut ultrasonic = MockHcSr04::mock(None);
+//! ultrasonic
+//!     .push_distance(1., Unit::Meters)
+//!     .push_no_echo()
+//!     .push_no_echo()
+//!     .push_distance(1., Unit::Meters);
+//!
+//! ultrasonic.measure().unwrap();
+//! assert!(ultrasonic.measure().is_err());
+//! ultrasonic.measurement_retrying(2).unwrap();
+//!
+//! let health = ultrasonic.health();
+//! assert_eq!(health.pings, 4);
+//! assert_eq!(health.echoes, 2);
+//! assert_eq!(health.timeouts, 2);
+//! assert_eq!(health.retries, 1);
+//! assert_eq!(health.consecutive_failures, 0);
+//! assert!(health.last_success.is_some());
+//! assert!(health.latency.is_some());
+//! # }
+//! ```
+
+use crate::{backend::Backend, error::Error, Distance, HcSr04, Measurement, Result};
+use std::{
+    collections::VecDeque,
+    time::{Duration, SystemTime},
+};
+
+/// Number of latest ping latencies [`Latency`] percentiles are computed over.
+const LATENCY_WINDOW: usize = 128;
+
+/// Sensor health transition reported by [`StuckDetector`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HealthEvent {
+    /// Readings stayed at `distance` (within the tolerance) since `since`, for longer than the
+    /// configured period.
+    SensorStuck {
+        distance: Distance,
+        since: SystemTime,
+    },
+    /// Readings changed again after the sensor was reported stuck.
+    Recovered,
+}
+
+/// Stuck sensor detector, reporting readings which stayed within a tolerance of each other for
+/// longer than a given period.
+///
+/// Measurements with no object in range break the streak of identical readings.
+///
+/// # Fields
+///
+/// - `period`: time readings must stay unchanged for the sensor to be considered stuck
+/// - `tolerance`: maximum difference from the first reading of the streak
+/// - `streak`: first reading of the current streak of unchanged readings and its timestamp
+/// - `stuck`: whether the sensor is currently reported stuck
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StuckDetector {
+    period: Duration,
+    tolerance: Distance,
+    streak: Option<(Distance, SystemTime)>,
+    stuck: bool,
+}
+
+impl StuckDetector {
+    /// Create a new detector reporting the sensor stuck once readings stayed bit-for-bit
+    /// identical for at least `period`.
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period,
+            tolerance: Distance::from_meters(0.),
+            streak: None,
+            stuck: false,
+        }
+    }
+
+    /// Consider readings within `tolerance` of each other unchanged (none by default), e.g. for
+    /// sensors jittering by a few tenths of a millimeter even when wedged.
+    pub fn tolerance(mut self, tolerance: Distance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Whether the sensor is currently reported stuck.
+    pub fn is_stuck(&self) -> bool {
+        self.stuck
+    }
+
+    /// Update the detector with `measurement`, returning the new health state on transitions.
+    pub fn update(&mut self, measurement: &Measurement) -> Option<HealthEvent> {
+        let unchanged = match (self.streak, measurement.distance) {
+            (Some((first, _)), Some(distance)) => distance.abs_diff(first) <= self.tolerance,
+            _ => false,
+        };
+
+        if !unchanged {
+            self.streak = measurement
+                .distance
+                .map(|distance| (distance, measurement.timestamp));
+            return self.recover();
+        }
+
+        let (distance, since) = self.streak?;
+        let elapsed = measurement.timestamp.duration_since(since).ok()?;
+        if self.stuck || elapsed < self.period {
+            return None;
+        }
+
+        self.stuck = true;
+        Some(HealthEvent::SensorStuck { distance, since })
+    }
+
+    /// Measure through `sensor` and update the detector (see [`StuckDetector::update`]).
+    pub fn poll<B: Backend>(&mut self, sensor: &mut HcSr04<B>) -> Result<Option<HealthEvent>> {
+        Ok(self.update(&sensor.measurement()?))
+    }
+
+    /// Discard the detector state, reporting the sensor healthy again.
+    pub fn reset(&mut self) {
+        self.streak = None;
+        self.stuck = false;
+    }
+
+    /// Leave the stuck state, if in it.
+    fn recover(&mut self) -> Option<HealthEvent> {
+        if !self.stuck {
+            return None;
+        }
+
+        self.stuck = false;
+        Some(HealthEvent::Recovered)
+    }
+}
+
+/// Percentiles of the time taken by the latest pings, from the **TRIGGER** pulse to the end of
+/// the **ECHO** pulse or the timeout.
+///
+/// # Fields
+///
+/// - `p50`: median ping latency
+/// - `p90`: 90th percentile of the ping latency
+/// - `p99`: 99th percentile of the ping latency
+/// - `max`: longest ping latency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Latency {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+/// Health counters of a sensor, returned by [`HcSr04::health`].
+///
+/// # Fields
+///
+/// - `pings`: total number of pings
+/// - `echoes`: number of pings receiving an **ECHO** pulse in time
+/// - `timeouts`: number of pings whose **ECHO** pulse didn't start or didn't end in time (no
+///   object in range)
+/// - `errors`: number of pings failing otherwise, e.g. on GPIO errors
+/// - `retries`: number of measurement attempts repeated by [`HcSr04::measurement_retrying`]
+/// - `consecutive_failures`: current number of consecutive pings receiving no **ECHO** pulse
+/// - `last_success`: time of the latest ping receiving an **ECHO** pulse, if any
+/// - `latency`: percentiles of the latest ping latencies, if any ping was performed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HealthMetrics {
+    pub pings: u64,
+    pub echoes: u64,
+    pub timeouts: u64,
+    pub errors: u64,
+    pub retries: u64,
+    pub consecutive_failures: u32,
+    pub last_success: Option<SystemTime>,
+    pub latency: Option<Latency>,
+}
+
+/// Counters updated by the sensor on every ping, behind [`HcSr04::health`].
+///
+/// # Fields
+///
+/// - `metrics`: counters reported as is
+/// - `latencies`: latencies of the latest pings, up to [`LATENCY_WINDOW`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Counters {
+    metrics: HealthMetrics,
+    latencies: VecDeque<Duration>,
+}
+
+impl Default for Counters {
+    fn default() -> Self {
+        Self {
+            metrics: HealthMetrics {
+                pings: 0,
+                echoes: 0,
+                timeouts: 0,
+                errors: 0,
+                retries: 0,
+                consecutive_failures: 0,
+                last_success: None,
+                latency: None,
+            },
+            latencies: VecDeque::with_capacity(LATENCY_WINDOW),
+        }
+    }
+}
+
+impl Counters {
+    /// Record the outcome of a ping, which took `latency`.
+    pub(crate) fn record(&mut self, echo: &Result<Option<Duration>>, latency: Duration) {
+        let metrics = &mut self.metrics;
+        metrics.pings += 1;
+        match echo {
+            Ok(Some(_)) => {
+                metrics.echoes += 1;
+                metrics.last_success = Some(SystemTime::now());
+            }
+            Ok(None) | Err(Error::EchoStartTimeout) => metrics.timeouts += 1,
+            Err(_) => metrics.errors += 1,
+        }
+        metrics.consecutive_failures = match echo {
+            Ok(Some(_)) => 0,
+            _ => metrics.consecutive_failures.saturating_add(1),
+        };
+
+        if self.latencies.len() == LATENCY_WINDOW {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(latency);
+    }
+
+    /// Record `retries` repeated measurement attempts.
+    pub(crate) fn record_retries(&mut self, retries: u32) {
+        self.metrics.retries += u64::from(retries);
+    }
+
+    /// Snapshot of the counters, computing the latency percentiles.
+    fn metrics(&self) -> HealthMetrics {
+        let mut sorted: Vec<_> = self.latencies.iter().copied().collect();
+        sorted.sort_unstable();
+        // Nearest rank percentile.
+        let percentile = |p: usize| sorted[((sorted.len() * p + 99) / 100).max(1) - 1];
+        let latency = (!sorted.is_empty()).then(|| Latency {
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: percentile(100),
+        });
+
+        HealthMetrics {
+            latency,
+            ..self.metrics
+        }
+    }
+}
+
+impl<B: Backend> HcSr04<B> {
+    /// Health counters of the sensor since it was built or the counters were reset (see
+    /// [`HcSr04::reset_health`]).
+    pub fn health(&self) -> HealthMetrics {
+        self.counters.metrics()
+    }
+
+    /// Reset the health counters.
+    pub fn reset_health(&mut self) {
+        self.counters = Counters::default();
+    }
+}
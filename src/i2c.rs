@@ -0,0 +1,199 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! Register-based I2C ultrasonic rangers, available with the `i2c` feature.
+//!
+//! [`I2cRanger`] drives rangers measuring on command and exposing the result through registers
+//! of the I2C bus, leaving no pulse timing to the host. Supported models are listed by
+//! [`I2cModel`]. Rangers implement [`DistanceSensor`](crate::DistanceSensor) like the GPIO
+//! sensors, so that mixed fleets are driven by the same code, filtered through
+//! [`Filtered`](crate::sensor::Filtered) and sampled in the background through
+//! [`continuous::start_with`](crate::continuous::start_with).
+//!
+//! ```rust,no_run
+//! use hc_sr04::{
+//!     i2c::{I2cModel, I2cRanger},
+//!     Unit,
+//! };
+//!
+//! let mut ranger = I2cRanger::new(I2cModel::Srf02).unwrap();
+//! ranger.calibrate(23.);
+//!
+//! match ranger.measure_distance(Unit::Centimeters).unwrap() {
+//!     Some(dist) => println!("Distance: {:.1}cm", dist),
+//!     None => println!("Object out of range"),
+//! }
+//! ```
+
+use crate::{error::Error, model::Spec, portable, Distance, Float, Result, Unit};
+use rppal::i2c::I2c;
+use std::{thread, time::Duration};
+
+/// SRF02 command register.
+const SRF02_COMMAND: u8 = 0x00;
+/// SRF02 command starting a ranging, with the result reported as echo time in µs.
+const SRF02_RANGE_MICROS: u8 = 0x52;
+/// SRF02 register holding the high byte of the result, followed by the low byte.
+const SRF02_RESULT: u8 = 0x02;
+/// RCWL-9620 command starting a ranging, with the result reported as 3 bytes (big endian µm).
+const RCWL9620_RANGE: u8 = 0x01;
+
+/// Register-based I2C ultrasonic ranger model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum I2cModel {
+    /// Devantech SRF02: *16cm* to *6m*, *66ms* ranging, reporting the echo time so that
+    /// distances are compensated for the ambient temperature (see [`I2cRanger::calibrate`]).
+    Srf02,
+    /// RCWL-9620: *2cm* to *4.5m*, *100ms* ranging, reporting the distance computed on board.
+    Rcwl9620,
+}
+
+impl I2cModel {
+    /// Factory default 7-bit address of the model on the bus.
+    pub const fn default_address(self) -> u16 {
+        match self {
+            Self::Srf02 => 0x70,
+            Self::Rcwl9620 => 0x57,
+        }
+    }
+
+    /// Timing and range specification of the model, with no **TRIGGER** pulse and the ranging
+    /// time as measurement cycle.
+    pub const fn spec(self) -> Spec {
+        match self {
+            Self::Srf02 => Spec {
+                trigger_pulse: Duration::ZERO,
+                min_cycle: Duration::from_millis(70),
+                min_range: 0.16,
+                max_range: 6.0,
+            },
+            Self::Rcwl9620 => Spec {
+                trigger_pulse: Duration::ZERO,
+                min_cycle: Duration::from_millis(100),
+                min_range: 0.02,
+                max_range: 4.5,
+            },
+        }
+    }
+}
+
+/// Register-based I2C ultrasonic ranger.
+///
+/// # Fields
+///
+/// - `i2c`: bus the ranger is wired to, addressing it
+/// - `model`: ranger model
+/// - `sound_speed`: speed of sound in m/s echo times are converted with
+/// - `max_range`: maximum measuring range in m, beyond which objects are out of range
+#[derive(Debug)]
+pub struct I2cRanger {
+    i2c: I2c,
+    model: I2cModel,
+    sound_speed: Float,
+    max_range: Float,
+}
+
+impl I2cRanger {
+    /// Initialize `model` ranger at its default address on the *Raspberry Pi* primary I2C bus
+    /// (GPIO pins 2 and 3).
+    pub fn new(model: I2cModel) -> Result<Self> {
+        Self::with_address(model, I2c::new()?, model.default_address())
+    }
+
+    /// Initialize `model` ranger at the 7-bit `address` on the already opened `i2c` bus (e.g. to
+    /// drive several rangers on the same bus, after changing their addresses).
+    pub fn with_address(model: I2cModel, mut i2c: I2c, address: u16) -> Result<Self> {
+        i2c.set_slave_address(address)?;
+        Ok(Self {
+            i2c,
+            model,
+            sound_speed: portable::sound_speed(20.),
+            max_range: model.spec().max_range,
+        })
+    }
+
+    /// Ranger model.
+    pub fn model(&self) -> I2cModel {
+        self.model
+    }
+
+    /// Maximum measuring range in m (defaults to the maximum range of the model).
+    pub fn max_range(&self) -> Float {
+        self.max_range
+    }
+
+    /// Set the maximum measuring range in m.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is not positive and finite.
+    pub fn set_max_range(&mut self, range: Float) {
+        crate::builder::assert_max_range(range);
+        self.max_range = range;
+    }
+
+    /// Calibrate the ranger with the given ambient temperature `temp` in *Celsius degrees*
+    /// (defaults to *20C*).
+    ///
+    /// Only affects the SRF02, the RCWL-9620 computing distances on board.
+    pub fn calibrate(&mut self, temp: Float) {
+        self.sound_speed = portable::sound_speed(temp);
+    }
+
+    /// Perform distance measurement, blocking for the ranging time of the model.
+    ///
+    /// Returns `Ok(None)` if the object is out of range and [`Error::TooClose`] if it is closer
+    /// than the minimum measuring range of the model.
+    pub fn measure(&mut self) -> Result<Option<Distance>> {
+        let meters = match self.model {
+            I2cModel::Srf02 => {
+                self.i2c
+                    .smbus_write_byte(SRF02_COMMAND, SRF02_RANGE_MICROS)?;
+                // The SRF02 doesn't answer on the bus while ranging.
+                thread::sleep(self.model.spec().min_cycle);
+
+                let mut result = [0; 2];
+                self.i2c.write_read(&[SRF02_RESULT], &mut result)?;
+                let echo = Duration::from_micros(u16::from_be_bytes(result).into());
+                portable::echo_distance(echo, self.sound_speed)
+            }
+            I2cModel::Rcwl9620 => {
+                self.i2c.write(&[RCWL9620_RANGE])?;
+                thread::sleep(self.model.spec().min_cycle);
+
+                let mut result = [0; 3];
+                self.i2c.read(&mut result)?;
+                let micros = u32::from_be_bytes([0, result[0], result[1], result[2]]);
+                micros as Float / 1_000_000.
+            }
+        };
+
+        // No echo received.
+        if meters == 0. || meters > self.max_range {
+            return Ok(None);
+        }
+        if meters < self.model.spec().min_range {
+            return Err(Error::TooClose);
+        }
+        Ok(Some(Distance::from_meters(meters)))
+    }
+
+    /// Perform distance measurement, in the given `unit`.
+    ///
+    /// Returns `Ok(None)` if the object is out of range.
+    pub fn measure_distance(&mut self, unit: Unit) -> Result<Option<Float>> {
+        Ok(self.measure()?.map(|distance| distance.as_unit(unit)))
+    }
+}
@@ -0,0 +1,266 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! LED indicator of proximity.
+//!
+//! [`LedIndicator`] maps the measured distance to an LED [`Pattern`]: off while no object is
+//! nearer than a far limit, blinking faster (or glowing brighter) as the object comes closer, and
+//! solid on once nearer than a near limit. It is handy for parking aids and for quick field
+//! diagnostics of a headless device.
+//!
+//! ```rust
+//! use hc_sr04::{
+//!     led::{LedIndicator, Pattern},
+//!     Distance, Unit,
+//! };
+//!
+//! let indicator = LedIndicator::new(
+//!     Distance::new(25., Unit::Centimeters),
+//!     Distance::new(1., Unit::Meters),
+//! )
+//! .frequencies(1., 9.);
+//!
+//! let reading = |meters| Some(Distance::from_meters(meters));
+//! assert_eq!(indicator.pattern(None), Pattern::Off);
+//! assert_eq!(indicator.pattern(reading(1.5)), Pattern::Off);
+//! assert_eq!(indicator.pattern(reading(0.625)), Pattern::Blink(5.));
+//! assert_eq!(indicator.pattern(reading(0.1)), Pattern::On);
+//!
+//! let dimmer = indicator.brightness();
+//! assert_eq!(dimmer.pattern(reading(0.625)), Pattern::Dim(0.5));
+//! ```
+//!
+//! With the `rppal` feature enabled, [`HcSr04::start_led`] drives an [`Led`] pin from the
+//! continuous mode, through software PWM so that blinking stays steady between measurements.
+
+#[cfg(feature = "rppal")]
+use crate::{backend::Backend, continuous::ContinuousWorker, portable, HcSr04};
+use crate::{Distance, Float};
+#[cfg(feature = "rppal")]
+use rppal::gpio::OutputPin;
+#[cfg(feature = "rppal")]
+use std::time::Duration;
+
+/// Default blink frequency at the far limit in Hz.
+const DEFAULT_MIN_FREQUENCY: Float = 1.;
+/// Default blink frequency at the near limit in Hz.
+const DEFAULT_MAX_FREQUENCY: Float = 10.;
+/// Period of the PWM signal dimming the LED, fast enough not to flicker visibly.
+#[cfg(feature = "rppal")]
+const DIM_PERIOD: Duration = Duration::from_millis(5);
+
+/// State of the LED.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pattern {
+    /// LED off.
+    Off,
+    /// LED blinking at the contained frequency in Hz, with 50% duty cycle.
+    Blink(Float),
+    /// LED dimmed to the contained brightness, from `0` (off) to `1` (fully on).
+    Dim(Float),
+    /// LED solid on.
+    On,
+}
+
+/// How proximity is shown between the near and far limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Mode {
+    /// Blink frequency proportional to proximity.
+    #[default]
+    Blink,
+    /// Brightness proportional to proximity.
+    Brightness,
+}
+
+/// Mapping of the measured distance to an LED pattern.
+///
+/// # Fields
+///
+/// - `near`: distance below which the LED is solid on
+/// - `far`: distance beyond which the LED is off
+/// - `mode`: how proximity is shown between `near` and `far`
+/// - `min_frequency`: blink frequency at `far` in Hz
+/// - `max_frequency`: blink frequency at `near` in Hz
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LedIndicator {
+    near: Distance,
+    far: Distance,
+    mode: Mode,
+    min_frequency: Float,
+    max_frequency: Float,
+}
+
+impl LedIndicator {
+    /// Create a new indicator switching the LED off beyond `far` and solid on below `near`,
+    /// blinking from *1Hz* to *10Hz* in between.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `near` is shorter than `far`.
+    pub fn new(near: Distance, far: Distance) -> Self {
+        assert!(near < far, "near limit must be shorter than far limit");
+
+        Self {
+            near,
+            far,
+            mode: Mode::Blink,
+            min_frequency: DEFAULT_MIN_FREQUENCY,
+            max_frequency: DEFAULT_MAX_FREQUENCY,
+        }
+    }
+
+    /// Blink frequencies in Hz at the far limit (`min`) and at the near limit (`max`).
+    ///
+    /// # Panics
+    ///
+    /// Panics unless both frequencies are positive and finite.
+    pub fn frequencies(mut self, min: Float, max: Float) -> Self {
+        assert!(
+            min > 0. && min.is_finite() && max > 0. && max.is_finite(),
+            "blink frequencies must be positive and finite"
+        );
+
+        self.min_frequency = min;
+        self.max_frequency = max;
+        self
+    }
+
+    /// Show proximity as brightness instead of blink frequency, for LEDs driven through PWM.
+    pub fn brightness(mut self) -> Self {
+        self.mode = Mode::Brightness;
+        self
+    }
+
+    /// Proximity of the object at `distance` (`None` if no object is in range), from `0` at the
+    /// far limit (or beyond) to `1` at the near limit (or closer).
+    pub fn proximity(&self, distance: Option<Distance>) -> Float {
+        match distance {
+            Some(distance) if distance <= self.near => 1.,
+            Some(distance) if distance < self.far => {
+                ((self.far - distance) / (self.far - self.near)).clamp(0., 1.)
+            }
+            _ => 0.,
+        }
+    }
+
+    /// LED pattern for an object at `distance` (`None` if no object is in range).
+    pub fn pattern(&self, distance: Option<Distance>) -> Pattern {
+        match distance {
+            Some(distance) if distance < self.near => Pattern::On,
+            Some(distance) if distance < self.far => {
+                let proximity = self.proximity(Some(distance));
+                match self.mode {
+                    Mode::Blink => Pattern::Blink(
+                        self.min_frequency + (self.max_frequency - self.min_frequency) * proximity,
+                    ),
+                    Mode::Brightness => Pattern::Dim(proximity),
+                }
+            }
+            _ => Pattern::Off,
+        }
+    }
+}
+
+/// LED pin showing [`Pattern`]s, available with the `rppal` feature.
+///
+/// Blinking and dimming are generated through software PWM, on a background thread. The LED is
+/// switched off when the pin is wrapped and again when dropped.
+///
+/// # Fields
+///
+/// - `pin`: output pin the LED is wired to, lighting it when high
+/// - `pattern`: pattern currently shown
+#[cfg(feature = "rppal")]
+#[derive(Debug)]
+pub struct Led {
+    pin: OutputPin,
+    pattern: Pattern,
+}
+
+#[cfg(feature = "rppal")]
+impl Led {
+    /// Drive the LED wired to `pin`.
+    pub fn new(mut pin: OutputPin) -> Self {
+        pin.set_low();
+        Self {
+            pin,
+            pattern: Pattern::Off,
+        }
+    }
+
+    /// Pattern currently shown.
+    pub fn pattern(&self) -> Pattern {
+        self.pattern
+    }
+
+    /// Show `pattern`, leaving the LED untouched if already showing it.
+    pub fn show(&mut self, pattern: Pattern) -> crate::Result<()> {
+        if pattern == self.pattern {
+            return Ok(());
+        }
+
+        match pattern {
+            Pattern::Off => {
+                self.pin.clear_pwm()?;
+                self.pin.set_low();
+            }
+            Pattern::On => {
+                self.pin.clear_pwm()?;
+                self.pin.set_high();
+            }
+            Pattern::Blink(frequency) => {
+                let period = portable::from_secs(1. / frequency);
+                self.pin.set_pwm(period, period / 2)?;
+            }
+            Pattern::Dim(brightness) => {
+                let width = portable::from_secs(portable::secs(DIM_PERIOD) * brightness);
+                self.pin.set_pwm(DIM_PERIOD, width)?;
+            }
+        }
+
+        self.pattern = pattern;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rppal")]
+impl Drop for Led {
+    fn drop(&mut self) {
+        let _ = self.show(Pattern::Off);
+    }
+}
+
+#[cfg(feature = "rppal")]
+impl<B: Backend + Send + 'static> HcSr04<B> {
+    /// Start measuring distance every `interval` on a worker thread (see
+    /// [`HcSr04::spawn_continuous`]), showing on `led` the pattern `indicator` maps every
+    /// distance to.
+    ///
+    /// Failed measurements leave the LED untouched. Once the worker is stopped, the LED is
+    /// switched off.
+    pub fn start_led(
+        self,
+        interval: Duration,
+        indicator: LedIndicator,
+        mut led: Led,
+    ) -> ContinuousWorker {
+        self.spawn_continuous(interval, move |measurement| {
+            if let Ok(measurement) = measurement {
+                let _ = led.show(indicator.pattern(measurement.distance));
+            }
+            true
+        })
+    }
+}
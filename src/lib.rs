@@ -17,7 +17,7 @@
 //! **HC-SR04** ultrasonic distance sensor driver.
 //!
 //! This crate provides a driver for the **HC-SR04**/**HC-SR04P** ultrasonic distance sensor on
-//! *Raspberry Pi*, using [rppal](https://docs.rs/rppal/0.13.1/rppal/) to access Raspberry Pi's
+//! *Raspberry Pi*, using [rppal](https://docs.rs/rppal/0.22/rppal/) to access Raspberry Pi's
 //! GPIO.
 //!
 //! ## Examples
@@ -26,163 +26,1352 @@
 //! [examples](https://github.com/marcoradocchia/hc-sr04/tree/master/examples) folder.
 //!
 //! ## Measure distance
-//! ```rust
+//! ```rust,no_run
 //! use hc_sr04::{HcSr04, Unit};
 //!
 //! // Initialize driver.
 //! let mut ultrasonic = HcSr04::new(
 //!     24,          // TRIGGER -> Gpio pin 24
 //!     23,          // ECHO -> Gpio pin 23
-//!     Some(23_f32) // Ambient temperature (if `None` defaults to 20.0C)
+//!     Some(23.)    // Ambient temperature (if `None` defaults to 20.0C)
 //! ).unwrap();
 //!
 //! // Perform distance measurement, specifying measuring unit of return value.
 //! match ultrasonic.measure_distance(Unit::Meters).unwrap() {
-//!     Some(dist) => println!("Distance: {.2}m", dist),
+//!     Some(dist) => println!("Distance: {:.2}m", dist),
 //!     None => println!("Object out of range"),
 //! }
 //! ```
 //!
+//! [`HcSr04::measure`] returns a unit-safe [`Distance`] instead, which can be compared and
+//! converted to any [`Unit`].
+//!
+//! ## Configure the sensor
+//!
+//! Sensor options other than pins and temperature can be set through [`HcSr04Builder`].
+//!
+//! ```rust,no_run
+//! use hc_sr04::{HcSr04, Pull, Unit};
+//! use std::time::Duration;
+//!
+//! let mut ultrasonic = HcSr04::builder(24, 23)
+//!     .temperature(23.)
+//!     .trigger_pulse(Duration::from_micros(20))
+//!     .max_range(2.)
+//!     .pull(Pull::Off)
+//!     .unit(Unit::Centimeters)
+//!     .build()
+//!     .unwrap();
+//!
+//! // Measure in the unit configured above.
+//! match ultrasonic.measure_default().unwrap() {
+//!     Some(dist) => println!("Distance: {:.1}cm", dist),
+//!     None => println!("Object out of range"),
+//! }
+//! ```
+//!
+//! Sensor models other than the HC-SR04 (e.g. the waterproof JSN-SR04T) are selected through
+//! `HcSr04Builder::model`, setting the matching timing and range defaults (see the [`model`]
+//! module).
+//!
+//! With the `config` feature enabled, sensors and the sinks their measurements are delivered to
+//! can be described in a TOML file instead, so that deployments can be reconfigured without
+//! recompiling (see the `config` module).
+//!
+//! ## Backends
+//!
+//! GPIO access is provided by a [`Backend`]: *Raspberry Pi* GPIO through
+//! `rppal` is the default (feature `rppal`), while the `embedded-hal` feature allows driving the
+//! sensor with any [embedded-hal](https://docs.rs/embedded-hal) pins, the `gpio-cdev` feature
+//! through the Linux GPIO character device of other boards and the `pigpio` feature through the
+//! pigpio daemon of a local or remote *Raspberry Pi* (see the [`backend`] module).
+//!
+//! With the `us100` feature, the US-100 sensor in UART mode, measuring on its own and replying
+//! over a serial line, is supported as well (see the `us100` module). With the `i2c` feature, so
+//! are the SRF02 and RCWL-9620 rangers, measuring on command over the I2C bus (see the `i2c`
+//! module).
+//!
+//! With the `realtime` feature, the measuring thread can be raised to `SCHED_FIFO` scheduling
+//! while capturing the **ECHO** pulse, reducing timing jitter (see `HcSr04Builder::realtime`).
+//!
+//! The math and state machine the driver is built upon (speed of sound, **ECHO** pulse capture
+//! and conversion to distance, units and filters) don't depend on the standard library: building
+//! with `default-features = false` and no other feature (all of them but `f64`, `measurements`
+//! and `uom` enable `std`) leaves the `no_std` [`portable`] core only, for reuse on
+//! microcontrollers.
+//!
+//! The `f64` feature switches the whole API and the math behind it from `f32` to `f64` precision
+//! (see [`Float`]), for long-term averaging and regression.
+//!
+//! With the `uom` feature enabled, distances and velocities are available as
+//! [uom](https://docs.rs/uom) `Length` and `Velocity` quantities of the same precision, plugging
+//! into dimensional analysis codebases without manual conversion factors. Similarly, the
+//! `measurements` feature converts distances to and from
+//! [measurements](https://docs.rs/measurements) `Length` values.
+//!
+//! ## Continuous measurement
+//!
+//! [`HcSr04::start_continuous`] measures distance at a fixed interval on a worker thread,
+//! streaming [`Measurement`]s over a channel (see the [`continuous`] module). A bounded queue
+//! with a [`Backpressure`](continuous::Backpressure) policy keeps slow consumers from growing
+//! memory or stalling measurement timing unnoticed, and [`HcSr04::spawn_continuous`] returns a
+//! [`ContinuousWorker`](continuous::ContinuousWorker) which stops gracefully, returning error
+//! statistics, e.g. on `SIGINT` and `SIGTERM` with the `signal` feature enabled (see the `signal`
+//! module). The last measurements can also be kept in a shared ring buffer, queried without
+//! consuming the stream (see the [`recent`] module). For battery or solar powered deployments,
+//! [`HcSr04::spawn_duty_cycled`] powers the sensor only around scheduled pings (see the [`duty`]
+//! module).
+//!
+//! ## Filtering
+//!
+//! Measured distances can be filtered by a chain of [`DistanceFilter`]s (median, outlier
+//! rejection, exponential moving average, Kalman filter or custom ones) set through
+//! [`HcSr04Builder::filter`] (see the [`filter`] module).
+//!
+//! ```rust,no_run
+//! use hc_sr04::{
+//!     filter::{Ema, Median, OutlierReject},
+//!     HcSr04, Unit,
+//! };
+//!
+//! let mut ultrasonic = HcSr04::builder(24, 23)
+//!     .filter(Median::new(5))
+//!     .filter(OutlierReject::new(0.3, 3))
+//!     .filter(Ema::new(0.3))
+//!     .build()
+//!     .unwrap();
+//!
+//! match ultrasonic.measure_distance(Unit::Meters).unwrap() {
+//!     Some(dist) => println!("Filtered distance: {:.2}m", dist),
+//!     None => println!("Object out of range"),
+//! }
+//! ```
+//!
+//! Alternatively, several pings can be aggregated into a single distance (mean, median,
+//! extremes or trimmed mean) through [`HcSr04::measure_n`] (see the [`burst`] module).
+//!
+//! Suitable filter settings for a given sensor unit and mounting can be found through
+//! [`HcSr04::characterize_noise`], measuring the noise against a static target (see the
+//! [`noise`] module).
+//!
+//! ## Motion estimation
+//!
+//! Consecutive measurements can be differentiated in order to estimate the approach or retreat
+//! speed of the object, classify it as approaching, receding or stationary, and report sudden
+//! confirmed distance jumps apart from sensor glitches (see the [`motion`] module).
+//!
+//! ## Statistics
+//!
+//! Count, mean, extremes and standard deviation of the latest distances can be tracked over a
+//! sliding window, e.g. fed by continuous measurement, and distances can be aggregated over time
+//! into a histogram (see the [`stats`] module).
+//!
+//! ## Sensor health
+//!
+//! [`health::StuckDetector`] reports sensors returning the very same distance for an abnormally
+//! long time, e.g. with a wedged **ECHO** line, which would otherwise pass for a perfectly stable
+//! reading, while [`HcSr04::health`] reports ping counters and latency percentiles for fleet
+//! monitoring (see the [`health`] module).
+//!
+//! [`HcSr04::self_test`] pings the sensor a few times and diagnoses the responses, telling wiring
+//! faults such as an **ECHO** pin never responding or stuck high apart from software bugs (see
+//! the [`diagnostics`] module). At runtime, [`HcSr04Builder::disconnect_detection`] reports a
+//! missing or unpowered sensor as [`Error::SensorNotResponding`] rather than as no object in
+//! range.
+//!
+//! ## Presence and zone detection
+//!
+//! [`presence::PresenceDetector`] reports objects coming closer than a threshold distance and
+//! moving away again, with hysteresis and debouncing (see the [`presence`] module).
+//! Similarly, [`zone::ZoneDetector`] reports which of a set of distance bands the object occupies
+//! (see the [`zone`] module), while [`dwell::Dwell`] fires once an object has been within (or
+//! away from) a distance for at least a given time (see the [`dwell`] module). Rather than
+//! absolute distances, [`HcSr04::measure_delta`] returns the deviation from a baseline captured
+//! in the empty scene, optionally adapting to slow drift while no object is present (see the
+//! [`baseline`] module).
+//!
+//! Building upon them, ready-made helpers cover common applications:
+//!
+//! - [`door::DoorSensor`] reports door openings and closings (see the [`door`] module)
+//! - [`parking::ParkingAssist`] grades the distance of a vehicle into warning levels, optionally
+//!   driving a buzzer or a LED (see the [`parking`] module)
+//! - [`tank::TankLevel`] converts the distance to a liquid surface into fill height, volume and
+//!   percent full (see the [`tank`] module)
+//! - [`alarm::Alarm`] turns an alarm on and off by threshold rules, with hysteresis and minimum
+//!   on and off times, optionally driving a relay or buzzer pin from the continuous mode (see the
+//!   [`alarm`] module)
+//! - [`led::LedIndicator`] shows proximity as LED blink rate or brightness, e.g. for field
+//!   diagnostics without a display (see the [`led`] module)
+//!
+//! ## Multiple sensors
+//!
+//! [`HcSr04Array`] measures several sensors in sequence, leaving a configurable delay between
+//! pings so that ultrasonic bursts don't interfere with each other. Sensors whose **TRIGGER**
+//! lines are wired to one GPIO pin are measured together by [`shared::SharedTrigger`], firing a
+//! single **TRIGGER** pulse (see the [`shared`] module). Conversely, [`aggregator::Aggregator`]
+//! runs every sensor on its own worker thread, merging their measurements into one labeled
+//! stream (see the [`aggregator`] module).
+//!
+//! Two sensors across a doorway can count people going in and out through
+//! [`counter::PeopleCounter`] (see the [`counter`] module).
+//!
+//! ## Sharing the sensor
+//!
+//! [`HcSr04Handle`] is a cloneable, thread-safe handle to the sensor, so that many threads (e.g.
+//! web request handlers) can take measurements without wrapping the driver themselves (see the
+//! [`handle`] module). A token bucket [`limiter::RateLimiter`] set on the handle spaces bursts
+//! of requests to the sensor's cycle, queuing or rejecting excess ones (see the [`limiter`]
+//! module).
+//!
+//! Measurements blocked waiting for the **ECHO** pulse can be interrupted from another thread,
+//! e.g. during application shutdown, through the sensor's [`CancelToken`] or
+//! [`HcSr04Handle::abort`] (see the [`cancel`] module).
+//!
+//! ## Writing sensor-agnostic code
+//!
+//! Applications written against the [`DistanceSensor`] trait, implemented by every supported
+//! sensor and by the mock one, can swap hardware without code changes (see the [`sensor`]
+//! module).
+//!
+//! ## Testing without hardware
+//!
+//! With the `mock` feature enabled, `MockHcSr04` replays scripted readings through the regular
+//! sensor API (see the `mock` module).
+//!
+//! ## Asynchronous measurement
+//!
+//! With the `tokio` feature enabled, `HcSr04Async` performs measurements without blocking the
+//! async runtime (see the `asynchronous` module). The `stream` feature additionally provides a
+//! `DistanceStream` of measurements, usable with `futures` combinators (see the `stream` module).
+//! Projects on the async-std runtime can enable the `async-std` feature instead, providing the
+//! same non-blocking measurements and streams without pulling in tokio (see the `async_std`
+//! module).
+//!
+//! ## Serialization
+//!
+//! With the `serde` feature enabled, public value types such as [`Measurement`], [`Unit`] and
+//! [`Distance`] implement `serde`'s `Serialize` and `Deserialize`, so that measurements can be
+//! pushed straight into JSON APIs, configuration files and message queues.
+//!
+//! ## Publishing measurements
+//!
+//! With the `mqtt` feature enabled, measurements can be published to an MQTT broker, e.g. to feed
+//! home-automation systems, while the `prometheus` feature exports readings and sensor health
+//! metrics to be scraped by Prometheus, the `http` feature serves the current distance as JSON on
+//! `GET /distance`, the `websocket` feature pushes live measurements to browser dashboards and the
+//! `influxdb` feature writes them to InfluxDB for time-series storage, while the `ros2` feature
+//! publishes them as `sensor_msgs/Range` messages to a ROS 2 graph (see the `sinks` module).
+//! With the `dbus` feature enabled, the sensor is exposed as a D-Bus object to the other processes
+//! on the same machine (see the `dbus` module).
+//!
+//! With the `recorder` feature enabled, measurements can be recorded to CSV or JSON Lines files
+//! for long-term data collection (see the `recorder` module), and replayed later through the
+//! normal sensor API, without hardware (see the `replay` module).
+//!
 //! ## Calibrate measurement
 //!
 //! Distance measurement can be calibrated at runtime using the [`HcSr04::calibrate`] method that
-//! this library exposes, passing the current ambient temperature as `f32`.
+//! this library exposes, passing the current ambient temperature as [`Float`]. When relative
+//! humidity is known as well, [`HcSr04::calibrate_env`] uses a more accurate speed of sound
+//! model, while [`HcSr04::calibrate_atmosphere`] also accounts for barometric pressure (see the
+//! [`calibration`] module). Calibration can be automated as well, wiring in a temperature sensor
+//! through [`HcSr04::auto_calibrate`], while [`HcSr04::calibrate_reference`] compensates mounting
+//! offsets against known reference distances. With the `profile` feature enabled, calibration can
+//! be persisted across restarts (see the `profile` module). For propagation media other than air,
+//! the speed of sound can be set directly through [`HcSr04::set_sound_speed`].
 //!
-//! ```rust
+//! ```rust,no_run
 //! use hc_sr04::{HcSr04, Unit};
 //!
 //! // Initialize driver.
 //! let mut ultrasonic = HcSr04::new(24, 23, None).unwrap();
 //!
 //! // Calibrate measurement with ambient temperature.
-//! ultrasonic.calibrate(23_f32);
+//! ultrasonic.calibrate(23.);
 //!
 //! // Perform distance measurement.
 //! match ultrasonic.measure_distance(Unit::Centimeters).unwrap() {
-//!     Some(dist) => println!("Distance: {.1}cm", dist),
+//!     Some(dist) => println!("Distance: {:.1}cm", dist),
 //!     None => println!("Object out of range"),
 //! }
 //! ```
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+pub mod aggregator;
+#[cfg(feature = "std")]
+pub mod alarm;
+#[cfg(feature = "std")]
+pub mod array;
+#[cfg(feature = "async-std")]
+pub mod async_std;
+#[cfg(feature = "tokio")]
+pub mod asynchronous;
+#[cfg(feature = "std")]
+pub mod backend;
+#[cfg(feature = "std")]
+pub mod baseline;
+#[cfg(feature = "std")]
+pub mod builder;
+#[cfg(feature = "std")]
+pub mod burst;
+#[cfg(feature = "std")]
+pub mod calibration;
+#[cfg(feature = "std")]
+pub mod cancel;
+#[cfg(feature = "config")]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod continuous;
+#[cfg(feature = "std")]
+pub mod counter;
+#[cfg(feature = "dbus")]
+pub mod dbus;
+#[cfg(feature = "std")]
+pub mod diagnostics;
+pub mod distance;
+#[cfg(feature = "std")]
+pub mod door;
+#[cfg(feature = "std")]
+pub mod duty;
+#[cfg(feature = "std")]
+pub mod dwell;
+#[cfg(feature = "std")]
 pub mod error;
+pub mod filter;
+#[cfg(feature = "std")]
+pub mod handle;
+#[cfg(feature = "std")]
+pub mod health;
+#[cfg(feature = "i2c")]
+pub mod i2c;
+#[cfg(feature = "std")]
+pub mod led;
+#[cfg(feature = "std")]
+pub mod limiter;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod model;
+#[cfg(feature = "std")]
+pub mod motion;
+#[cfg(feature = "std")]
+pub mod noise;
+#[cfg(feature = "std")]
+pub mod parking;
+pub mod portable;
+#[cfg(feature = "std")]
+pub mod presence;
+#[cfg(feature = "profile")]
+pub mod profile;
+#[cfg(feature = "std")]
+pub mod quality;
+#[cfg(feature = "realtime")]
+pub mod realtime;
+#[cfg(feature = "std")]
+pub mod recent;
+#[cfg(feature = "recorder")]
+pub mod recorder;
+#[cfg(feature = "recorder")]
+pub mod replay;
+#[cfg(feature = "std")]
+pub mod sensor;
+#[cfg(feature = "rppal")]
+pub mod shared;
+#[cfg(feature = "signal")]
+pub mod signal;
+#[cfg(any(
+    feature = "http",
+    feature = "influxdb",
+    feature = "mqtt",
+    feature = "prometheus",
+    feature = "ros2",
+    feature = "websocket"
+))]
+pub mod sinks;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "stream")]
+pub mod stream;
+#[cfg(feature = "std")]
+pub mod tank;
+#[cfg(feature = "us100")]
+pub mod us100;
+#[cfg(feature = "std")]
+pub mod zone;
 
+#[cfg(feature = "std")]
+pub use array::HcSr04Array;
+#[cfg(feature = "tokio")]
+pub use asynchronous::HcSr04Async;
+#[cfg(feature = "std")]
+use backend::{Backend, Timing};
+#[cfg(feature = "std")]
+pub use builder::{HcSr04Builder, Pull};
+#[cfg(feature = "std")]
+use calibration::{AutoCalibration, Correction, TemperatureSource};
+#[cfg(feature = "std")]
+pub use cancel::CancelToken;
+pub use distance::Distance;
+#[cfg(feature = "std")]
 use error::Error;
-use rppal::gpio::{Gpio, InputPin, Level, OutputPin, Trigger};
+#[cfg(feature = "std")]
+use filter::{DistanceFilter, Pipeline};
+#[cfg(feature = "std")]
+pub use handle::HcSr04Handle;
+#[cfg(feature = "mock")]
+pub use mock::MockHcSr04;
+#[cfg(feature = "std")]
+use portable::echo_timeout;
+pub use portable::Float;
+#[cfg(feature = "std")]
+use recent::RecentReadings;
+#[cfg(feature = "rppal")]
+use rppal::gpio::{Gpio, InputPin, OutputPin};
+#[cfg(feature = "std")]
+pub use sensor::DistanceSensor;
+#[cfg(feature = "std")]
 use std::{
-    thread,
-    time::{Duration, Instant},
+    mem, thread,
+    time::{Duration, Instant, SystemTime},
 };
+#[cfg(feature = "stream")]
+pub use stream::DistanceStream;
 
+#[cfg(feature = "std")]
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// [`Backend`] used by [`HcSr04`] when not specified otherwise.
+#[cfg(feature = "rppal")]
+type DefaultBackend = backend::Rppal;
+/// [`Backend`] used by [`HcSr04`] when not specified otherwise.
+#[cfg(all(feature = "std", not(feature = "rppal")))]
+type DefaultBackend = ();
+
 /// Measuring unit (defaults to [`Unit::Meters`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Unit {
     Millimeters,
     Centimeters,
     Decimeters,
+    #[default]
     Meters,
+    Inches,
+    Feet,
+    Yards,
 }
 
-/// **HC-SR04** ultrasonic sensor on *Raspberry Pi*.
-///
-/// # Fileds
-///
-/// - `trig`: **TRIGGER** output GPIO pin
-/// - `echo`: **ECHO** input GPIO pin
-/// - `temp`: ambient **Temperature** measure calibration
-/// - `sound_speed`: speed of sound given the ambient **Temperature**
-/// - `timeout`: **ECHO** pin polling timeout, considering the maximum measuring range of 4m for
-///     the sensor and the speed of sound given the ambient **Temperature**
-#[derive(Debug)]
-pub struct HcSr04 {
-    trig: OutputPin,
-    echo: InputPin,
-    sound_speed: f32,
-    timeout: Duration,
+impl Unit {
+    /// Number of units in one meter.
+    fn per_meter(self) -> Float {
+        match self {
+            Unit::Millimeters => 1000.,
+            Unit::Centimeters => 100.,
+            Unit::Decimeters => 10.,
+            Unit::Meters => 1.,
+            Unit::Inches => 1. / 0.0254,
+            Unit::Feet => 1. / 0.3048,
+            Unit::Yards => 1. / 0.9144,
+        }
+    }
+
+    /// Symbol of the unit (e.g. `"cm"`).
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Unit::Millimeters => "mm",
+            Unit::Centimeters => "cm",
+            Unit::Decimeters => "dm",
+            Unit::Meters => "m",
+            Unit::Inches => "in",
+            Unit::Feet => "ft",
+            Unit::Yards => "yd",
+        }
+    }
 }
 
-impl HcSr04 {
-    /// Perform `sound_speed` and `timeout` calculations required to calibrate the sensor,
-    /// based on **ambient temperature**.
-    fn calibration_calc(temp: f32) -> (f32, Duration) {
-        /// Speed of sound at 0C in m/s.
-        const SOUND_SPEED_0C: f32 = 331.3;
-        /// Increase speed of sound over temperature factor m/[sC].
-        const SOUND_SPEED_INC_OVER_TEMP: f32 = 0.606;
-        /// Maximum measuring range for HC-SR04 sensor in m.
-        const MAX_RANGE: f32 = 4.0;
+/// Outcome of a distance measurement (see [`HcSr04::measure_outcome`]).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Outcome {
+    /// Object detected at the contained distance.
+    Distance(Distance),
+    /// No object within maximum measuring range: the **ECHO** pulse didn't end in time.
+    OutOfRange,
+    /// No **ECHO** pulse at all: it didn't start within the configured timeout (see
+    /// [`HcSr04Builder::echo_start_timeout`]), e.g. due to wiring or sensor failure.
+    NoEcho,
+    /// Object within the minimum measuring range (see [`HcSr04Builder::min_range`]), where the
+    /// sensor returns implausibly short pulses.
+    TooClose,
+    /// Reading rejected by the filters set on the sensor.
+    Rejected,
+}
 
-        // Speed of sound, depending on ambient temperature (if `temp` is `None`, default to 20C).
-        let sound_speed = SOUND_SPEED_0C + (SOUND_SPEED_INC_OVER_TEMP * temp);
+impl Outcome {
+    /// Detected distance, if any.
+    pub fn distance(self) -> Option<Distance> {
+        match self {
+            Self::Distance(distance) => Some(distance),
+            _ => None,
+        }
+    }
+}
 
-        // Polling timeout for **ECHO** pin: since max range for HC-SR04 is 4m, it doesn't make
-        // sense to wait longer than the time required to the ultrasonic sound wave to cover the
-        // max range distance. In other words, if the timeout is reached, the measurement was not
-        // successfull or the object is located too far away from the sensor in order to be
-        // detected.
-        let timeout = Duration::from_secs_f32(MAX_RANGE / sound_speed * 2.);
+/// Distance measurement taken at a given time.
+///
+/// # Fields
+///
+/// - `distance`: measured distance (`None` if no object is present within maximum measuring
+///   range)
+/// - `variance`: variance of the filtered `distance` estimate in m² (`None` if no filter tracks
+///   it or no object is present)
+/// - `timestamp`: time at which the measurement completed
+/// - `confidence`: quality score in `0.0..=1.0` (see the [`quality`] module)
+/// - `echo_duration`: raw **ECHO** pulse width the distance was computed from (`None` if no
+///   object is present within maximum measuring range)
+/// - `temperature_used`: ambient temperature in *Celsius degrees* the sensor was calibrated with
+///   (`None` if the speed of sound was set directly)
+/// - `retries`: number of failed attempts before the measurement (see
+///   [`HcSr04::measurement_retrying`])
+/// - `filtered`: whether `distance` went through the filters set on the sensor or on the stream
+///   it was read from
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Measurement {
+    pub distance: Option<Distance>,
+    pub variance: Option<Float>,
+    pub timestamp: SystemTime,
+    #[cfg_attr(feature = "serde", serde(default = "full_confidence"))]
+    pub confidence: Float,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub echo_duration: Option<Duration>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub temperature_used: Option<Float>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub retries: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub filtered: bool,
+}
 
-        (sound_speed, timeout)
+#[cfg(feature = "std")]
+impl Measurement {
+    /// Create a new measurement of `distance` completed at `timestamp`, with full confidence and
+    /// no further metadata (e.g. for feeding detectors with synthetic measurements).
+    pub fn new(distance: Option<Distance>, timestamp: SystemTime) -> Self {
+        Self {
+            distance,
+            variance: None,
+            timestamp,
+            confidence: 1.,
+            echo_duration: None,
+            temperature_used: None,
+            retries: 0,
+            filtered: false,
+        }
     }
+}
+
+/// Confidence of measurements recorded before it was tracked.
+#[cfg(feature = "serde")]
+fn full_confidence() -> Float {
+    1.
+}
+
+/// **HC-SR04** ultrasonic sensor, driven by *Raspberry Pi* GPIO unless another [`Backend`] is
+/// specified.
+///
+/// # Fileds
+///
+/// - `backend`: GPIO access and pulse timing
+/// - `timing`: **TRIGGER** pulse width and **ECHO** pin polling timeouts; unless explicitly set,
+///   the **ECHO** pulse timeout considers the maximum measuring range for the sensor and the
+///   speed of sound given the ambient **Temperature**
+/// - `max_range`: maximum measuring range in m, used to compute the **ECHO** pulse timeout
+/// - `min_range`: minimum measuring range in m, shorter distances being implausible
+/// - `echo_timeout`: user provided **ECHO** pulse timeout, overriding the computed one
+/// - `echo_timeout_error`: report **ECHO** pulse timeouts as errors rather than out of range
+/// - `min_cycle`: minimum time between consecutive **TRIGGER** pulses
+/// - `min_cycle_error`: report measurements attempted too soon as errors rather than waiting
+/// - `jitter`: maximum random spread of the gap between pings in continuous mode
+/// - `last_ping`: time of the latest **TRIGGER** pulse
+/// - `history`: outcomes of the latest pings
+/// - `watchdog`: consecutive failed pings after which the backend attempts to recover
+/// - `failures`: current number of consecutive failed pings
+/// - `disconnect_detection`: consecutive pings without response after which the sensor is
+///   reported as not responding
+/// - `silent_pings`: current number of consecutive pings without response
+/// - `counters`: health counters (see [`HcSr04::health`])
+/// - `recent`: ring buffer of the latest measurements, if enabled
+/// - `unit`: default measuring unit
+/// - `filter`: filters applied to measured distances
+/// - `sound_speed`: speed of sound given the ambient **Temperature**, or set directly
+/// - `temperature`: ambient temperature the sensor is calibrated with, `None` if the speed of
+///   sound was set directly
+/// - `relative_humidity`: ambient relative humidity the sensor is calibrated with, if known
+/// - `pressure`: ambient barometric pressure the sensor is calibrated with, if known
+/// - `auto_calibration`: ambient temperature source periodically recalibrating the sensor
+/// - `correction`: linear correction applied to measured distances
+/// - `baseline`: distance measured in the empty scene, if captured
+/// - `adaptation`: settings of the baseline adapting to drift, if enabled
+/// - `cancel`: flag cancelling in-flight and subsequent measurements
+/// - `realtime`: `SCHED_FIFO` priority of the measuring thread during **ECHO** pulse capture
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct HcSr04<B = DefaultBackend> {
+    backend: B,
+    timing: Timing,
+    max_range: Float,
+    min_range: Float,
+    echo_timeout: Option<Duration>,
+    echo_timeout_error: bool,
+    min_cycle: Duration,
+    min_cycle_error: bool,
+    jitter: Option<Duration>,
+    last_ping: Option<Instant>,
+    history: quality::History,
+    watchdog: Option<u32>,
+    failures: u32,
+    disconnect_detection: Option<u32>,
+    silent_pings: u32,
+    counters: health::Counters,
+    recent: Option<RecentReadings>,
+    unit: Unit,
+    filter: Pipeline,
+    sound_speed: Float,
+    temperature: Option<Float>,
+    relative_humidity: Option<Float>,
+    pressure: Option<Float>,
+    auto_calibration: Option<AutoCalibration>,
+    correction: Correction,
+    baseline: Option<Distance>,
+    adaptation: Option<baseline::Adaptation>,
+    cancel: CancelToken,
+    #[cfg(feature = "realtime")]
+    realtime: Option<i32>,
+}
 
+#[cfg(feature = "rppal")]
+impl HcSr04 {
     /// Initialize HC-SR04 sensor and register GPIO interrupt on `echo` pin for RisingEdge events
     /// in order to poll it for bouncing UltraSonic waves detection.
     ///
+    /// Shorthand for [`HcSr04::builder`] with default options. Use the builder in order to
+    /// configure the sensor further.
+    ///
     /// # Parameters
     ///
     /// - `trig`: **TRIGGER** output GPIO pin
     /// - `echo`: **ECHO** input GPIO pin
     /// - `temp`: ambient **TEMPERATURE** used for calibration (if `None` defaults to `20.0`)
-    pub fn new(trig: u8, echo: u8, temp: Option<f32>) -> Result<Self> {
-        let gpio = Gpio::new()?;
+    pub fn new(trig: u8, echo: u8, temp: Option<Float>) -> Result<Self> {
+        let builder = Self::builder(trig, echo);
+        match temp {
+            Some(temp) => builder.temperature(temp),
+            None => builder,
+        }
+        .build()
+    }
 
-        let mut echo = gpio.get(echo)?.into_input_pulldown();
-        echo.set_interrupt(Trigger::Both)?;
+    /// Initialize HC-SR04 sensor as [`HcSr04::new`] does, getting the pins from the
+    /// application's `gpio` instance rather than opening a new one.
+    pub fn with_gpio(gpio: &Gpio, trig: u8, echo: u8, temp: Option<Float>) -> Result<Self> {
+        let builder = Self::builder(trig, echo);
+        match temp {
+            Some(temp) => builder.temperature(temp),
+            None => builder,
+        }
+        .build_with(gpio)
+    }
 
-        let (sound_speed, timeout) = Self::calibration_calc(temp.unwrap_or(20.));
+    /// Initialize HC-SR04 sensor from already set up `trig` (**TRIGGER**) output and `echo`
+    /// (**ECHO**) input pins, e.g. configured by the application, registering GPIO interrupt for
+    /// both edges on `echo`.
+    ///
+    /// - `temp`: ambient **TEMPERATURE** used for calibration (if `None` defaults to `20.0`)
+    pub fn with_pins(trig: OutputPin, echo: InputPin, temp: Option<Float>) -> Result<Self> {
+        let builder = HcSr04Builder::with_backend(backend::Rppal::from_pins(trig, echo)?);
+        Ok(match temp {
+            Some(temp) => builder.temperature(temp),
+            None => builder,
+        }
+        .build())
+    }
 
-        Ok(Self {
-            trig: gpio.get(trig)?.into_output_low(),
-            echo,
-            sound_speed,
-            timeout,
-        })
+    /// Tear the sensor down, clearing the **ECHO** pin interrupt and resetting both pins to their
+    /// previous mode, and return the **TRIGGER** and **ECHO** GPIO pin numbers, e.g. to
+    /// re-create the sensor later on.
+    ///
+    /// Dropping the sensor tears it down the same way.
+    ///
+    /// ```rust,no_run
+    /// use hc_sr04::HcSr04;
+    ///
+    /// let ultrasonic = HcSr04::new(24, 23, None).unwrap();
+    /// let (trig, echo) = ultrasonic.release();
+    /// let ultrasonic = HcSr04::new(trig, echo, Some(25.)).unwrap();
+    /// ```
+    pub fn release(self) -> (u8, u8) {
+        self.backend.pins()
     }
 
+    /// Create an [`HcSr04Builder`] for the sensor wired to `trig` (**TRIGGER**) and `echo`
+    /// (**ECHO**) GPIO pins.
+    pub fn builder(trig: u8, echo: u8) -> HcSr04Builder<builder::Pins> {
+        HcSr04Builder::new(trig, echo)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<B: Backend> HcSr04<B> {
     /// Calibrate the sensor with the given **ambient temperature** (`temp`) expressed as *Celsius
     /// degrees*.
-    pub fn calibrate(&mut self, temp: f32) {
-        (self.sound_speed, self.timeout) = Self::calibration_calc(temp);
+    pub fn calibrate(&mut self, temp: Float) {
+        self.set_sound_speed(calibration::sound_speed(temp));
+        self.temperature = Some(temp);
+    }
+
+    /// Recalibrate the sensor with the given **ambient temperature** (`temp`) expressed as
+    /// *Celsius degrees*, keeping the relative humidity and barometric pressure it was last
+    /// calibrated with.
+    fn recalibrate(&mut self, temp: Float) {
+        let (relative_humidity, pressure) = (self.relative_humidity, self.pressure);
+        self.set_sound_speed(calibration::sound_speed_env(
+            temp,
+            relative_humidity,
+            pressure,
+        ));
+        self.temperature = Some(temp);
+        self.relative_humidity = relative_humidity;
+        self.pressure = pressure;
+    }
+
+    /// Calibrate the sensor with the given **ambient temperature** (`temp`) expressed as *Celsius
+    /// degrees* and **relative humidity** expressed in percent, using a more complete speed of
+    /// sound model (see [`calibration::sound_speed_humid`]).
+    pub fn calibrate_env(&mut self, temp: Float, relative_humidity: Float) {
+        self.set_sound_speed(calibration::sound_speed_humid(temp, relative_humidity));
+        self.temperature = Some(temp);
+        self.relative_humidity = Some(relative_humidity);
+    }
+
+    /// Calibrate the sensor with the given **ambient temperature** (`temp`) expressed as *Celsius
+    /// degrees*, **relative humidity** expressed in percent and **barometric pressure** expressed
+    /// in hPa (see [`calibration::sound_speed_at`]), e.g. for high-altitude deployments.
+    ///
+    /// When only the altitude is known, the pressure can be estimated through
+    /// [`calibration::pressure_at_altitude`].
+    pub fn calibrate_atmosphere(&mut self, temp: Float, relative_humidity: Float, pressure: Float) {
+        self.set_sound_speed(calibration::sound_speed_at(
+            temp,
+            relative_humidity,
+            pressure,
+        ));
+        self.temperature = Some(temp);
+        self.relative_humidity = Some(relative_humidity);
+        self.pressure = Some(pressure);
+    }
+
+    /// Automatically recalibrate the sensor reading the ambient temperature from `source`, at
+    /// most once per `interval`, right before measuring. Replaces any previously set source.
+    ///
+    /// Readings from `source` returning `None` leave the current calibration untouched. The
+    /// relative humidity and barometric pressure the sensor was last calibrated with (e.g. set
+    /// through [`HcSr04Builder::relative_humidity`] and [`HcSr04Builder::pressure`]) are kept.
+    pub fn auto_calibrate(
+        &mut self,
+        source: impl TemperatureSource + Send + 'static,
+        interval: Duration,
+    ) {
+        self.auto_calibration = Some(AutoCalibration::new(source, interval));
+    }
+
+    /// Stop automatic recalibration (see [`HcSr04::auto_calibrate`]), keeping the current
+    /// calibration.
+    pub fn stop_auto_calibration(&mut self) {
+        self.auto_calibration = None;
+    }
+
+    /// Calibrate the sensor against known reference distances, given `(measured, reference)`
+    /// pairs expressed in m, where `measured` is the distance measured by the sensor (with its
+    /// current correction applied) pointed at a target placed at `reference` distance.
+    ///
+    /// The fitted [`Correction`] compensates mounting offsets and unit-to-unit sensor variation,
+    /// and is applied to all subsequent measurements, before filtering. Returns the resulting
+    /// correction, or `None` (leaving the current one untouched) if it cannot be fitted (see
+    /// [`Correction::fit`]).
+    pub fn calibrate_reference(&mut self, measured: &[(Float, Float)]) -> Option<Correction> {
+        let correction = Correction::fit(measured)?.compose(&self.correction);
+        self.correction = correction;
+        Some(correction)
+    }
+
+    /// Linear correction applied to measured distances.
+    pub fn correction(&self) -> Correction {
+        self.correction
+    }
+
+    /// Set the linear correction applied to measured distances, e.g. restoring a previously
+    /// fitted one (see [`HcSr04::calibrate_reference`]).
+    pub fn set_correction(&mut self, correction: Correction) {
+        self.correction = correction;
+    }
+
+    /// Speed of sound in m/s the sensor is currently calibrated with.
+    pub fn sound_speed(&self) -> Float {
+        self.sound_speed
+    }
+
+    /// Set the speed of sound in m/s directly, rather than deriving it from the ambient
+    /// conditions (e.g. for underwater measurements or other propagation media), recomputing the
+    /// **ECHO** timeout unless an explicit one is set.
+    ///
+    /// Automatic recalibration (see [`HcSr04::auto_calibrate`]) overrides the speed set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sound_speed` is not positive and finite.
+    pub fn set_sound_speed(&mut self, sound_speed: Float) {
+        builder::assert_sound_speed(sound_speed);
+        self.sound_speed = sound_speed;
+        self.temperature = None;
+        self.relative_humidity = None;
+        self.pressure = None;
+        self.timing.pulse_timeout = self
+            .echo_timeout
+            .unwrap_or_else(|| echo_timeout(self.max_range, sound_speed));
+    }
+
+    /// Maximum measuring range in m.
+    pub fn max_range(&self) -> Float {
+        self.max_range
+    }
+
+    /// Set the maximum measuring range in m (see [`HcSr04Builder::max_range`]), recomputing the
+    /// **ECHO** timeout unless an explicit one is set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is not positive and finite.
+    pub fn set_max_range(&mut self, range: Float) {
+        builder::assert_max_range(range);
+        self.max_range = range;
+        self.timing.pulse_timeout = self
+            .echo_timeout
+            .unwrap_or_else(|| echo_timeout(range, self.sound_speed));
+    }
+
+    /// Fraction of the latest pings which received an **ECHO** pulse in time, rather than timing
+    /// out or failing (see the [`quality`] module).
+    pub fn reliability(&self) -> Float {
+        self.history.reliability()
+    }
+
+    /// Minimum measuring range in m.
+    pub fn min_range(&self) -> Float {
+        self.min_range
+    }
+
+    /// Set the minimum measuring range in m (see [`HcSr04Builder::min_range`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is negative or not finite.
+    pub fn set_min_range(&mut self, range: Float) {
+        builder::assert_min_range(range);
+        self.min_range = range;
+    }
+
+    /// **ECHO** pulse timeout currently in use, either explicitly set or computed from the
+    /// maximum measuring range and the speed of sound.
+    pub fn echo_timeout(&self) -> Duration {
+        self.timing.pulse_timeout
+    }
+
+    /// Set (or remove, if `None`) an explicit **ECHO** pulse timeout, overriding the one computed
+    /// from the maximum measuring range and the speed of sound.
+    pub fn set_echo_timeout(&mut self, timeout: Option<Duration>) {
+        self.echo_timeout = timeout;
+        self.timing.pulse_timeout =
+            timeout.unwrap_or_else(|| echo_timeout(self.max_range, self.sound_speed));
+    }
+
+    /// Width of the **TRIGGER** pulse.
+    pub fn trigger_pulse(&self) -> Duration {
+        self.timing.trigger_pulse
+    }
+
+    /// Set the width of the **TRIGGER** pulse (see [`HcSr04Builder::trigger_pulse`]).
+    pub fn set_trigger_pulse(&mut self, width: Duration) {
+        self.timing.trigger_pulse = width;
+    }
+
+    /// Maximum time to wait for the **ECHO** pulse to start, if any.
+    pub fn echo_start_timeout(&self) -> Option<Duration> {
+        self.timing.start_timeout
+    }
+
+    /// Set (or remove, if `None`) the maximum time to wait for the **ECHO** pulse to start (see
+    /// [`HcSr04Builder::echo_start_timeout`]).
+    pub fn set_echo_start_timeout(&mut self, timeout: Option<Duration>) {
+        self.timing.start_timeout = timeout;
+    }
+
+    /// Set whether **ECHO** pulse timeouts are reported as [`Error::EchoPulseTimeout`] rather than
+    /// objects out of range (see [`HcSr04Builder::echo_timeout_error`]).
+    pub fn set_echo_timeout_error(&mut self, enabled: bool) {
+        self.echo_timeout_error = enabled;
+    }
+
+    /// Minimum time between consecutive **TRIGGER** pulses (see [`HcSr04Builder::min_cycle`]).
+    pub fn min_cycle(&self) -> Duration {
+        self.min_cycle
+    }
+
+    /// Set the minimum time between consecutive **TRIGGER** pulses (see
+    /// [`HcSr04Builder::min_cycle`]).
+    pub fn set_min_cycle(&mut self, cycle: Duration) {
+        self.min_cycle = cycle;
+    }
+
+    /// Set whether measurements attempted before the minimum cycle elapsed fail with
+    /// [`Error::CycleTooShort`] rather than waiting (see [`HcSr04Builder::min_cycle_error`]).
+    pub fn set_min_cycle_error(&mut self, enabled: bool) {
+        self.min_cycle_error = enabled;
+    }
+
+    /// Maximum random spread of the gap between pings in continuous mode, if enabled (see
+    /// [`HcSr04Builder::jitter`]).
+    pub fn jitter(&self) -> Option<Duration> {
+        self.jitter
+    }
+
+    /// Set (or disable, if `None`) the maximum random spread of the gap between pings in
+    /// continuous mode (see [`HcSr04Builder::jitter`]), taking effect on workers started
+    /// afterwards.
+    pub fn set_jitter(&mut self, jitter: Option<Duration>) {
+        self.jitter = jitter;
+    }
+
+    /// Set (or disable, if `None`) the number of consecutive failed pings after which the backend
+    /// attempts to recover (see [`HcSr04Builder::watchdog`]).
+    pub fn set_watchdog(&mut self, failures: Option<u32>) {
+        self.watchdog = failures.map(|failures| failures.max(1));
+        self.failures = 0;
+    }
+
+    /// Whether the sensor is powered, or `None` if the backend doesn't switch its supply (see
+    /// [`HcSr04Builder::power_pin`]).
+    pub fn is_powered(&self) -> Option<bool> {
+        self.backend.is_powered()
+    }
+
+    /// Power the sensor on, waiting for it to warm up, or off, e.g. between measurements to save
+    /// battery, if the backend switches its supply (see [`HcSr04Builder::power_pin`]).
+    /// Measuring powers the sensor on again anyway.
+    pub fn set_powered(&mut self, on: bool) -> Result<()> {
+        self.backend.set_powered(on)
+    }
+
+    /// Set the time the sensor takes to become operational after power-on, if the backend
+    /// switches its supply (see [`HcSr04Builder::warm_up`]).
+    pub fn set_warm_up(&mut self, delay: Duration) {
+        self.backend.set_warm_up(delay);
+    }
+
+    /// Set (or disable, if `None`) the number of consecutive pings without response after which
+    /// measurement fails with [`Error::SensorNotResponding`] (see
+    /// [`HcSr04Builder::disconnect_detection`]).
+    pub fn set_disconnect_detection(&mut self, pings: Option<u32>) {
+        self.disconnect_detection = pings.map(|pings| pings.max(1));
+        self.silent_pings = 0;
+    }
+
+    /// Keep (or stop keeping, if `None`) the last `capacity` measurements in a ring buffer (see
+    /// [`HcSr04Builder::recent`]), discarding the ones kept so far.
+    pub fn set_recent(&mut self, capacity: Option<usize>) {
+        self.recent = capacity.map(RecentReadings::new);
+    }
+
+    /// Last (at most) `n` measurements kept in the ring buffer, oldest first, or none if it isn't
+    /// enabled (see [`HcSr04Builder::recent`]).
+    pub fn recent(&self, n: usize) -> Vec<Measurement> {
+        self.recent
+            .as_ref()
+            .map_or_else(Vec::new, |recent| recent.recent(n))
+    }
+
+    /// Measurements kept in the ring buffer which were taken within the last `window`, oldest
+    /// first, or none if it isn't enabled (see [`HcSr04Builder::recent`]).
+    ///
+    /// Only the last measurements up to the buffer capacity are kept: size it after the longest
+    /// window and the measuring rate.
+    pub fn history(&self, window: Duration) -> Vec<Measurement> {
+        self.recent
+            .as_ref()
+            .map_or_else(Vec::new, |recent| recent.history(window))
+    }
+
+    /// Shared handle to the ring buffer of the last measurements, if enabled, which keeps
+    /// observing it after the sensor is moved, e.g. to a continuous worker.
+    pub fn recent_readings(&self) -> Option<RecentReadings> {
+        self.recent.clone()
+    }
+
+    /// Token cancelling the in-flight measurement and the subsequent ones (see the [`cancel`]
+    /// module), e.g. from another thread during application shutdown.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    /// Keep `measurement` in the ring buffer, if enabled.
+    fn record(&self, measurement: Measurement) {
+        if let Some(recent) = &self.recent {
+            recent.push(measurement);
+        }
+    }
+
+    /// Set (or disable, if `None`) the `SCHED_FIFO` priority the measuring thread is raised to
+    /// while capturing the **ECHO** pulse (see [`HcSr04Builder::realtime`]).
+    #[cfg(feature = "realtime")]
+    pub fn set_realtime(&mut self, priority: Option<i32>) {
+        self.realtime = priority;
+    }
+
+    /// Default measuring unit, as configured through [`HcSr04Builder::unit`].
+    pub fn unit(&self) -> Unit {
+        self.unit
+    }
+
+    /// Set the default measuring unit, used by [`HcSr04::measure_default`] (see
+    /// [`HcSr04Builder::unit`]).
+    pub fn set_unit(&mut self, unit: Unit) {
+        self.unit = unit;
+    }
+
+    /// Filters applied to measured distances, e.g. to append further stages.
+    pub fn filter_mut(&mut self) -> &mut Pipeline {
+        &mut self.filter
+    }
+
+    /// Replace the filters applied to measured distances.
+    pub fn set_filter(&mut self, filter: Pipeline) {
+        self.filter = filter;
     }
 
     /// Perform **distance measurement**.
     ///
     /// Returns `Ok` variant if measurement succedes. Inner `Option` value is `None` if no object
-    /// is present within maximum measuring range (*4m*); otherwhise, on `Some` variant instead,
-    /// contained value represents distance expressed as the specified `unit`
-    /// (**unit of measure**).
-    pub fn measure_distance(&mut self, unit: Unit) -> Result<Option<f32>> {
-        self.trig.set_high();
-        thread::sleep(Duration::from_micros(10));
-        self.trig.set_low();
-
-        // Wait for the `RisingEdge` by ensuring the resulting level is `Level::High`.
-        while self.echo.poll_interrupt(false, None)? != Some(Level::High) {}
-        let instant = Instant::now();
-        // Wait for the `FallingEdge` by ensuring the resulting level is `Level::Low`.
-        if self.echo.poll_interrupt(false, Some(self.timeout))? != Some(Level::Low) {
+    /// is present within maximum measuring range (*4m* by default); otherwhise, on `Some` variant
+    /// instead, contained value represents distance expressed as the specified `unit`
+    /// (**unit of measure**), filtered by the filters set on the sensor. Readings rejected by a
+    /// filter are reported as `None` as well.
+    ///
+    /// Fails with [`Error::EchoStartTimeout`] if the **ECHO** pulse doesn't start in time and,
+    /// if enabled, with [`Error::EchoPulseTimeout`] instead of returning `None` if it doesn't end
+    /// in time. Fails with [`Error::TooClose`] if the object is within the minimum measuring
+    /// range.
+    pub fn measure_distance(&mut self, unit: Unit) -> Result<Option<Float>> {
+        Ok(self
+            .measure_meters()?
+            .map(|distance| portable::to_unit(distance, unit)))
+    }
+
+    /// Perform **distance measurement** (see [`HcSr04::measure_distance`]), returning a unit-safe
+    /// [`Distance`].
+    pub fn measure(&mut self) -> Result<Option<Distance>> {
+        Ok(self.measure_meters()?.map(Distance::from_meters))
+    }
+
+    /// Perform **distance measurement** (see [`HcSr04::measure_distance`]), returning a
+    /// [uom](https://docs.rs/uom) length quantity, available with the `uom` feature.
+    #[cfg(feature = "uom")]
+    pub fn measure_length(&mut self) -> Result<Option<distance::Length>> {
+        Ok(self.measure()?.map(distance::Length::from))
+    }
+
+    /// Perform **distance measurement** (see [`HcSr04::measure_distance`]) in the default
+    /// measuring unit (see [`HcSr04Builder::unit`]), so that the unit isn't repeated at every
+    /// call site.
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "mock")]
+    /// # {
+    /// use hc_sr04::{MockHcSr04, Unit};
+    ///
+    /// let mut ultrasonic = MockHcSr04::mock(None);
+    /// ultrasonic.set_unit(Unit::Centimeters);
+    /// ultrasonic.push_distance(1.5, Unit::Meters);
+    ///
+    /// let dist = ultrasonic.measure_default().unwrap().unwrap();
+    /// assert!((dist - 150.).abs() < 0.01);
+    /// # }
+    /// ```
+    pub fn measure_default(&mut self) -> Result<Option<Float>> {
+        let unit = self.unit;
+        self.measure_distance(unit)
+    }
+
+    /// Perform **distance measurement** (see [`HcSr04::measure_distance`]), additionally
+    /// filtering the distance through the caller's `filter`.
+    ///
+    /// This allows selecting filters per call, e.g. using different smoothing for different
+    /// purposes on the same sensor.
+    pub fn measure_distance_with(
+        &mut self,
+        unit: Unit,
+        filter: &mut impl DistanceFilter,
+    ) -> Result<Option<Float>> {
+        Ok(self
+            .measure_meters()?
+            .and_then(|distance| filter.apply(distance))
+            .map(|distance| portable::to_unit(distance, unit)))
+    }
+
+    /// Perform **distance measurement** (see [`HcSr04::measure_distance`]), waiting at most
+    /// `timeout` for the **ECHO** pulse to end instead of the configured timeout (see
+    /// [`HcSr04::echo_timeout`]) for this call only.
+    ///
+    /// A shorter timeout speeds up measurement when only nearby objects matter, e.g. in fast
+    /// scanning loops: farther objects are reported as out of range.
+    ///
+    /// ```rust,no_run
+    /// use hc_sr04::{HcSr04, Unit};
+    /// use std::time::Duration;
+    ///
+    /// let mut ultrasonic = HcSr04::new(24, 23, None).unwrap();
+    ///
+    /// // Roughly 50cm round trip at 20C.
+    /// let timeout = Duration::from_micros(2_900);
+    /// match ultrasonic.measure_distance_with_timeout(Unit::Centimeters, timeout).unwrap() {
+    ///     Some(dist) => println!("Obstacle at {:.1}cm", dist),
+    ///     None => println!("No obstacle nearby"),
+    /// }
+    /// ```
+    pub fn measure_distance_with_timeout(
+        &mut self,
+        unit: Unit,
+        timeout: Duration,
+    ) -> Result<Option<Float>> {
+        let default = mem::replace(&mut self.timing.pulse_timeout, timeout);
+        let distance = self.measure_distance(unit);
+        self.timing.pulse_timeout = default;
+        distance
+    }
+
+    /// Perform **distance measurement**, telling apart why no distance is available, if so.
+    ///
+    /// Rather than failing with [`Error::EchoStartTimeout`], [`Error::EchoPulseTimeout`] (if
+    /// enabled) and [`Error::TooClose`], returns [`Outcome::NoEcho`], [`Outcome::OutOfRange`] and
+    /// [`Outcome::TooClose`] respectively.
+    ///
+    /// ```rust,no_run
+    /// use hc_sr04::{HcSr04, Outcome};
+    /// use std::time::Duration;
+    ///
+    /// let mut ultrasonic = HcSr04::builder(24, 23)
+    ///     .echo_start_timeout(Duration::from_millis(10))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// match ultrasonic.measure_outcome().unwrap() {
+    ///     Outcome::Distance(dist) => println!("Distance: {:.2}m", dist.as_meters()),
+    ///     Outcome::OutOfRange => println!("Object out of range"),
+    ///     Outcome::NoEcho => println!("No echo: check the wiring"),
+    ///     Outcome::TooClose => println!("Object too close"),
+    ///     Outcome::Rejected => println!("Reading rejected by the filters"),
+    /// }
+    /// ```
+    pub fn measure_outcome(&mut self) -> Result<Outcome> {
+        let echo = match self.measure_echo() {
+            Ok(Some(echo)) => echo,
+            Ok(None) | Err(Error::EchoPulseTimeout) => return Ok(Outcome::OutOfRange),
+            Err(Error::EchoStartTimeout) => return Ok(Outcome::NoEcho),
+            Err(error) => return Err(error),
+        };
+
+        Ok(match self.echo_meters(echo) {
+            Ok(Some(distance)) => Outcome::Distance(Distance::from_meters(distance)),
+            Ok(None) => Outcome::Rejected,
+            Err(Error::TooClose) => Outcome::TooClose,
+            Err(error) => return Err(error),
+        })
+    }
+
+    /// Perform filtered **distance measurement** in m.
+    fn measure_meters(&mut self) -> Result<Option<Float>> {
+        match self.measure_echo()? {
+            Some(echo) => self.echo_meters(echo),
+            None => Ok(None),
+        }
+    }
+
+    /// Convert `echo` to the corrected distance in m, filtered through the filters set on the
+    /// sensor, failing with [`Error::TooClose`] within the minimum measuring range.
+    fn echo_meters(&mut self, echo: Duration) -> Result<Option<Float>> {
+        let distance = self.corrected_meters(echo);
+        if distance < self.min_range {
+            return Err(Error::TooClose);
+        }
+
+        Ok(self.filter.apply(distance))
+    }
+
+    /// Convert `echo` to the corrected, unfiltered distance in m.
+    fn corrected_meters(&self, echo: Duration) -> Float {
+        self.correction
+            .apply(portable::echo_distance(echo, self.sound_speed))
+    }
+
+    /// Perform raw **ECHO** pulse measurement, returning the ultrasonic wave round-trip time
+    /// (time of flight).
+    ///
+    /// The pulse width is neither converted to distance nor filtered, allowing to apply a custom
+    /// speed of sound model or to log raw sensor data. Returns `None` if no object is present
+    /// within maximum measuring range, and fails as [`HcSr04::measure_distance`] does.
+    pub fn measure_echo(&mut self) -> Result<Option<Duration>> {
+        if self.cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        let temp = self
+            .auto_calibration
+            .as_mut()
+            .and_then(AutoCalibration::poll);
+        if let Some(temp) = temp {
+            self.recalibrate(temp);
+        }
+
+        if self.backend.is_powered() == Some(false) {
+            self.backend.set_powered(true)?;
+        }
+
+        // Let residual echoes of the previous measurement fade out.
+        let remaining = self
+            .last_ping
+            .and_then(|last| self.min_cycle.checked_sub(last.elapsed()))
+            .filter(|remaining| !remaining.is_zero());
+        if let Some(remaining) = remaining {
+            if self.min_cycle_error {
+                return Err(Error::CycleTooShort(remaining));
+            }
+            thread::sleep(remaining);
+        }
+        self.last_ping = Some(Instant::now());
+
+        #[cfg(feature = "realtime")]
+        let _scheduler = self
+            .realtime
+            .map(realtime::SchedulerGuard::fifo)
+            .transpose()?;
+
+        let idle_high =
+            self.disconnect_detection.is_some() && self.backend.echo_level() == Some(true);
+        let start = Instant::now();
+        let echo = self.backend.ping_cancellable(&self.timing, &self.cancel);
+        if matches!(echo, Err(Error::Cancelled)) {
+            return echo;
+        }
+        self.counters.record(&echo, start.elapsed());
+        // A pulse never starting, or never ending from an ECHO pin already high before the ping.
+        self.silent_pings = match echo {
+            Err(Error::EchoStartTimeout) => self.silent_pings.saturating_add(1),
+            Ok(None) if idle_high => self.silent_pings.saturating_add(1),
+            _ => 0,
+        };
+        self.history.record(matches!(echo, Ok(Some(_))));
+        self.failures = match echo {
+            Ok(Some(_)) => 0,
+            _ => self.failures.saturating_add(1),
+        };
+        if matches!(self.watchdog, Some(watchdog) if self.failures >= watchdog) {
+            self.failures = 0;
+            self.backend.recover()?;
+        }
+        if matches!(self.disconnect_detection, Some(pings) if self.silent_pings >= pings) {
+            return Err(Error::SensorNotResponding);
+        }
+
+        match echo? {
+            Some(echo) => Ok(Some(echo)),
+            None if self.echo_timeout_error => Err(Error::EchoPulseTimeout),
             // Timeout reached: object out of range (distance > maximum range).
-            return Ok(None);
+            None => Ok(None),
         }
+    }
+
+    /// Perform **distance measurement** (see [`HcSr04::measure`]), timestamping the result and
+    /// describing how it was obtained (see [`Measurement`]).
+    pub fn measurement(&mut self) -> Result<Measurement> {
+        let measurement = self.sample()?;
+        self.record(measurement);
+        Ok(measurement)
+    }
 
-        // Distance in m.
-        let distance = (self.sound_speed * instant.elapsed().as_secs_f32()) / 2.;
+    /// Perform a timestamped measurement, without recording it.
+    fn sample(&mut self) -> Result<Measurement> {
+        let echo = self.measure_echo()?;
+        let distance = match echo {
+            Some(echo) => self.echo_meters(echo)?.map(Distance::from_meters),
+            None => None,
+        };
+        let variance = distance.and(self.filter.variance());
+        let spread = variance.map_or(1., |variance| quality::spread_factor(variance.sqrt()));
 
-        Ok(Some(match unit {
-            Unit::Millimeters => distance * 1000.,
-            Unit::Centimeters => distance * 100.,
-            Unit::Decimeters => distance * 10.,
-            Unit::Meters => distance,
-        }))
+        Ok(Measurement {
+            distance,
+            variance,
+            timestamp: SystemTime::now(),
+            confidence: self.reliability() * spread,
+            echo_duration: echo,
+            temperature_used: self.temperature,
+            retries: 0,
+            filtered: !self.filter.is_empty(),
+        })
+    }
+
+    /// Perform **distance measurement** as [`HcSr04::measurement`] does, trying again up to
+    /// `retries` times while it fails or finds no distance, e.g. in noisy environments. The
+    /// number of failed attempts is reported in [`Measurement::retries`].
+    ///
+    /// Returns the outcome of the last attempt if none succeeds.
+    ///
+    /// ```rust,no_run
+    /// use hc_sr04::HcSr04;
+    ///
+    /// let mut ultrasonic = HcSr04::new(24, 23, None).unwrap();
+    ///
+    /// let measurement = ultrasonic.measurement_retrying(3).unwrap();
+    /// println!(
+    ///     "{:?} after {} retries, echo {:?} at {:?}C",
+    ///     measurement.distance,
+    ///     measurement.retries,
+    ///     measurement.echo_duration,
+    ///     measurement.temperature_used,
+    /// );
+    /// ```
+    pub fn measurement_retrying(&mut self, retries: u32) -> Result<Measurement> {
+        let mut attempt = 0;
+        loop {
+            let measurement = self.sample();
+            let found = matches!(
+                measurement,
+                Ok(Measurement {
+                    distance: Some(_),
+                    ..
+                })
+            );
+            if found || attempt == retries {
+                self.counters.record_retries(attempt);
+                let measurement = Measurement {
+                    retries: attempt,
+                    ..measurement?
+                };
+                self.record(measurement);
+                return Ok(measurement);
+            }
+            attempt += 1;
+        }
     }
 }
@@ -0,0 +1,183 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! Rate limiting of measurement requests.
+//!
+//! Many callers sharing a sensor through an [`HcSr04Handle`](crate::HcSr04Handle) can request
+//! measurements faster than the sensor can perform them. A [`RateLimiter`] set on the handle (see
+//! [`HcSr04Handle::rate_limited`](crate::HcSr04Handle::rate_limited)) admits requests as per a
+//! token bucket: one token is added every interval, up to the burst size, and every request takes
+//! one. Requests finding the bucket empty wait for the next token or are rejected with
+//! [`Error::RateLimited`], as per the [`Overflow`] policy.
+//!
+//! ```rust
+//! use hc_sr04::limiter::{Overflow, RateLimiter};
+//! use std::time::Duration;
+//!
+//! let limiter = RateLimiter::new(Duration::from_secs(1))
+//!     .burst(2)
+//!     .overflow(Overflow::Reject);
+//!
+//! assert!(limiter.acquire().is_ok());
+//! assert!(limiter.acquire().is_ok());
+//! assert!(limiter.acquire().is_err());
+//! ```
+
+use crate::{error::Error, Result};
+use std::{
+    sync::{Mutex, MutexGuard, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// What happens to requests finding the [`RateLimiter`] bucket empty (defaults to
+/// [`Overflow::Queue`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Wait for a token, in the order requests arrived.
+    #[default]
+    Queue,
+    /// Wait for a token as long as at most the given number of requests are already waiting,
+    /// rejecting the request otherwise.
+    QueueUpTo(usize),
+    /// Reject the request right away.
+    Reject,
+}
+
+/// Token bucket state of the [`RateLimiter`].
+///
+/// # Fields
+///
+/// - `full`: time the bucket is full again, if any token was taken
+/// - `waiting`: number of requests waiting for their token
+#[derive(Debug)]
+struct Bucket {
+    full: Option<Instant>,
+    waiting: usize,
+}
+
+/// Token bucket rate limiter for measurement requests, shareable between threads.
+///
+/// Tokens are handed out in the order requests arrive: waiting requests are spaced by the
+/// interval once the burst is spent.
+///
+/// # Fields
+///
+/// - `interval`: time it takes to add a token to the bucket
+/// - `burst`: capacity of the bucket, i.e. maximum number of requests admitted at once
+/// - `overflow`: policy for requests finding the bucket empty
+/// - `bucket`: current state of the bucket
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    burst: u32,
+    overflow: Overflow,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    /// Create a new limiter admitting one request every `interval`, with no burst, queuing
+    /// excess requests.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            burst: 1,
+            overflow: Overflow::default(),
+            bucket: Mutex::new(Bucket {
+                full: None,
+                waiting: 0,
+            }),
+        }
+    }
+
+    /// Admit up to `burst` requests at once (at least 1, the default), e.g. after a quiet period.
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    /// Policy for requests finding the bucket empty (defaults to [`Overflow::Queue`]).
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Time it takes to add a token to the bucket.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Number of requests currently waiting for their token.
+    pub fn waiting(&self) -> usize {
+        self.lock().waiting
+    }
+
+    /// Take a token, waiting for it as per the overflow policy.
+    ///
+    /// Fails with [`Error::RateLimited`] if the policy rejects the request.
+    pub fn acquire(&self) -> Result<()> {
+        let mut bucket = self.lock();
+        let (full, wait) = self.reserve(&bucket);
+
+        if !wait.is_zero() {
+            let admitted = match self.overflow {
+                Overflow::Queue => true,
+                Overflow::QueueUpTo(max) => bucket.waiting < max,
+                Overflow::Reject => false,
+            };
+            if !admitted {
+                return Err(Error::RateLimited);
+            }
+        }
+        bucket.full = Some(full);
+        if wait.is_zero() {
+            return Ok(());
+        }
+
+        bucket.waiting += 1;
+        drop(bucket);
+        thread::sleep(wait);
+        self.lock().waiting -= 1;
+        Ok(())
+    }
+
+    /// Take a token if one is available, without waiting, returning whether it was taken.
+    pub fn try_acquire(&self) -> bool {
+        let mut bucket = self.lock();
+        let (full, wait) = self.reserve(&bucket);
+        if !wait.is_zero() {
+            return false;
+        }
+        bucket.full = Some(full);
+        true
+    }
+
+    /// Time the bucket would be full again once the next token is taken, and the time left until
+    /// that token is available.
+    fn reserve(&self, bucket: &Bucket) -> (Instant, Duration) {
+        let now = Instant::now();
+        let full = bucket.full.map_or(now, |full| full.max(now));
+        // Taking a token when the bucket holds none means waiting for one to be added.
+        let wait = full
+            .saturating_duration_since(now)
+            .saturating_sub(self.interval * (self.burst - 1));
+        (full + self.interval, wait)
+    }
+
+    /// Lock the bucket, recovering it if a thread panicked while holding it.
+    fn lock(&self) -> MutexGuard<'_, Bucket> {
+        self.bucket.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,132 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Mock sensor for unit testing applications without GPIO hardware, available with the `mock`
+//! feature.
+//!
+//! [`MockHcSr04`] is an [`HcSr04`] driven by the [`Mock`] backend, which replays scripted
+//! readings instead of accessing GPIO pins: the whole sensor API is therefore available.
+//!
+//! ```rust
+//! use hc_sr04::{MockHcSr04, Unit};
+//!
+//! let mut ultrasonic = MockHcSr04::mock(None);
+//! ultrasonic
+//!     .push_distance(1.5, Unit::Meters)
+//!     .push_out_of_range()
+//!     .push_error("wire disconnected");
+//!
+//! let dist = ultrasonic.measure_distance(Unit::Centimeters).unwrap().unwrap();
+//! assert!((dist - 150.).abs() < 0.01);
+//! assert_eq!(ultrasonic.measure_distance(Unit::Meters).unwrap(), None);
+//! assert!(ultrasonic.measure_distance(Unit::Meters).is_err());
+//! ```
+
+use crate::{
+    backend::{Backend, Timing},
+    error::Error,
+    portable::from_secs,
+    Float, HcSr04, HcSr04Builder, Result, Unit,
+};
+use std::{collections::VecDeque, time::Duration};
+
+/// [`HcSr04`] sensor replaying scripted readings.
+pub type MockHcSr04 = HcSr04<Mock>;
+
+/// [`Backend`] replaying scripted **ECHO** pulses and errors, in the order they were pushed.
+///
+/// Once the script is exhausted, every measurement behaves as if no object is in range.
+#[derive(Debug, Default)]
+pub struct Mock {
+    script: VecDeque<Result<Option<Duration>>>,
+    echo_high: bool,
+}
+
+impl Backend for Mock {
+    fn ping(&mut self, timing: &Timing) -> Result<Option<Duration>> {
+        self.script
+            .pop_front()
+            .unwrap_or(Ok(None))
+            .map(|echo| echo.filter(|echo| *echo < timing.pulse_timeout))
+    }
+
+    fn echo_level(&mut self) -> Option<bool> {
+        Some(self.echo_high)
+    }
+}
+
+impl HcSr04<Mock> {
+    /// Initialize the mock sensor with the given ambient temperature `temp` used for calibration
+    /// (if `None` defaults to `20.0`) and an empty script.
+    pub fn mock(temp: Option<Float>) -> Self {
+        // Scripted measurements have no residual echoes to wait for.
+        let builder = HcSr04Builder::with_backend(Mock::default()).min_cycle(Duration::ZERO);
+        match temp {
+            Some(temp) => builder.temperature(temp),
+            None => builder,
+        }
+        .build()
+    }
+
+    /// Script a measurement of `distance` (expressed as `unit`).
+    ///
+    /// The distance is converted to an **ECHO** pulse using the current calibration: calibrating
+    /// the sensor afterwards changes the measured distance accordingly.
+    pub fn push_distance(&mut self, distance: Float, unit: Unit) -> &mut Self {
+        let echo = from_secs(distance / unit.per_meter() * 2. / self.sound_speed);
+        self.push_echo(echo)
+    }
+
+    /// Script a raw **ECHO** pulse width. Pulses longer than the **ECHO** timeout in use when
+    /// measuring are reported as out of range.
+    pub fn push_echo(&mut self, echo: Duration) -> &mut Self {
+        self.backend.script.push_back(Ok(Some(echo)));
+        self
+    }
+
+    /// Script a measurement with no object within maximum measuring range.
+    pub fn push_out_of_range(&mut self) -> &mut Self {
+        self.backend.script.push_back(Ok(None));
+        self
+    }
+
+    /// Script a measurement whose **ECHO** pulse never starts, failing with
+    /// [`Error::EchoStartTimeout`].
+    pub fn push_no_echo(&mut self) -> &mut Self {
+        self.backend.script.push_back(Err(Error::EchoStartTimeout));
+        self
+    }
+
+    /// Script a measurement failing with [`Error::Mock`] carrying `message`.
+    pub fn push_error(&mut self, message: impl Into<String>) -> &mut Self {
+        self.backend
+            .script
+            .push_back(Err(Error::Mock(message.into())));
+        self
+    }
+
+    /// Report the **ECHO** pin as stuck high between pings, or idling low again (the default),
+    /// e.g. to exercise [`HcSr04::self_test`].
+    pub fn set_echo_high(&mut self, high: bool) -> &mut Self {
+        self.backend.echo_high = high;
+        self
+    }
+
+    /// Number of scripted readings not yet measured.
+    pub fn pending(&self) -> usize {
+        self.backend.script.len()
+    }
+}
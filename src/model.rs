@@ -0,0 +1,77 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! Specifications of the supported ultrasonic sensor models.
+
+use crate::Float;
+use core::time::Duration;
+
+/// Timing and range specification of an ultrasonic sensor model.
+///
+/// # Fields
+///
+/// - `trigger_pulse`: width of the **TRIGGER** pulse
+/// - `min_cycle`: minimum time between consecutive **TRIGGER** pulses
+/// - `min_range`: minimum measuring range (dead zone) in m
+/// - `max_range`: maximum measuring range in m
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spec {
+    pub trigger_pulse: Duration,
+    pub min_cycle: Duration,
+    pub min_range: Float,
+    pub max_range: Float,
+}
+
+/// Ultrasonic sensor model (defaults to [`Model::HcSr04`]), setting the sensor's timing and
+/// range defaults (see [`Model::spec`]).
+///
+/// ```rust
+/// use hc_sr04::model::Model;
+///
+/// let spec = Model::JsnSr04t.spec();
+/// assert!(spec.max_range > Model::HcSr04.spec().max_range);
+/// assert!(spec.min_range > Model::HcSr04.spec().min_range);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Model {
+    /// HC-SR04/HC-SR04P: *2cm* to *4m*, *10µs* **TRIGGER** pulse and *60ms* cycle.
+    #[default]
+    HcSr04,
+    /// JSN-SR04T waterproof sensor: *25cm* to *6m*, *20µs* **TRIGGER** pulse (some revisions
+    /// ignore shorter ones) and *100ms* cycle, letting the ringing of the sealed transducer fade
+    /// out.
+    JsnSr04t,
+}
+
+impl Model {
+    /// Timing and range specification of the model.
+    pub const fn spec(self) -> Spec {
+        match self {
+            Self::HcSr04 => Spec {
+                trigger_pulse: Duration::from_micros(10),
+                min_cycle: Duration::from_millis(60),
+                min_range: 0.02,
+                max_range: 4.0,
+            },
+            Self::JsnSr04t => Spec {
+                trigger_pulse: Duration::from_micros(20),
+                min_cycle: Duration::from_millis(100),
+                min_range: 0.25,
+                max_range: 6.0,
+            },
+        }
+    }
+}
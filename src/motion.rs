@@ -0,0 +1,412 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Motion estimation from consecutive measurements.
+//!
+//! [`VelocityEstimator`] differentiates consecutive timestamped [`Measurement`]s (e.g. received
+//! from [`HcSr04::start_continuous`](crate::HcSr04::start_continuous) or a stream), smoothing
+//! the result, in order to estimate the speed at which the object approaches or retreats, along
+//! with the time left before it reaches the sensor.
+//!
+//! ```rust
+//! use hc_sr04::{motion::VelocityEstimator, Distance, Measurement};
+//! use std::time::{Duration, SystemTime};
+//!
+//! let start = SystemTime::now();
+//! let measurement = |meters, millis| {
+//!     Measurement::new(
+//!         Some(Distance::from_meters(meters)),
+//!         start + Duration::from_millis(millis),
+//!     )
+//! };
+//!
+//! let mut estimator = VelocityEstimator::new(1.);
+//! assert_eq!(estimator.update(&measurement(2., 0)), None);
+//! // Approaching at 1m/s.
+//! let velocity = estimator.update(&measurement(1.9, 100)).unwrap();
+//! assert!((velocity + 1.).abs() < 1e-3);
+//! // Reaching the sensor in 1.9s.
+//! let time_to_contact = estimator.time_to_contact().unwrap();
+//! assert!((time_to_contact.as_secs_f32() - 1.9).abs() < 1e-2);
+//! ```
+//!
+//! [`DirectionDetector`] classifies the smoothed velocity as a [`Direction`] of motion, given a
+//! speed threshold below which the object is considered stationary, e.g. for doorway and
+//! driveway sensing.
+//!
+//! ```rust
+//! use hc_sr04::{
+//!     motion::{Direction, DirectionDetector},
+//!     Distance, Measurement,
+//! };
+//! use std::time::{Duration, SystemTime};
+//!
+//! let start = SystemTime::now();
+//! let measurement = |meters, millis| {
+//!     Measurement::new(
+//!         Some(Distance::from_meters(meters)),
+//!         start + Duration::from_millis(millis),
+//!     )
+//! };
+//!
+//! let mut detector = DirectionDetector::new(0.5, 0.1);
+//! assert_eq!(detector.update(&measurement(2., 0)), None);
+//! assert_eq!(detector.update(&measurement(2., 100)), Some(Direction::Stationary));
+//! assert_eq!(detector.update(&measurement(1.8, 200)), Some(Direction::Approaching));
+//! ```
+//!
+//! [`JumpDetector`] raises a [`Jump`] when the distance changes faster than a speed threshold
+//! between consecutive measurements, e.g. a door opening or a pallet dropped in front of the
+//! sensor. The new distance must be confirmed by further measurements, so that single glitchy
+//! readings don't raise false alerts.
+//!
+//! ```rust
+//! use hc_sr04::{motion::JumpDetector, Distance, Measurement};
+//! use std::time::{Duration, SystemTime};
+//!
+//! let start = SystemTime::now();
+//! let measurement = |meters, millis| {
+//!     Measurement::new(
+//!         Some(Distance::from_meters(meters)),
+//!         start + Duration::from_millis(millis),
+//!     )
+//! };
+//!
+//! // Alert on changes faster than 2m/s, confirmed by one more measurement.
+//! let mut detector = JumpDetector::new(2., 1);
+//! assert_eq!(detector.update(&measurement(2., 0)), None);
+//! // Glitch: back to 2m right away.
+//! assert_eq!(detector.update(&measurement(0.5, 100)), None);
+//! assert_eq!(detector.update(&measurement(2., 200)), None);
+//! assert_eq!(detector.glitches(), 1);
+//! // Genuine jump, confirmed by the next measurement.
+//! assert_eq!(detector.update(&measurement(1., 300)), None);
+//! let jump = detector.update(&measurement(1., 400)).unwrap();
+//! assert_eq!(jump.from, Distance::from_meters(2.));
+//! assert_eq!(jump.to, Distance::from_meters(1.));
+//! assert!((jump.rate + 10.).abs() < 1e-3);
+//! ```
+
+use crate::{
+    portable::{abs, from_secs, secs},
+    Distance, Float, Measurement,
+};
+use std::time::{Duration, SystemTime};
+
+/// [uom](https://docs.rs/uom) velocity quantity of the crate's [`Float`] precision, available
+/// with the `uom` feature.
+#[cfg(all(feature = "uom", not(feature = "f64")))]
+pub type Velocity = uom::si::f32::Velocity;
+/// [uom](https://docs.rs/uom) velocity quantity of the crate's [`Float`] precision, available
+/// with the `uom` feature.
+#[cfg(all(feature = "uom", feature = "f64"))]
+pub type Velocity = uom::si::f64::Velocity;
+
+/// Velocity estimator, smoothing the velocity between consecutive measurements through an
+/// exponential moving average.
+///
+/// Velocity is expressed in m/s: positive when the object retreats (distance increases), negative
+/// when it approaches.
+///
+/// # Fields
+///
+/// - `alpha`: weight of the latest velocity sample, in `(0, 1]`
+/// - `last`: latest measured distance and its timestamp
+/// - `velocity`: current velocity estimate in m/s (`None` until two consecutive measurements)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VelocityEstimator {
+    alpha: Float,
+    last: Option<(Distance, SystemTime)>,
+    velocity: Option<Float>,
+}
+
+impl VelocityEstimator {
+    /// Create a new estimator weighting the latest velocity sample by `alpha` (clamped to
+    /// `(0, 1]`): the lower `alpha`, the smoother (and slower to react) the estimate.
+    pub fn new(alpha: Float) -> Self {
+        Self {
+            alpha: alpha.clamp(Float::EPSILON, 1.),
+            last: None,
+            velocity: None,
+        }
+    }
+
+    /// Update the estimate with `measurement`, returning the current velocity in m/s.
+    ///
+    /// Returns `None` if no previous measurement is available to differentiate against: after the
+    /// first measurement, and after measurements with no object in range, which break the
+    /// sequence of consecutive distances.
+    pub fn update(&mut self, measurement: &Measurement) -> Option<Float> {
+        let distance = match measurement.distance {
+            Some(distance) => distance,
+            None => {
+                self.last = None;
+                return None;
+            }
+        };
+
+        let current = (distance, measurement.timestamp);
+        let sample = rate(self.last.replace(current)?, current)?;
+
+        let velocity = match self.velocity {
+            None => sample,
+            Some(velocity) => velocity + self.alpha * (sample - velocity),
+        };
+
+        self.velocity = Some(velocity);
+        Some(velocity)
+    }
+
+    /// Current velocity estimate in m/s, `None` if not enough measurements have been performed.
+    pub fn velocity(&self) -> Option<Float> {
+        self.velocity
+    }
+
+    /// Current velocity estimate as a [uom](https://docs.rs/uom) quantity (see
+    /// [`VelocityEstimator::velocity`]), available with the `uom` feature.
+    #[cfg(feature = "uom")]
+    pub fn velocity_quantity(&self) -> Option<Velocity> {
+        self.velocity
+            .map(Velocity::new::<uom::si::velocity::meter_per_second>)
+    }
+
+    /// Estimated time until the object reaches the sensor, given the latest distance and the
+    /// current velocity, e.g. for obstacle avoidance to brake pre-emptively.
+    ///
+    /// Returns `None` if the object is stationary (approaching slower than *1mm/s*), receding, or
+    /// not enough measurements have been performed.
+    pub fn time_to_contact(&self) -> Option<Duration> {
+        /// Approach speed in m/s below which the object is considered stationary.
+        const STATIONARY_SPEED: Float = 1e-3;
+
+        let (distance, _) = self.last?;
+        let approach_speed = -self.velocity?;
+        if approach_speed < STATIONARY_SPEED {
+            return None;
+        }
+
+        let secs = distance.as_meters() / approach_speed;
+        // Guard against overflow for far objects approaching slowly.
+        (secs < u64::MAX as Float).then(|| from_secs(secs))
+    }
+
+    /// Direction of motion given the current velocity estimate, the object being stationary if
+    /// moving slower than `threshold` m/s.
+    ///
+    /// Returns `None` if not enough measurements have been performed.
+    pub fn direction(&self, threshold: Float) -> Option<Direction> {
+        let velocity = self.velocity?;
+        Some(if velocity <= -threshold {
+            Direction::Approaching
+        } else if velocity >= threshold {
+            Direction::Receding
+        } else {
+            Direction::Stationary
+        })
+    }
+
+    /// Discard the estimator state, e.g. after the sensor has been moved.
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.velocity = None;
+    }
+}
+
+/// Direction of motion of the object relative to the sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    /// The object gets closer to the sensor.
+    Approaching,
+    /// The object gets farther from the sensor.
+    Receding,
+    /// The object moves slower than the speed threshold.
+    Stationary,
+}
+
+/// Direction of motion detector, classifying the smoothed velocity (see [`VelocityEstimator`])
+/// against a speed threshold.
+///
+/// # Fields
+///
+/// - `estimator`: velocity estimator
+/// - `threshold`: speed in m/s below which the object is considered stationary
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirectionDetector {
+    estimator: VelocityEstimator,
+    threshold: Float,
+}
+
+impl DirectionDetector {
+    /// Create a new detector weighting the latest velocity sample by `alpha` (see
+    /// [`VelocityEstimator::new`]), considering objects moving slower than `threshold` m/s
+    /// stationary.
+    pub fn new(alpha: Float, threshold: Float) -> Self {
+        Self {
+            estimator: VelocityEstimator::new(alpha),
+            threshold: abs(threshold),
+        }
+    }
+
+    /// Update the detector with `measurement`, returning the current direction of motion.
+    ///
+    /// Returns `None` if not enough consecutive measurements have been performed (see
+    /// [`VelocityEstimator::update`]).
+    pub fn update(&mut self, measurement: &Measurement) -> Option<Direction> {
+        self.estimator.update(measurement)?;
+        self.direction()
+    }
+
+    /// Current direction of motion, `None` if not enough measurements have been performed.
+    pub fn direction(&self) -> Option<Direction> {
+        self.estimator.direction(self.threshold)
+    }
+
+    /// Underlying velocity estimator.
+    pub fn estimator(&self) -> &VelocityEstimator {
+        &self.estimator
+    }
+
+    /// Discard the detector state, e.g. after the sensor has been moved.
+    pub fn reset(&mut self) {
+        self.estimator.reset();
+    }
+}
+
+/// Sudden distance change reported by [`JumpDetector`].
+///
+/// # Fields
+///
+/// - `from`: distance before the change
+/// - `to`: distance after the change, as of the last confirming measurement
+/// - `rate`: rate of change in m/s at the jump (negative when the distance decreased)
+/// - `timestamp`: time of the measurement the change was first seen at
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Jump {
+    pub from: Distance,
+    pub to: Distance,
+    pub rate: Float,
+    pub timestamp: SystemTime,
+}
+
+/// Rate of change detector, reporting distance changes faster than a speed threshold between
+/// consecutive measurements once confirmed by further measurements agreeing with the new
+/// distance.
+///
+/// Unconfirmed changes, where the distance moves back or elsewhere before enough confirming
+/// measurements, are discarded as glitches. Measurements with no object in range are ignored.
+///
+/// # Fields
+///
+/// - `threshold`: rate of change in m/s above which the distance is considered to jump
+/// - `confirm`: number of measurements required to confirm a jump, after the one it was first
+///   seen at
+/// - `reference`: latest settled distance and its timestamp
+/// - `pending`: jump waiting for confirmation, with the latest confirming distance and the number
+///   of confirmations left
+/// - `glitches`: number of discarded unconfirmed jumps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JumpDetector {
+    threshold: Float,
+    confirm: u32,
+    reference: Option<(Distance, SystemTime)>,
+    pending: Option<(Jump, SystemTime, u32)>,
+    glitches: u64,
+}
+
+impl JumpDetector {
+    /// Create a new detector reporting distance changes faster than `threshold` m/s, confirmed
+    /// by `confirm` further measurements (none to report them right away).
+    pub fn new(threshold: Float, confirm: u32) -> Self {
+        Self {
+            threshold: abs(threshold),
+            confirm,
+            reference: None,
+            pending: None,
+            glitches: 0,
+        }
+    }
+
+    /// Update the detector with `measurement`, returning the jump it confirms, if any.
+    pub fn update(&mut self, measurement: &Measurement) -> Option<Jump> {
+        let sample = (measurement.distance?, measurement.timestamp);
+        let reference = match self.reference {
+            Some(reference) => reference,
+            None => {
+                self.reference = Some(sample);
+                return None;
+            }
+        };
+
+        if let Some((mut jump, last, left)) = self.pending {
+            if abs(rate((jump.to, last), sample)?) <= self.threshold {
+                jump.to = sample.0;
+                if left <= 1 {
+                    self.pending = None;
+                    self.reference = Some(sample);
+                    return Some(jump);
+                }
+                self.pending = Some((jump, sample.1, left - 1));
+                return None;
+            }
+            // The new distance didn't hold: start over from the settled one.
+            self.pending = None;
+            self.glitches += 1;
+        }
+
+        let rate = rate(reference, sample)?;
+        if abs(rate) <= self.threshold {
+            self.reference = Some(sample);
+            return None;
+        }
+
+        let jump = Jump {
+            from: reference.0,
+            to: sample.0,
+            rate,
+            timestamp: sample.1,
+        };
+        if self.confirm == 0 {
+            self.reference = Some(sample);
+            return Some(jump);
+        }
+        self.pending = Some((jump, sample.1, self.confirm));
+        None
+    }
+
+    /// Number of changes discarded as glitches, as they weren't confirmed.
+    pub fn glitches(&self) -> u64 {
+        self.glitches
+    }
+
+    /// Discard the detector state, e.g. after the sensor has been moved. The glitch count is
+    /// kept.
+    pub fn reset(&mut self) {
+        self.reference = None;
+        self.pending = None;
+    }
+}
+
+/// Rate of change in m/s from the `from` to the `to` timestamped distance, `None` unless `to`
+/// was measured after `from`.
+fn rate(from: (Distance, SystemTime), to: (Distance, SystemTime)) -> Option<Float> {
+    let elapsed =
+        to.1.duration_since(from.1)
+            .ok()
+            .filter(|elapsed| !elapsed.is_zero())?;
+    Some((to.0 - from.0).as_meters() / secs(elapsed))
+}
@@ -0,0 +1,228 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Noise characterization of a sensor unit and its mounting.
+//!
+//! [`HcSr04::characterize_noise`] performs a batch of measurements against a static target and
+//! reports, in a [`NoiseReport`], how much readings scatter around the target distance, how
+//! often they are outliers or missing altogether, and filter settings suiting that noise (see
+//! [`Recommendation`]).
+//!
+//! ```rust,no_run
+//! use hc_sr04::HcSr04;
+//!
+//! let mut ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//!
+//! // Point the sensor at a wall and keep the scene still.
+//! let report = ultrasonic.characterize_noise(200).unwrap();
+//! println!(
+//!     "σ = {:?}m, {:.1}% outliers, {:.1}% dropouts",
+//!     report.std_dev,
+//!     report.outlier_rate * 100.,
+//!     report.dropout_rate * 100.,
+//! );
+//!
+//! if let Some(recommended) = report.recommended {
+//!     ultrasonic.set_filter(recommended.pipeline(1e-4));
+//! }
+//! ```
+
+use crate::{
+    backend::Backend,
+    error::Error,
+    filter::{Kalman, Median, OutlierReject, Pipeline},
+    Float, HcSr04, Result,
+};
+
+/// Readings farther than this many (robust) standard deviations from the median are outliers.
+const OUTLIER_SIGMAS: Float = 3.;
+
+/// Minimum outlier threshold in m, as the sensor resolution makes the spread of very stable
+/// readings meaningless.
+const MIN_OUTLIER_DEVIATION: Float = 0.005;
+
+/// Minimum measurement noise standard deviation in m recommended for the [`Kalman`] filter.
+const MIN_NOISE: Float = 0.001;
+
+/// Widest median window recommended, limiting the added latency.
+const MAX_MEDIAN_WINDOW: usize = 9;
+
+/// Scale factor turning the median absolute deviation into a standard deviation estimate, for
+/// normally distributed readings.
+const MAD_SCALE: Float = 1.4826;
+
+/// Noise characteristics of the sensor, measured by [`HcSr04::characterize_noise`].
+///
+/// Distances are expressed in m, unfiltered but corrected (see
+/// [`HcSr04::calibrate_reference`]).
+///
+/// # Fields
+///
+/// - `samples`: number of measurements performed
+/// - `valid`: number of measurements detecting the target
+/// - `mean`: mean distance, outliers excluded (`None` if no measurement detected the target)
+/// - `median`: median distance (`None` as `mean`)
+/// - `std_dev`: population standard deviation of the distances, outliers excluded (`None` as
+///   `mean`)
+/// - `outlier_rate`: fraction of the valid measurements deviating more than three (robust)
+///   standard deviations from the median, and at least *5mm*
+/// - `dropout_rate`: fraction of the measurements which didn't detect the target
+/// - `recommended`: filter settings suiting the measured noise (`None` if fewer than two
+///   measurements detected the target)
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NoiseReport {
+    pub samples: usize,
+    pub valid: usize,
+    pub mean: Option<Float>,
+    pub median: Option<Float>,
+    pub std_dev: Option<Float>,
+    pub outlier_rate: Float,
+    pub dropout_rate: Float,
+    pub recommended: Option<Recommendation>,
+}
+
+/// Filter settings recommended by [`HcSr04::characterize_noise`], to be chained in the order of
+/// the fields (see [`Recommendation::pipeline`]).
+///
+/// # Fields
+///
+/// - `outlier_reject`: [`OutlierReject`] maximum deviation in m and maximum consecutive
+///   rejections, covering the longest run of outliers measured (`None` if there were none)
+/// - `median`: [`Median`] window size, wide enough to outvote the longest run of outliers
+///   measured, up to 9 readings (`None` if there were none)
+/// - `measurement_noise`: [`Kalman`] measurement noise variance in m²
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Recommendation {
+    pub outlier_reject: Option<(Float, u32)>,
+    pub median: Option<usize>,
+    pub measurement_noise: Float,
+}
+
+impl Recommendation {
+    /// Build the recommended filters, with a [`Kalman`] filter expecting the actual distance to
+    /// change by `process_noise` (variance in m²) between measurements, which depends on the
+    /// application rather than on the sensor.
+    pub fn pipeline(&self, process_noise: Float) -> Pipeline {
+        let mut pipeline = Pipeline::new();
+        if let Some((max_deviation, max_rejections)) = self.outlier_reject {
+            pipeline.push(OutlierReject::new(max_deviation, max_rejections));
+        }
+        if let Some(size) = self.median {
+            pipeline.push(Median::new(size));
+        }
+
+        pipeline.then(Kalman::new(process_noise, self.measurement_noise))
+    }
+}
+
+impl<B: Backend> HcSr04<B> {
+    /// Perform `samples` measurements against a static target and characterize their noise.
+    ///
+    /// Readings are taken unfiltered, spaced by the minimum measurement cycle. Measurements
+    /// finding no object, or whose **ECHO** pulse doesn't start or end in time, count as dropouts.
+    /// Fails on the first measurement returning any other error.
+    pub fn characterize_noise(&mut self, samples: usize) -> Result<NoiseReport> {
+        let mut readings = Vec::with_capacity(samples);
+        for _ in 0..samples {
+            match self.measure_echo() {
+                Ok(Some(echo)) => readings.push(self.corrected_meters(echo)),
+                Ok(None) | Err(Error::EchoStartTimeout) | Err(Error::EchoPulseTimeout) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(NoiseReport::from_readings(samples, &readings))
+    }
+}
+
+impl NoiseReport {
+    /// Characterize `readings` in m, out of `samples` measurements.
+    fn from_readings(samples: usize, readings: &[Float]) -> Self {
+        let dropout_rate = match samples {
+            0 => 0.,
+            samples => 1. - readings.len() as Float / samples as Float,
+        };
+        let mut report = Self {
+            samples,
+            valid: readings.len(),
+            mean: None,
+            median: None,
+            std_dev: None,
+            outlier_rate: 0.,
+            dropout_rate,
+            recommended: None,
+        };
+        if readings.is_empty() {
+            return report;
+        }
+
+        let median = median_of(readings.to_vec());
+        let mad = median_of(
+            readings
+                .iter()
+                .map(|reading| (reading - median).abs())
+                .collect(),
+        );
+        let threshold = (OUTLIER_SIGMAS * MAD_SCALE * mad).max(MIN_OUTLIER_DEVIATION);
+        let outlier = |reading: &Float| (reading - median).abs() > threshold;
+
+        let inliers: Vec<Float> = readings.iter().copied().filter(|r| !outlier(r)).collect();
+        let mean = inliers.iter().sum::<Float>() / inliers.len() as Float;
+        let variance = inliers
+            .iter()
+            .map(|reading| (reading - mean) * (reading - mean))
+            .sum::<Float>()
+            / inliers.len() as Float;
+        let outliers = readings.len() - inliers.len();
+        let outlier_rate = outliers as Float / readings.len() as Float;
+
+        report.mean = Some(mean);
+        report.median = Some(median);
+        report.std_dev = Some(variance.sqrt());
+        report.outlier_rate = outlier_rate;
+        if readings.len() < 2 {
+            return report;
+        }
+
+        // Longest run of consecutive outliers.
+        let run = readings
+            .iter()
+            .scan(0_u32, |run, reading| {
+                *run = if outlier(reading) { *run + 1 } else { 0 };
+                Some(*run)
+            })
+            .max()
+            .unwrap_or(0);
+        report.recommended = Some(Recommendation {
+            outlier_reject: (outliers > 0).then_some((threshold, run)),
+            median: (outliers > 0).then(|| (2 * run as usize + 1).clamp(3, MAX_MEDIAN_WINDOW)),
+            measurement_noise: variance.max(MIN_NOISE * MIN_NOISE),
+        });
+        report
+    }
+}
+
+/// Median of `values`, not empty.
+fn median_of(mut values: Vec<Float>) -> Float {
+    values.sort_by(Float::total_cmp);
+    let mid = values.len() / 2;
+    match values.len() % 2 {
+        0 => (values[mid - 1] + values[mid]) / 2.,
+        _ => values[mid],
+    }
+}
@@ -0,0 +1,176 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! Parking assistance.
+//!
+//! [`ParkingAssist`] grades the distance of a vehicle approaching a garage wall into
+//! [`Proximity`] levels: clear, increasingly urgent warning bands and stop. With the `rppal`
+//! feature, the level can drive an output pin (e.g. a buzzer or a LED) with a blinking pattern
+//! getting faster as the vehicle gets closer, and solid on once it must stop.
+//!
+//! ```rust
+//! use hc_sr04::{
+//!     parking::{ParkingAssist, Proximity},
+//!     Distance, Unit,
+//! };
+//!
+//! let assist = ParkingAssist::new(Distance::new(30., Unit::Centimeters))
+//!     .band(Distance::new(1., Unit::Meters))
+//!     .band(Distance::new(60., Unit::Centimeters));
+//!
+//! let reading = |meters| Some(Distance::from_meters(meters));
+//! assert_eq!(assist.proximity(None), Proximity::Clear);
+//! assert_eq!(assist.proximity(reading(1.5)), Proximity::Clear);
+//! assert_eq!(assist.proximity(reading(0.8)), Proximity::Warning(1));
+//! assert_eq!(assist.proximity(reading(0.5)), Proximity::Warning(2));
+//! assert_eq!(assist.proximity(reading(0.2)), Proximity::Stop);
+//! ```
+
+use crate::{backend::Backend, Distance, HcSr04, Result};
+#[cfg(feature = "rppal")]
+use rppal::gpio::OutputPin;
+#[cfg(feature = "rppal")]
+use std::time::{Duration, Instant};
+
+/// Half period of the indicator blinking pattern for the farthest warning band, divided by the
+/// warning level for the closer ones.
+#[cfg(feature = "rppal")]
+const BLINK_HALF_PERIOD: Duration = Duration::from_millis(400);
+
+/// Graded proximity of the vehicle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Proximity {
+    /// No vehicle within the warning bands.
+    #[default]
+    Clear,
+    /// Vehicle within a warning band, with the contained level: `1` for the farthest band,
+    /// increasing as the vehicle gets closer.
+    Warning(u8),
+    /// Vehicle closer than the stop distance.
+    Stop,
+}
+
+/// Output pin driven as per the proximity level.
+///
+/// # Fields
+///
+/// - `pin`: output GPIO pin
+/// - `since`: start of the blinking pattern
+#[cfg(feature = "rppal")]
+#[derive(Debug)]
+struct Indicator {
+    pin: OutputPin,
+    since: Instant,
+}
+
+/// Parking assistant, grading distances into [`Proximity`] levels.
+///
+/// # Fields
+///
+/// - `stop`: distance below which the vehicle must stop
+/// - `bands`: limit distances of the warning bands, ascending
+/// - `indicator`: output pin driven as per the proximity level, if any
+#[derive(Debug)]
+pub struct ParkingAssist {
+    stop: Distance,
+    bands: Vec<Distance>,
+    #[cfg(feature = "rppal")]
+    indicator: Option<Indicator>,
+}
+
+impl ParkingAssist {
+    /// Create a new parking assistant reporting vehicles closer than `stop` as
+    /// [`Proximity::Stop`], with no warning bands.
+    pub fn new(stop: Distance) -> Self {
+        Self {
+            stop,
+            bands: Vec::new(),
+            #[cfg(feature = "rppal")]
+            indicator: None,
+        }
+    }
+
+    /// Add a warning band extending from the next closer band (or the stop distance) up to
+    /// `limit`. Bands can be added in any order, and are graded by distance.
+    pub fn band(mut self, limit: Distance) -> Self {
+        let index = self.bands.partition_point(|band| *band < limit);
+        self.bands.insert(index, limit);
+        self
+    }
+
+    /// Drive `pin` as per the proximity level on every update: off while clear, blinking within
+    /// the warning bands, faster as the vehicle gets closer (*400ms* half period for the
+    /// farthest band, divided by the warning level), and solid on once the vehicle must stop.
+    ///
+    /// The blinking pattern is sampled on updates: polling the sensor faster than the pattern
+    /// makes it smoother.
+    #[cfg(feature = "rppal")]
+    pub fn indicator(mut self, pin: OutputPin) -> Self {
+        self.indicator = Some(Indicator {
+            pin,
+            since: Instant::now(),
+        });
+        self
+    }
+
+    /// Proximity level of the vehicle at the given `distance` (`None` if no object is in range).
+    pub fn proximity(&self, distance: Option<Distance>) -> Proximity {
+        let distance = match distance {
+            Some(distance) => distance,
+            None => return Proximity::Clear,
+        };
+        if distance < self.stop {
+            return Proximity::Stop;
+        }
+
+        let bands = self.bands.len();
+        match self.bands.iter().position(|limit| distance < *limit) {
+            // Bands beyond `u8::MAX` share the farthest warning level.
+            Some(index) => Proximity::Warning((bands - index).min(u8::MAX as usize) as u8),
+            None => Proximity::Clear,
+        }
+    }
+
+    /// Update the assistant with the measured `distance` (`None` if no object is in range),
+    /// driving the indicator pin if any, and returning the proximity level.
+    pub fn update(&mut self, distance: Option<Distance>) -> Proximity {
+        let proximity = self.proximity(distance);
+        #[cfg(feature = "rppal")]
+        if let Some(indicator) = &mut self.indicator {
+            let on = match proximity {
+                Proximity::Clear => false,
+                Proximity::Warning(level) => {
+                    let half_period = BLINK_HALF_PERIOD / level.max(1) as u32;
+                    let phase = indicator.since.elapsed().as_nanos() / half_period.as_nanos();
+                    phase % 2 == 0
+                }
+                Proximity::Stop => true,
+            };
+            if on {
+                indicator.pin.set_high();
+            } else {
+                indicator.pin.set_low();
+            }
+        }
+        proximity
+    }
+
+    /// Measure distance through `sensor` and update the assistant (see
+    /// [`ParkingAssist::update`]).
+    pub fn poll<B: Backend>(&mut self, sensor: &mut HcSr04<B>) -> Result<Proximity> {
+        Ok(self.update(sensor.measure()?))
+    }
+}
@@ -0,0 +1,213 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Platform-independent core of the driver, available without the `std` feature.
+//!
+//! This module gathers the math and the state machine the driver is built upon, depending on
+//! [`core`] only so that they can be reused on microcontrollers, along with the [`Unit`],
+//! [`Distance`](crate::Distance) and filters (except those requiring allocation) types:
+//!
+//! - speed of sound given the ambient temperature ([`sound_speed`])
+//! - **ECHO** timeout given the maximum measuring range ([`echo_timeout`])
+//! - conversion of **ECHO** pulses to distance ([`echo_distance`])
+//! - **ECHO** pulse capture, timing out as per [`Timing`] ([`EchoCapture`])
+//!
+//! The platform layer (see the `backend` module) only deals with GPIO access on top of it.
+//!
+//! All of the math, as well as the public API of the crate, is carried out in [`Float`]
+//! precision: `f32` by default, or `f64` with the `f64` feature, for long-term averaging and
+//! regression where accumulated `f32` rounding is measurable. Enabling the feature affects every
+//! crate depending on the driver in the same build.
+//!
+//! ```rust
+//! use hc_sr04::portable::{self, Echo, EchoCapture, Timing};
+//! use std::time::Duration;
+//!
+//! let sound_speed = portable::sound_speed(20.);
+//! let timing = Timing {
+//!     trigger_pulse: Duration::from_micros(10),
+//!     start_timeout: Some(Duration::from_millis(10)),
+//!     pulse_timeout: portable::echo_timeout(4., sound_speed),
+//! };
+//!
+//! // Sample the ECHO pin level after the TRIGGER pulse, along with a monotonic clock.
+//! let mut capture = EchoCapture::new(&timing, Duration::ZERO);
+//! assert_eq!(capture.poll(Duration::from_micros(100), false), None);
+//! assert_eq!(capture.poll(Duration::from_micros(200), true), None);
+//! let echo = capture.poll(Duration::from_micros(6024), false);
+//! assert_eq!(echo, Some(Echo::Pulse(Duration::from_micros(5824))));
+//!
+//! let distance = portable::echo_distance(Duration::from_micros(5824), sound_speed);
+//! assert!((distance - 1.).abs() < 1e-3);
+//! ```
+
+use crate::Unit;
+use core::time::Duration;
+
+/// Floating point type of the crate's math and public API (`f32`, unless the `f64` feature is
+/// enabled).
+#[cfg(not(feature = "f64"))]
+pub type Float = f32;
+/// Floating point type of the crate's math and public API (`f64`, as the `f64` feature is
+/// enabled).
+#[cfg(feature = "f64")]
+pub type Float = f64;
+
+/// Timing parameters of a single measurement.
+///
+/// # Fields
+///
+/// - `trigger_pulse`: width of the **TRIGGER** pulse
+/// - `start_timeout`: maximum time to wait for the **ECHO** pulse to start, after the
+///   **TRIGGER** pulse (`None` to wait indefinitely)
+/// - `pulse_timeout`: maximum time to wait for the **ECHO** pulse to end, after it started
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timing {
+    pub trigger_pulse: Duration,
+    pub start_timeout: Option<Duration>,
+    pub pulse_timeout: Duration,
+}
+
+/// Speed of sound in m/s given the ambient temperature `temp` in *Celsius degrees*, using the
+/// linear approximation for dry air.
+pub fn sound_speed(temp: Float) -> Float {
+    /// Speed of sound in dry air at 0C in m/s.
+    const SOUND_SPEED_0C: Float = 331.3;
+    /// Increase speed of sound over temperature factor m/[sC].
+    const SOUND_SPEED_INC_OVER_TEMP: Float = 0.606;
+
+    SOUND_SPEED_0C + (SOUND_SPEED_INC_OVER_TEMP * temp)
+}
+
+/// Compute the **ECHO** pin polling timeout given the **maximum measuring range** (in m) and the
+/// **speed of sound** (in m/s).
+pub fn echo_timeout(max_range: Float, sound_speed: Float) -> Duration {
+    // Polling timeout for **ECHO** pin: it doesn't make sense to wait longer than the time
+    // required to the ultrasonic sound wave to cover the max range distance. In other words, if
+    // the timeout is reached, the measurement was not successfull or the object is located too
+    // far away from the sensor in order to be detected.
+    from_secs(max_range / sound_speed * 2.)
+}
+
+/// Distance in m travelled back and forth by the ultrasonic wave whose **ECHO** pulse lasted
+/// `echo`, given the **speed of sound** (in m/s).
+pub fn echo_distance(echo: Duration, sound_speed: Float) -> Float {
+    (sound_speed * secs(echo)) / 2.
+}
+
+/// Convert `distance` in m to `unit`.
+pub fn to_unit(distance: Float, unit: Unit) -> Float {
+    distance * unit.per_meter()
+}
+
+/// Duration of `secs` seconds, in [`Float`] precision.
+#[cfg(not(feature = "f64"))]
+pub(crate) fn from_secs(secs: Float) -> Duration {
+    Duration::from_secs_f32(secs)
+}
+
+/// Duration of `secs` seconds, in [`Float`] precision.
+#[cfg(feature = "f64")]
+pub(crate) fn from_secs(secs: Float) -> Duration {
+    Duration::from_secs_f64(secs)
+}
+
+/// Seconds elapsed in `duration`, in [`Float`] precision.
+#[cfg(not(feature = "f64"))]
+pub(crate) fn secs(duration: Duration) -> Float {
+    duration.as_secs_f32()
+}
+
+/// Seconds elapsed in `duration`, in [`Float`] precision.
+#[cfg(feature = "f64")]
+pub(crate) fn secs(duration: Duration) -> Float {
+    duration.as_secs_f64()
+}
+
+/// Absolute value of `value`, as `abs` is not available on floats without `std` on older
+/// toolchains.
+pub(crate) fn abs(value: Float) -> Float {
+    if value < 0. {
+        -value
+    } else {
+        value
+    }
+}
+
+/// Outcome of an **ECHO** pulse capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Echo {
+    /// The **ECHO** pulse lasted the contained time.
+    Pulse(Duration),
+    /// The **ECHO** pulse didn't end in time: no object is within maximum measuring range.
+    OutOfRange,
+    /// The **ECHO** pulse didn't start in time.
+    NoStart,
+}
+
+/// Phase of an **ECHO** pulse capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// Waiting for the pulse to start, since the contained time.
+    Start(Duration),
+    /// Waiting for the pulse started at the contained time to end.
+    Pulse(Duration),
+}
+
+/// State machine capturing the **ECHO** pulse following a **TRIGGER** pulse, from **ECHO** pin
+/// level samples timestamped by a monotonic clock.
+///
+/// # Fields
+///
+/// - `timing`: timeouts of the capture
+/// - `phase`: current phase of the capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EchoCapture {
+    timing: Timing,
+    phase: Phase,
+}
+
+impl EchoCapture {
+    /// Start capturing at time `now`, right after the **TRIGGER** pulse ended.
+    pub fn new(timing: &Timing, now: Duration) -> Self {
+        Self {
+            timing: *timing,
+            phase: Phase::Start(now),
+        }
+    }
+
+    /// Feed the **ECHO** pin level (`high`) sampled at time `now`, returning the outcome of the
+    /// capture once complete.
+    pub fn poll(&mut self, now: Duration, high: bool) -> Option<Echo> {
+        match self.phase {
+            Phase::Start(_) if high => {
+                self.phase = Phase::Pulse(now);
+                None
+            }
+            Phase::Start(since) => match self.timing.start_timeout {
+                Some(timeout) if now.saturating_sub(since) >= timeout => Some(Echo::NoStart),
+                _ => None,
+            },
+            Phase::Pulse(start) if !high => Some(Echo::Pulse(now.saturating_sub(start))),
+            Phase::Pulse(start) if now.saturating_sub(start) >= self.timing.pulse_timeout => {
+                // Timeout reached: object out of range (distance > maximum range).
+                Some(Echo::OutOfRange)
+            }
+            Phase::Pulse(_) => None,
+        }
+    }
+}
@@ -0,0 +1,141 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Presence detection.
+//!
+//! [`PresenceDetector`] turns distance readings into [`Presence`] transitions: an object is
+//! present when closer than a threshold distance, and absent again only once farther than the
+//! threshold plus a hysteresis band, so that objects lingering around the threshold don't make the
+//! state flicker. Transitions can additionally be debounced, requiring several consecutive
+//! readings to agree.
+//!
+//! ```rust
+//! use hc_sr04::{
+//!     presence::{Presence, PresenceDetector},
+//!     Distance, Unit,
+//! };
+//!
+//! let mut detector = PresenceDetector::new(
+//!     Distance::new(1.2, Unit::Meters),
+//!     Distance::new(10., Unit::Centimeters),
+//! )
+//! .debounce(2);
+//!
+//! let reading = |meters| Some(Distance::from_meters(meters));
+//! assert_eq!(detector.update(reading(1.)), None);
+//! assert_eq!(detector.update(reading(1.)), Some(Presence::Present));
+//! // Within the hysteresis band: still present.
+//! assert_eq!(detector.update(reading(1.25)), None);
+//! assert_eq!(detector.update(reading(1.25)), None);
+//! // No object in range.
+//! assert_eq!(detector.update(None), None);
+//! assert_eq!(detector.update(None), Some(Presence::Absent));
+//! ```
+
+use crate::{backend::Backend, Distance, HcSr04, Result};
+
+/// Presence of an object in front of the sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Presence {
+    /// An object is closer than the threshold distance.
+    Present,
+    /// No object is closer than the threshold distance (plus hysteresis).
+    #[default]
+    Absent,
+}
+
+/// Presence detector with hysteresis and debouncing.
+///
+/// # Fields
+///
+/// - `threshold`: distance below which an object is present
+/// - `hysteresis`: distance beyond the threshold an object must move away to be absent again
+/// - `debounce`: number of consecutive readings required to confirm a transition
+/// - `state`: current presence state
+/// - `pending`: number of consecutive readings disagreeing with the current state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PresenceDetector {
+    threshold: Distance,
+    hysteresis: Distance,
+    debounce: u32,
+    state: Presence,
+    pending: u32,
+}
+
+impl PresenceDetector {
+    /// Create a new detector reporting objects closer than `threshold` as present, and as absent
+    /// again once farther than `threshold + hysteresis`. Starts in the [`Presence::Absent`] state.
+    pub fn new(threshold: Distance, hysteresis: Distance) -> Self {
+        Self {
+            threshold,
+            hysteresis,
+            debounce: 1,
+            state: Presence::Absent,
+            pending: 0,
+        }
+    }
+
+    /// Require `readings` consecutive readings (at least one, the default) to confirm a
+    /// transition.
+    pub fn debounce(mut self, readings: u32) -> Self {
+        self.debounce = readings.max(1);
+        self
+    }
+
+    /// Current presence state.
+    pub fn state(&self) -> Presence {
+        self.state
+    }
+
+    /// Update the detector with the measured `distance` (`None` if no object is in range),
+    /// returning the new state on transitions.
+    pub fn update(&mut self, distance: Option<Distance>) -> Option<Presence> {
+        let next = match (self.state, distance) {
+            (Presence::Absent, Some(distance)) if distance < self.threshold => Presence::Present,
+            (Presence::Present, Some(distance)) if distance <= self.threshold + self.hysteresis => {
+                Presence::Present
+            }
+            _ => Presence::Absent,
+        };
+
+        if next == self.state {
+            self.pending = 0;
+            return None;
+        }
+
+        self.pending += 1;
+        if self.pending < self.debounce {
+            return None;
+        }
+
+        self.pending = 0;
+        self.state = next;
+        Some(next)
+    }
+
+    /// Measure distance through `sensor` and update the detector (see
+    /// [`PresenceDetector::update`]).
+    pub fn poll<B: Backend>(&mut self, sensor: &mut HcSr04<B>) -> Result<Option<Presence>> {
+        Ok(self.update(sensor.measure()?))
+    }
+
+    /// Discard the detector state, back to [`Presence::Absent`].
+    pub fn reset(&mut self) {
+        self.state = Presence::Absent;
+        self.pending = 0;
+    }
+}
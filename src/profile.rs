@@ -0,0 +1,175 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Persistent calibration profiles.
+//!
+//! A [`CalibrationProfile`] collects the ambient conditions, the distance [`Correction`] and the
+//! filter settings of a sensor, and is saved to and loaded from JSON files, so that long-running
+//! deployments survive restarts without recomputing or hard-coding calibration.
+//!
+//! ```rust
+//! use hc_sr04::profile::{CalibrationProfile, FilterSettings};
+//!
+//! let mut profile = CalibrationProfile::new(23.);
+//! profile.correction.offset = -0.02;
+//! profile.filters.push(FilterSettings::Median { size: 5 });
+//!
+//! let path = std::env::temp_dir().join("hc-sr04-profile.json");
+//! profile.save(&path).unwrap();
+//! assert_eq!(CalibrationProfile::load(&path).unwrap(), profile);
+//! # std::fs::remove_file(path).unwrap();
+//! ```
+//!
+//! Profiles are applied when building the sensor (see [`HcSr04Builder::profile`]) or at runtime
+//! (see [`HcSr04::apply_profile`]).
+
+#[cfg(doc)]
+use crate::HcSr04Builder;
+use crate::{
+    backend::Backend,
+    builder::DEFAULT_TEMPERATURE,
+    calibration::{self, Correction},
+    filter::{Ema, Kalman, Median, OutlierReject, Percentile, Pipeline},
+    Float, HcSr04, Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+};
+
+/// Settings of a filter stage (see the [`filter`](crate::filter) module).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FilterSettings {
+    /// [`Median`] over the latest `size` readings.
+    Median { size: usize },
+    /// [`Percentile`] of the latest `size` readings.
+    Percentile { size: usize, percentile: Float },
+    /// [`OutlierReject`] filter.
+    OutlierReject {
+        max_deviation: Float,
+        max_rejections: u32,
+    },
+    /// [`Ema`] filter.
+    Ema { alpha: Float },
+    /// [`Kalman`] filter.
+    Kalman {
+        process_noise: Float,
+        measurement_noise: Float,
+    },
+}
+
+impl FilterSettings {
+    /// Append the filter described by the settings to `pipeline`.
+    pub fn append_to(self, pipeline: Pipeline) -> Pipeline {
+        match self {
+            Self::Median { size } => pipeline.then(Median::new(size)),
+            Self::Percentile { size, percentile } => {
+                pipeline.then(Percentile::new(size, percentile))
+            }
+            Self::OutlierReject {
+                max_deviation,
+                max_rejections,
+            } => pipeline.then(OutlierReject::new(max_deviation, max_rejections)),
+            Self::Ema { alpha } => pipeline.then(Ema::new(alpha)),
+            Self::Kalman {
+                process_noise,
+                measurement_noise,
+            } => pipeline.then(Kalman::new(process_noise, measurement_noise)),
+        }
+    }
+
+    /// Build the filter pipeline made of the stages described by `filters`, in order.
+    pub fn pipeline(filters: &[Self]) -> Pipeline {
+        filters.iter().fold(Pipeline::new(), |pipeline, settings| {
+            settings.append_to(pipeline)
+        })
+    }
+}
+
+/// Calibration of a sensor, persisted across restarts.
+///
+/// # Fields
+///
+/// - `temperature`: ambient temperature in *Celsius degrees*
+/// - `relative_humidity`: ambient relative humidity in percent, if known
+/// - `pressure`: ambient barometric pressure in hPa, if known
+/// - `correction`: linear correction applied to measured distances
+/// - `filters`: filter stages applied to measured distances, in order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CalibrationProfile {
+    pub temperature: Float,
+    pub relative_humidity: Option<Float>,
+    pub pressure: Option<Float>,
+    pub correction: Correction,
+    pub filters: Vec<FilterSettings>,
+}
+
+impl CalibrationProfile {
+    /// Create a new profile for the given ambient temperature (`temp`) in *Celsius degrees*,
+    /// with no correction nor filters.
+    pub fn new(temp: Float) -> Self {
+        Self {
+            temperature: temp,
+            relative_humidity: None,
+            pressure: None,
+            correction: Correction::IDENTITY,
+            filters: Vec::new(),
+        }
+    }
+
+    /// Load the profile from the JSON file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Save the profile to the JSON file at `path`, overwriting it.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        Ok(writer.flush()?)
+    }
+
+    /// Speed of sound in m/s given the ambient conditions of the profile.
+    pub(crate) fn sound_speed(&self) -> Float {
+        calibration::sound_speed_env(self.temperature, self.relative_humidity, self.pressure)
+    }
+
+    /// Build the filter pipeline described by the profile.
+    pub fn pipeline(&self) -> Pipeline {
+        FilterSettings::pipeline(&self.filters)
+    }
+}
+
+impl Default for CalibrationProfile {
+    fn default() -> Self {
+        Self::new(DEFAULT_TEMPERATURE)
+    }
+}
+
+impl<B: Backend> HcSr04<B> {
+    /// Apply `profile` to the sensor: recalibrate it given the ambient conditions, set the
+    /// distance correction and replace the filters (discarding their state).
+    pub fn apply_profile(&mut self, profile: &CalibrationProfile) {
+        self.set_sound_speed(profile.sound_speed());
+        self.correction = profile.correction;
+        self.filter = profile.pipeline();
+    }
+}
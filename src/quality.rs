@@ -0,0 +1,80 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Measurement confidence metric.
+//!
+//! Every [`Measurement`](crate::Measurement) and [`Burst`](crate::burst::Burst) carries a
+//! confidence score in `0.0..=1.0`, so that downstream logic can ignore low-quality readings. The
+//! score is the product of:
+//!
+//! - the sensor's recent reliability (see
+//!   [`HcSr04::reliability`](crate::HcSr04::reliability)): the fraction of the latest
+//!   [`HISTORY_LEN`] pings which received an **ECHO** pulse in time, rather than timing out or
+//!   failing
+//! - for bursts, the fraction of pings detecting an object, out of the requested ones
+//! - a spread factor, `1 / (1 + σ / REFERENCE_SPREAD)`, given the standard deviation `σ` of the
+//!   distance estimate (tracked by filters such as [`Kalman`](crate::filter::Kalman), or of the
+//!   distances within a burst), if known
+//!
+//! ```rust
+//! use hc_sr04::quality;
+//!
+//! assert_eq!(quality::spread_factor(0.), 1.);
+//! assert_eq!(quality::spread_factor(quality::REFERENCE_SPREAD), 0.5);
+//! ```
+
+use crate::Float;
+
+/// Number of latest pings the sensor's reliability is computed over.
+pub const HISTORY_LEN: u32 = 16;
+
+/// Standard deviation in m of the distance estimate halving the confidence.
+pub const REFERENCE_SPREAD: Float = 0.01;
+
+/// Confidence factor given the standard deviation `std_dev` in m of the distance estimate.
+pub fn spread_factor(std_dev: Float) -> Float {
+    1. / (1. + std_dev.max(0.) / REFERENCE_SPREAD)
+}
+
+/// Outcomes of the latest pings, most recent in the lowest bit (set if an **ECHO** pulse was
+/// received in time).
+///
+/// # Fields
+///
+/// - `bits`: ping outcomes
+/// - `len`: number of recorded pings, up to [`HISTORY_LEN`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct History {
+    bits: u16,
+    len: u32,
+}
+
+impl History {
+    /// Record the outcome of a ping, discarding the oldest one if the history is full.
+    pub(crate) fn record(&mut self, echo: bool) {
+        self.bits = self.bits << 1 | u16::from(echo);
+        self.len = (self.len + 1).min(HISTORY_LEN);
+    }
+
+    /// Fraction of the recorded pings which received an **ECHO** pulse in time (`1.0` if none
+    /// was recorded).
+    pub(crate) fn reliability(&self) -> Float {
+        match self.len {
+            0 => 1.,
+            len => self.bits.count_ones() as Float / len as Float,
+        }
+    }
+}
@@ -0,0 +1,78 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Real-time scheduling of the measuring thread, available with the `realtime` feature.
+//!
+//! While the **ECHO** pulse is being captured, the measuring thread may be preempted by the
+//! scheduler, adding jitter to the measured pulse width. Raising it to the `SCHED_FIFO` policy for
+//! the duration of the capture prevents ordinary processes from preempting it (see
+//! [`HcSr04Builder::realtime`](crate::HcSr04Builder::realtime)).
+//!
+//! Changing the scheduling policy requires the `CAP_SYS_NICE` capability (e.g. running as root)
+//! or a suitable `RLIMIT_RTPRIO` resource limit.
+
+use crate::{error::Error, Result};
+use std::{io, mem};
+
+/// Scheduling policy of the current thread, restored when dropped.
+///
+/// # Fields
+///
+/// - `policy`: previous scheduling policy
+/// - `param`: previous scheduling parameters
+pub(crate) struct SchedulerGuard {
+    policy: i32,
+    param: libc::sched_param,
+}
+
+impl SchedulerGuard {
+    /// Raise the current thread to the `SCHED_FIFO` policy with the given `priority` (`1..=99`
+    /// on Linux), until the returned guard is dropped.
+    pub(crate) fn fifo(priority: i32) -> Result<Self> {
+        // SAFETY: `sched_param` is a plain C struct, for which all-zero is a valid value, filled
+        // in by `pthread_getschedparam` for the calling thread.
+        unsafe {
+            let thread = libc::pthread_self();
+            let mut policy = 0;
+            let mut param: libc::sched_param = mem::zeroed();
+            check(libc::pthread_getschedparam(thread, &mut policy, &mut param))?;
+
+            let mut fifo = param;
+            fifo.sched_priority = priority;
+            check(libc::pthread_setschedparam(thread, libc::SCHED_FIFO, &fifo))?;
+
+            Ok(Self { policy, param })
+        }
+    }
+}
+
+impl Drop for SchedulerGuard {
+    fn drop(&mut self) {
+        // SAFETY: restoring the parameters previously returned by `pthread_getschedparam` for
+        // the calling thread. Nothing can be done on failure, the thread keeps running.
+        unsafe {
+            libc::pthread_setschedparam(libc::pthread_self(), self.policy, &self.param);
+        }
+    }
+}
+
+/// Map a pthread function return value to [`Error::Realtime`].
+fn check(code: i32) -> Result<()> {
+    match code {
+        0 => Ok(()),
+        code => Err(Error::Realtime(io::Error::from_raw_os_error(code))),
+    }
+}
@@ -0,0 +1,136 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Ring buffer of the most recent measurements.
+//!
+//! When enabled through [`HcSr04Builder::recent`](crate::HcSr04Builder::recent), the sensor keeps
+//! its last measurements in a fixed-capacity [`RecentReadings`] buffer. The buffer is shared: a
+//! handle obtained before moving the sensor to a continuous worker keeps observing it, without
+//! consuming the measurement stream.
+//!
+//! ```rust,no_run
+//! use hc_sr04::HcSr04;
+//! use std::time::Duration;
+//!
+//! let ultrasonic = HcSr04::builder(24, 23).recent(32).build().unwrap();
+//! let recent = ultrasonic.recent_readings().unwrap();
+//!
+//! let _receiver = ultrasonic.start_continuous(Duration::from_millis(100));
+//!
+//! // Later on, e.g. from a debugging endpoint.
+//! for measurement in recent.recent(5) {
+//!     println!("{:?}", measurement.distance);
+//! }
+//!
+//! // Everything measured over the last 10 seconds.
+//! let trend: Vec<_> = recent
+//!     .history(Duration::from_secs(10))
+//!     .into_iter()
+//!     .filter_map(|measurement| measurement.distance)
+//!     .collect();
+//! println!("{} distances, {:?}", trend.len(), trend.last());
+//! ```
+
+use crate::Measurement;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, SystemTime},
+};
+
+/// Shared fixed-capacity ring buffer of the most recent measurements.
+///
+/// Cloning yields a new handle to the same buffer.
+#[derive(Debug, Clone)]
+pub struct RecentReadings {
+    buffer: Arc<Mutex<VecDeque<Measurement>>>,
+    capacity: usize,
+}
+
+impl RecentReadings {
+    /// Create an empty buffer of the last `capacity` measurements (at least one).
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            buffer: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    /// Maximum number of measurements kept.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of measurements currently kept.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether no measurement was recorded yet.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Last (at most) `n` measurements, oldest first.
+    pub fn recent(&self, n: usize) -> Vec<Measurement> {
+        let buffer = self.lock();
+        buffer
+            .iter()
+            .skip(buffer.len().saturating_sub(n))
+            .copied()
+            .collect()
+    }
+
+    /// Measurements taken within the last `window`, oldest first, e.g. for trend displays.
+    ///
+    /// Measurements timestamped in the future, e.g. after the system clock was set back, are
+    /// included.
+    pub fn history(&self, window: Duration) -> Vec<Measurement> {
+        let now = SystemTime::now();
+        self.lock()
+            .iter()
+            .filter(|measurement| {
+                now.duration_since(measurement.timestamp)
+                    .map_or(true, |age| age <= window)
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Most recent measurement, if any.
+    pub fn latest(&self) -> Option<Measurement> {
+        self.lock().back().copied()
+    }
+
+    /// Discard all kept measurements.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Record `measurement`, discarding the oldest one if the buffer is full.
+    pub(crate) fn push(&self, measurement: Measurement) {
+        let mut buffer = self.lock();
+        if buffer.len() == self.capacity {
+            buffer.pop_front();
+        }
+        buffer.push_back(measurement);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<Measurement>> {
+        self.buffer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
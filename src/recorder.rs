@@ -0,0 +1,160 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Recording of measurements to files, available with the `recorder` feature.
+//!
+//! A [`Recorder`] appends timestamped measurements to a CSV or JSON Lines file (see [`Format`]),
+//! for long-term data collection. Measurements can be recorded in blocking mode through
+//! [`Recorder::poll`], or from continuous mode:
+//!
+//! ```rust,no_run
+//! use hc_sr04::{
+//!     recorder::{Format, Recorder},
+//!     HcSr04,
+//! };
+//! use std::time::Duration;
+//!
+//! let ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//! let mut recorder = Recorder::create("distance.csv", Format::Csv)
+//!     .unwrap()
+//!     .flush_interval(Duration::from_secs(10));
+//!
+//! for measurement in ultrasonic.start_continuous(Duration::from_millis(100)) {
+//!     recorder.record(&measurement.unwrap()).unwrap();
+//! }
+//! ```
+
+use crate::{backend::Backend, error::Error, HcSr04, Measurement, Result};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    path::Path,
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+/// Header line of CSV files.
+pub(crate) const CSV_HEADER: &str = "timestamp,distance,variance";
+/// Default interval between file flushes.
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Format of recorded measurements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    /// Comma separated values, one measurement per line, with a header line: UNIX timestamp in s,
+    /// distance in m and its variance in m² (both empty if not available).
+    Csv,
+    /// One JSON object per line (see [`Measurement`]).
+    JsonLines,
+}
+
+/// Recorder of measurements to a file.
+///
+/// Measurements are buffered and flushed to the file at most once per flush interval, and when
+/// the recorder is dropped.
+///
+/// # Fields
+///
+/// - `writer`: buffered file writer
+/// - `format`: format of recorded measurements
+/// - `flush_interval`: minimum time between consecutive flushes
+/// - `last_flush`: time of the latest flush
+#[derive(Debug)]
+pub struct Recorder {
+    writer: BufWriter<File>,
+    format: Format,
+    flush_interval: Duration,
+    last_flush: Instant,
+}
+
+impl Recorder {
+    /// Open the file at `path` for recording measurements in `format`, appending to it if it
+    /// already exists. CSV files get a header line when created.
+    ///
+    /// Measurements are flushed to the file every second by default.
+    pub fn create(path: impl AsRef<Path>, format: Format) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let empty = file.metadata()?.len() == 0;
+
+        let mut writer = BufWriter::new(file);
+        if format == Format::Csv && empty {
+            writeln!(writer, "{}", CSV_HEADER)?;
+        }
+
+        Ok(Self {
+            writer,
+            format,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            last_flush: Instant::now(),
+        })
+    }
+
+    /// Minimum time between consecutive flushes to the file (defaults to *1s*): the longer, the
+    /// fewer writes at the risk of losing more measurements on power loss. A zero interval
+    /// flushes every measurement.
+    pub fn flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// Record `measurement`, flushing the file if the flush interval elapsed.
+    pub fn record(&mut self, measurement: &Measurement) -> Result<()> {
+        match self.format {
+            Format::Csv => {
+                let timestamp = measurement
+                    .timestamp
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs_f64();
+                let distance = measurement
+                    .distance
+                    .map(|distance| distance.as_meters().to_string());
+                let variance = measurement.variance.map(|variance| variance.to_string());
+
+                writeln!(
+                    self.writer,
+                    "{:.6},{},{}",
+                    timestamp,
+                    distance.unwrap_or_default(),
+                    variance.unwrap_or_default()
+                )?;
+            }
+            Format::JsonLines => {
+                serde_json::to_writer(&mut self.writer, measurement).map_err(Error::Serialize)?;
+                writeln!(self.writer)?;
+            }
+        }
+
+        if self.last_flush.elapsed() >= self.flush_interval {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Measure distance through `sensor` and record the measurement, returning it.
+    pub fn poll<B: Backend>(&mut self, sensor: &mut HcSr04<B>) -> Result<Measurement> {
+        let measurement = sensor.measurement()?;
+        self.record(&measurement)?;
+        Ok(measurement)
+    }
+
+    /// Flush recorded measurements to the file.
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Replay of recorded measurements, available with the `recorder` feature.
+//!
+//! The [`Replay`] backend plays back a measurement log written by a
+//! [`Recorder`](crate::recorder::Recorder) through the normal sensor API, optionally honoring the
+//! original timing, so that data pipelines and detection logic can be developed and
+//! regression-tested without hardware.
+//!
+//! ```rust
+//! use hc_sr04::{
+//!     recorder::{Format, Recorder},
+//!     Distance, HcSr04, Measurement,
+//! };
+//! use std::time::SystemTime;
+//!
+//! let path = std::env::temp_dir().join("hc-sr04-replay.csv");
+//! # let _ = std::fs::remove_file(&path);
+//! let mut recorder = Recorder::create(&path, Format::Csv).unwrap();
+//! for distance in [Some(1.5), None] {
+//!     recorder
+//!         .record(&Measurement::new(
+//!             distance.map(Distance::from_meters),
+//!             SystemTime::now(),
+//!         ))
+//!         .unwrap();
+//! }
+//! drop(recorder);
+//!
+//! let mut ultrasonic = HcSr04::replay(&path, Format::Csv).unwrap();
+//! assert_eq!(ultrasonic.remaining(), 2);
+//! let dist = ultrasonic.measure().unwrap().unwrap();
+//! assert!((dist.as_meters() - 1.5).abs() < 1e-3);
+//! assert_eq!(ultrasonic.measure().unwrap(), None);
+//! # std::fs::remove_file(path).unwrap();
+//! ```
+
+use crate::{
+    backend::{Backend, Timing},
+    portable::from_secs,
+    recorder::{Format, CSV_HEADER},
+    Float, HcSr04, HcSr04Builder, Measurement, Result,
+};
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// [`Backend`] replaying recorded **ECHO** pulses, in the order they were recorded.
+///
+/// Once the log is exhausted, every measurement behaves as if no object is in range.
+///
+/// # Fields
+///
+/// - `log`: recorded **ECHO** pulses (`None` if no object was in range) and their timestamps
+/// - `realtime`: whether replay honors the original timing
+/// - `origin`: replay start time and timestamp of the first replayed pulse
+#[derive(Debug, Default)]
+pub struct Replay {
+    log: VecDeque<(SystemTime, Option<Duration>)>,
+    realtime: bool,
+    origin: Option<(Instant, SystemTime)>,
+}
+
+impl Backend for Replay {
+    fn ping(&mut self, timing: &Timing) -> Result<Option<Duration>> {
+        let (timestamp, echo) = match self.log.pop_front() {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        if self.realtime {
+            let (start, first) = *self.origin.get_or_insert((Instant::now(), timestamp));
+            let offset = timestamp.duration_since(first).unwrap_or_default();
+            if let Some(wait) = (start + offset).checked_duration_since(Instant::now()) {
+                thread::sleep(wait);
+            }
+        }
+
+        // Pulses beyond the current maximum measuring range are out of range.
+        Ok(echo.filter(|echo| *echo < timing.pulse_timeout))
+    }
+}
+
+impl HcSr04<Replay> {
+    /// Initialize a sensor replaying the measurement log at `path`, recorded in `format`, as fast
+    /// as measurements are performed.
+    ///
+    /// Recorded distances are converted to **ECHO** pulses using the default calibration:
+    /// calibrating the sensor afterwards changes the measured distances accordingly.
+    pub fn replay(path: impl AsRef<Path>, format: Format) -> Result<Self> {
+        let mut sensor = HcSr04Builder::with_backend(Replay::default())
+            .min_cycle(Duration::ZERO)
+            .build();
+
+        for (index, line) in fs::read_to_string(path)?.lines().enumerate() {
+            if line.is_empty() || (format == Format::Csv && line == CSV_HEADER) {
+                continue;
+            }
+
+            let (timestamp, distance) = parse(line, format).ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("malformed measurement on line {}", index + 1),
+                )
+            })?;
+            let echo =
+                distance.map(|distance| from_secs(distance.max(0.) * 2. / sensor.sound_speed));
+            sensor.backend.log.push_back((timestamp, echo));
+        }
+
+        Ok(sensor)
+    }
+
+    /// Honor the original timing of the log, waiting between measurements as much time as it
+    /// elapsed between the recorded ones (disabled by default).
+    pub fn realtime(&mut self, enabled: bool) -> &mut Self {
+        self.backend.realtime = enabled;
+        self.backend.origin = None;
+        self
+    }
+
+    /// Number of recorded measurements not yet replayed.
+    pub fn remaining(&self) -> usize {
+        self.backend.log.len()
+    }
+}
+
+/// Parse the timestamp and distance in m of the measurement recorded on `line` in `format`.
+fn parse(line: &str, format: Format) -> Option<(SystemTime, Option<Float>)> {
+    match format {
+        Format::Csv => {
+            let mut fields = line.split(',');
+            let timestamp = fields
+                .next()?
+                .parse::<f64>()
+                .ok()
+                .filter(|timestamp| (0.0..u64::MAX as f64).contains(timestamp))?;
+            let distance = match fields.next()? {
+                "" => None,
+                distance => Some(distance.parse().ok()?),
+            };
+            let timestamp = UNIX_EPOCH.checked_add(Duration::from_secs_f64(timestamp))?;
+
+            Some((timestamp, distance))
+        }
+        Format::JsonLines => {
+            let measurement: Measurement = serde_json::from_str(line).ok()?;
+            Some((
+                measurement.timestamp,
+                measurement.distance.map(|distance| distance.as_meters()),
+            ))
+        }
+    }
+}
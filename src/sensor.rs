@@ -0,0 +1,238 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! Sensor-agnostic distance measurement.
+//!
+//! [`DistanceSensor`] is implemented by [`HcSr04`] for any [`Backend`] (the mock sensor
+//! included) and by the other supported sensors, so that applications can be written against
+//! the trait and swap hardware without code changes.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{DistanceSensor, HcSr04, Unit};
+//!
+//! fn report(sensor: &mut impl DistanceSensor) {
+//!     match sensor.measure_distance(Unit::Centimeters).unwrap() {
+//!         Some(dist) => println!("Distance: {:.1}cm", dist),
+//!         None => println!("Object out of range"),
+//!     }
+//! }
+//!
+//! let mut ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//! ultrasonic.calibrate(23.);
+//! report(&mut ultrasonic);
+//! ```
+//!
+//! Sensors measuring on their own, with no filters of their own, are filtered by wrapping them
+//! into [`Filtered`], and sampled in the background through
+//! [`continuous::start_with`](crate::continuous::start_with). Fleets mixing sensor types are
+//! driven as `Vec<Box<dyn DistanceSensor>>`.
+
+#[cfg(feature = "i2c")]
+use crate::i2c::I2cRanger;
+#[cfg(feature = "us100")]
+use crate::us100::Us100;
+use crate::{
+    backend::Backend,
+    filter::{DistanceFilter, Pipeline},
+    model::Spec,
+    Distance, Float, HcSr04, Result, Unit,
+};
+#[cfg(feature = "us100")]
+use std::time::Duration;
+
+/// Distance sensor, measuring the distance of the closest object in range.
+pub trait DistanceSensor {
+    /// Perform distance measurement.
+    ///
+    /// Returns `Ok(None)` if the object is out of range.
+    fn measure(&mut self) -> Result<Option<Distance>>;
+
+    /// Calibrate the sensor with the given ambient temperature `temp` in *Celsius degrees*.
+    fn calibrate(&mut self, temp: Float);
+
+    /// Timing and range specification the sensor currently operates with.
+    fn spec(&self) -> Spec;
+
+    /// Perform distance measurement, in the given `unit`.
+    ///
+    /// Returns `Ok(None)` if the object is out of range.
+    fn measure_distance(&mut self, unit: Unit) -> Result<Option<Float>> {
+        Ok(self.measure()?.map(|distance| distance.as_unit(unit)))
+    }
+}
+
+/// Sensor whose measured distances are filtered through a [`Pipeline`], obtained through
+/// [`Filtered::new`].
+///
+/// Readings rejected by the pipeline are reported as out of range, like the filters set on the
+/// HC-SR04 do.
+///
+/// ```rust
+/// # #[cfg(feature = "mock")]
+/// # {
+/// use hc_sr04::{filter::Median, sensor::Filtered, DistanceSensor, MockHcSr04, Unit};
+///
+/// let mut mock = MockHcSr04::mock(None);
+/// mock.push_distance(1., Unit::Meters);
+/// mock.push_distance(3., Unit::Meters);
+/// mock.push_distance(1., Unit::Meters);
+///
+/// let mut sensor = Filtered::new(mock).then(Median::new(3));
+/// sensor.measure().unwrap();
+/// sensor.measure().unwrap();
+/// // Spike removed by the median.
+/// let dist = sensor.measure_distance(Unit::Meters).unwrap().unwrap();
+/// assert!((dist - 1.).abs() < 0.01);
+/// # }
+/// ```
+///
+/// # Fields
+///
+/// - `sensor`: wrapped sensor
+/// - `pipeline`: filters applied to the distances measured by `sensor`
+#[derive(Debug)]
+pub struct Filtered<S> {
+    sensor: S,
+    pipeline: Pipeline,
+}
+
+impl<S: DistanceSensor> Filtered<S> {
+    /// Wrap `sensor`, with no filters yet.
+    pub fn new(sensor: S) -> Self {
+        Self {
+            sensor,
+            pipeline: Pipeline::new(),
+        }
+    }
+
+    /// Append `filter` to the pipeline.
+    pub fn then(mut self, filter: impl DistanceFilter + Send + 'static) -> Self {
+        self.pipeline.push(filter);
+        self
+    }
+
+    /// Wrapped sensor.
+    pub fn sensor_mut(&mut self) -> &mut S {
+        &mut self.sensor
+    }
+
+    /// Discard the state of the filters, e.g. after the sensor has been moved.
+    pub fn reset(&mut self) {
+        self.pipeline.reset();
+    }
+
+    /// Unwrap the sensor, dropping the filters.
+    pub fn into_inner(self) -> S {
+        self.sensor
+    }
+}
+
+impl<S: DistanceSensor> DistanceSensor for Filtered<S> {
+    fn measure(&mut self) -> Result<Option<Distance>> {
+        Ok(self
+            .sensor
+            .measure()?
+            .and_then(|distance| self.pipeline.apply(distance.as_meters()))
+            .map(Distance::from_meters))
+    }
+
+    fn calibrate(&mut self, temp: Float) {
+        self.sensor.calibrate(temp)
+    }
+
+    fn spec(&self) -> Spec {
+        self.sensor.spec()
+    }
+}
+
+impl<S: DistanceSensor + ?Sized> DistanceSensor for Box<S> {
+    fn measure(&mut self) -> Result<Option<Distance>> {
+        (**self).measure()
+    }
+
+    fn calibrate(&mut self, temp: Float) {
+        (**self).calibrate(temp)
+    }
+
+    fn spec(&self) -> Spec {
+        (**self).spec()
+    }
+
+    fn measure_distance(&mut self, unit: Unit) -> Result<Option<Float>> {
+        (**self).measure_distance(unit)
+    }
+}
+
+impl<B: Backend> DistanceSensor for HcSr04<B> {
+    fn measure(&mut self) -> Result<Option<Distance>> {
+        HcSr04::measure(self)
+    }
+
+    fn calibrate(&mut self, temp: Float) {
+        HcSr04::calibrate(self, temp)
+    }
+
+    fn spec(&self) -> Spec {
+        Spec {
+            trigger_pulse: self.trigger_pulse(),
+            min_cycle: self.min_cycle(),
+            min_range: self.min_range(),
+            max_range: self.max_range(),
+        }
+    }
+
+    fn measure_distance(&mut self, unit: Unit) -> Result<Option<Float>> {
+        HcSr04::measure_distance(self, unit)
+    }
+}
+
+#[cfg(feature = "us100")]
+impl DistanceSensor for Us100 {
+    fn measure(&mut self) -> Result<Option<Distance>> {
+        Us100::measure(self)
+    }
+
+    /// Does nothing: the US-100 compensates the speed of sound with its on-board temperature
+    /// sensor.
+    fn calibrate(&mut self, _temp: Float) {}
+
+    /// No **TRIGGER** pulse nor measurement cycle: the US-100 measures on request.
+    fn spec(&self) -> Spec {
+        Spec {
+            trigger_pulse: Duration::ZERO,
+            min_cycle: Duration::ZERO,
+            min_range: crate::us100::MIN_RANGE,
+            max_range: self.max_range(),
+        }
+    }
+}
+
+#[cfg(feature = "i2c")]
+impl DistanceSensor for I2cRanger {
+    fn measure(&mut self) -> Result<Option<Distance>> {
+        I2cRanger::measure(self)
+    }
+
+    fn calibrate(&mut self, temp: Float) {
+        I2cRanger::calibrate(self, temp)
+    }
+
+    fn spec(&self) -> Spec {
+        Spec {
+            max_range: self.max_range(),
+            ..self.model().spec()
+        }
+    }
+}
@@ -0,0 +1,232 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Several sensors triggered through one shared GPIO pin.
+//!
+//! Wiring the **TRIGGER** lines of several sensors to the same GPIO pin saves pins on dense
+//! sensor arrays: [`SharedTrigger`] fires a single **TRIGGER** pulse and captures the **ECHO**
+//! pulses of the requested sensors at once, busy polling their pins.
+//!
+//! Sensors pinged together can pick up each other's bursts: only ping sensors facing different
+//! directions together, and measure the others in separate calls.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{shared::SharedTrigger, Unit};
+//!
+//! // TRIGGER lines of all sensors wired to GPIO 24.
+//! let mut sensors = SharedTrigger::new(24, &[23, 27, 22], None).unwrap();
+//!
+//! // Ping front (0) and rear (2) sensors together.
+//! for (i, dist) in [0, 2].iter().zip(sensors.measure_distance(&[0, 2], Unit::Meters)) {
+//!     match dist.unwrap() {
+//!         Some(dist) => println!("Sensor {i} distance: {:.2}m", dist),
+//!         None => println!("Sensor {i}: object out of range"),
+//!     }
+//! }
+//! ```
+
+use crate::{
+    backend::Timing,
+    builder::DEFAULT_TEMPERATURE,
+    error::Error,
+    model::Model,
+    portable::{self, Echo, EchoCapture},
+    Float, Result, Unit,
+};
+use rppal::gpio::{Gpio, InputPin, OutputPin};
+use std::{
+    hint, thread,
+    time::{Duration, Instant},
+};
+
+/// Sensors sharing one **TRIGGER** pin, each with its own **ECHO** pin, measured as per the
+/// default sensor model (see [`Model`]).
+///
+/// # Fields
+///
+/// - `trig`: shared **TRIGGER** output pin
+/// - `echoes`: **ECHO** input pins, one per sensor
+/// - `timing`: **TRIGGER** pulse width and **ECHO** pin polling timeouts
+/// - `sound_speed`: speed of sound in m/s, computed from the ambient temperature
+/// - `max_range`: maximum measuring range in m, used to compute the **ECHO** pulse timeout
+/// - `min_range`: minimum measuring range in m
+/// - `min_cycle`: minimum time between consecutive pings
+/// - `last_ping`: time of the last ping
+#[derive(Debug)]
+pub struct SharedTrigger {
+    trig: OutputPin,
+    echoes: Vec<InputPin>,
+    timing: Timing,
+    sound_speed: Float,
+    max_range: Float,
+    min_range: Float,
+    min_cycle: Duration,
+    last_ping: Option<Instant>,
+}
+
+impl SharedTrigger {
+    /// Initialize the sensors sharing the `trig` (**TRIGGER**) GPIO pin, with the given `echoes`
+    /// (**ECHO**) GPIO pins, and the ambient temperature `temp` used for calibration (if `None`
+    /// defaults to `20.0`). **ECHO** pins are pulled down.
+    pub fn new(trig: u8, echoes: &[u8], temp: Option<Float>) -> Result<Self> {
+        Self::with_gpio(&Gpio::new()?, trig, echoes, temp)
+    }
+
+    /// Initialize the sensors as [`SharedTrigger::new`] does, getting the pins from the
+    /// application's `gpio` instance rather than opening a new one.
+    pub fn with_gpio(gpio: &Gpio, trig: u8, echoes: &[u8], temp: Option<Float>) -> Result<Self> {
+        let echoes = echoes
+            .iter()
+            .map(|&echo| Ok(gpio.get(echo)?.into_input_pulldown()))
+            .collect::<Result<_>>()?;
+        let trig = gpio.get(trig)?.into_output_low();
+
+        let spec = Model::default().spec();
+        let sound_speed = portable::sound_speed(temp.unwrap_or(DEFAULT_TEMPERATURE));
+        Ok(Self {
+            trig,
+            echoes,
+            timing: Timing {
+                trigger_pulse: spec.trigger_pulse,
+                start_timeout: None,
+                pulse_timeout: portable::echo_timeout(spec.max_range, sound_speed),
+            },
+            sound_speed,
+            max_range: spec.max_range,
+            min_range: spec.min_range,
+            min_cycle: spec.min_cycle,
+            last_ping: None,
+        })
+    }
+
+    /// Number of sensors (**ECHO** pins).
+    pub fn len(&self) -> usize {
+        self.echoes.len()
+    }
+
+    /// Returns `true` if there are no sensors.
+    pub fn is_empty(&self) -> bool {
+        self.echoes.is_empty()
+    }
+
+    /// Calibrate the sensors with the given **ambient temperature** (`temp`) expressed as
+    /// *Celsius degrees*.
+    pub fn calibrate(&mut self, temp: Float) {
+        self.sound_speed = portable::sound_speed(temp);
+        self.timing.pulse_timeout = portable::echo_timeout(self.max_range, self.sound_speed);
+    }
+
+    /// Set (or remove, if `None`) the maximum time to wait for each **ECHO** pulse to start
+    /// (see [`HcSr04Builder::echo_start_timeout`](crate::HcSr04Builder::echo_start_timeout)).
+    pub fn set_echo_start_timeout(&mut self, timeout: Option<Duration>) {
+        self.timing.start_timeout = timeout;
+    }
+
+    /// Fire one **TRIGGER** pulse and capture the **ECHO** pulses of the sensors at the given
+    /// `echoes` indices, returning the pulse widths in the same order.
+    ///
+    /// Each sensor's result is `None` if no object is within maximum measuring range, or fails
+    /// with [`Error::EchoStartTimeout`] if its **ECHO** pulse doesn't start in time, without
+    /// affecting the others. As the pins are polled in turn, timing resolution decreases with
+    /// the number of sensors captured at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any index is out of bounds.
+    pub fn ping(&mut self, echoes: &[usize]) -> Vec<Result<Option<Duration>>> {
+        let pins: Vec<&InputPin> = echoes.iter().map(|&index| &self.echoes[index]).collect();
+
+        // Let residual echoes of the previous ping fade out.
+        let remaining = self
+            .last_ping
+            .and_then(|last| self.min_cycle.checked_sub(last.elapsed()));
+        if let Some(remaining) = remaining {
+            thread::sleep(remaining);
+        }
+        self.last_ping = Some(Instant::now());
+
+        self.trig.set_high();
+        let trigger = Instant::now();
+        while trigger.elapsed() < self.timing.trigger_pulse {
+            hint::spin_loop();
+        }
+        self.trig.set_low();
+
+        let origin = Instant::now();
+        let mut captures = vec![EchoCapture::new(&self.timing, Duration::ZERO); pins.len()];
+        let mut outcomes: Vec<Option<Result<Option<Duration>>>> =
+            pins.iter().map(|_| None).collect();
+        let mut pending = pins.len();
+        while pending > 0 {
+            for ((pin, capture), outcome) in pins.iter().zip(&mut captures).zip(&mut outcomes) {
+                if outcome.is_some() {
+                    continue;
+                }
+                *outcome = match capture.poll(origin.elapsed(), pin.is_high()) {
+                    Some(Echo::Pulse(width)) => Some(Ok(Some(width))),
+                    Some(Echo::OutOfRange) => Some(Ok(None)),
+                    Some(Echo::NoStart) => Some(Err(Error::EchoStartTimeout)),
+                    None => continue,
+                };
+                pending -= 1;
+            }
+        }
+
+        outcomes.into_iter().flatten().collect()
+    }
+
+    /// Perform **distance measurement** on the sensors at the given `echoes` indices through one
+    /// **TRIGGER** pulse (see [`SharedTrigger::ping`]), returning the distances expressed as
+    /// `unit` in the same order.
+    ///
+    /// Each sensor's result fails as [`SharedTrigger::ping`] does, or with [`Error::TooClose`]
+    /// if the object is within the minimum measuring range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any index is out of bounds.
+    pub fn measure_distance(&mut self, echoes: &[usize], unit: Unit) -> Vec<Result<Option<Float>>> {
+        let sound_speed = self.sound_speed;
+        let min_range = self.min_range;
+        self.ping(echoes)
+            .into_iter()
+            .map(|echo| match echo? {
+                Some(echo) => {
+                    let distance = portable::echo_distance(echo, sound_speed);
+                    if distance < min_range {
+                        return Err(Error::TooClose);
+                    }
+                    Ok(Some(portable::to_unit(distance, unit)))
+                }
+                None => Ok(None),
+            })
+            .collect()
+    }
+
+    /// Perform **distance measurement** on every sensor through one **TRIGGER** pulse (see
+    /// [`SharedTrigger::measure_distance`]).
+    pub fn measure_all(&mut self, unit: Unit) -> Vec<Result<Option<Float>>> {
+        let echoes: Vec<usize> = (0..self.echoes.len()).collect();
+        self.measure_distance(&echoes, unit)
+    }
+}
+
+impl Drop for SharedTrigger {
+    /// Drive the **TRIGGER** pin low, before the pins are reset to their previous mode.
+    fn drop(&mut self) {
+        self.trig.set_low();
+    }
+}
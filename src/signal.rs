@@ -0,0 +1,136 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! Shutdown on Unix signals, available with the `signal` feature.
+//!
+//! [`Shutdown`] catches `SIGINT` and `SIGTERM`, so that daemons (e.g. managed by systemd, which
+//! stops services through `SIGTERM`) stop their continuous workers cleanly instead of being
+//! killed mid-write: the in-flight measurement completes, recorders and sinks owned by the
+//! handlers are dropped, flushing them, and the pins are released, restoring their state. A
+//! second signal received while shutting down exits right away.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{signal::Shutdown, HcSr04};
+//! use std::time::Duration;
+//!
+//! let mut shutdown = Shutdown::install().unwrap();
+//! let ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//!
+//! let worker = ultrasonic.spawn_continuous(Duration::from_secs(1), |measurement| {
+//!     println!("{:?}", measurement);
+//!     true
+//! });
+//!
+//! // Blocks until SIGINT or SIGTERM is received, or the worker finishes on its own.
+//! let stats = worker.stop_on(&mut shutdown);
+//! println!("{} measurements", stats.measurements);
+//! ```
+
+use crate::{
+    continuous::{ContinuousStats, ContinuousWorker},
+    Result,
+};
+use signal_hook::{
+    consts::{SIGINT, SIGTERM},
+    flag,
+    iterator::Signals,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Signals requesting shutdown.
+pub const SIGNALS: [i32; 2] = [SIGINT, SIGTERM];
+/// Exit status of the process when a second signal is received while shutting down.
+const FORCED_EXIT_STATUS: i32 = 1;
+/// Interval between checks for finished workers while waiting for a signal.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Catcher of the shutdown [`SIGNALS`].
+///
+/// Once installed, the signals no longer terminate the process: they must be waited for through
+/// [`Shutdown::wait`] or [`Shutdown::stop_all`].
+///
+/// # Fields
+///
+/// - `signals`: caught signals not handled yet
+/// - `requested`: whether shutdown was requested, making further signals exit right away
+#[derive(Debug)]
+pub struct Shutdown {
+    signals: Signals,
+    requested: Arc<AtomicBool>,
+}
+
+impl Shutdown {
+    /// Start catching `SIGINT` and `SIGTERM`.
+    pub fn install() -> Result<Self> {
+        let requested = Arc::new(AtomicBool::new(false));
+        for signal in SIGNALS {
+            flag::register_conditional_shutdown(signal, FORCED_EXIT_STATUS, requested.clone())?;
+        }
+
+        Ok(Self {
+            signals: Signals::new(SIGNALS)?,
+            requested,
+        })
+    }
+
+    /// Whether shutdown was requested.
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Signal received since the previous call, if any, without blocking.
+    pub fn received(&mut self) -> Option<i32> {
+        let signal = self.signals.pending().next()?;
+        self.requested.store(true, Ordering::SeqCst);
+        Some(signal)
+    }
+
+    /// Block until a shutdown signal is received, returning it.
+    pub fn wait(&mut self) -> i32 {
+        // The iterator only ends once the signals are closed, which never happens.
+        let signal = self.signals.forever().next().unwrap_or(SIGTERM);
+        self.requested.store(true, Ordering::SeqCst);
+        signal
+    }
+
+    /// Block until a shutdown signal is received or all `workers` finish on their own, then stop
+    /// them, returning their error statistics in the same order.
+    pub fn stop_all(
+        &mut self,
+        workers: impl IntoIterator<Item = ContinuousWorker>,
+    ) -> Vec<ContinuousStats> {
+        let workers: Vec<_> = workers.into_iter().collect();
+        while self.received().is_none() && !workers.iter().all(ContinuousWorker::is_finished) {
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        workers.into_iter().map(ContinuousWorker::stop).collect()
+    }
+}
+
+impl ContinuousWorker {
+    /// Block until `shutdown` receives a signal or the worker finishes on its own, then stop it
+    /// (see [`Shutdown::stop_all`]).
+    pub fn stop_on(self, shutdown: &mut Shutdown) -> ContinuousStats {
+        shutdown.stop_all([self]).pop().unwrap_or_default()
+    }
+}
@@ -0,0 +1,41 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Sinks publishing measurements to external services.
+//!
+//! Available sinks:
+//!
+//! - `http`: HTTP endpoint serving the current distance (feature `http`)
+//! - `influxdb`: InfluxDB line protocol writer (feature `influxdb`)
+//! - `mqtt`: MQTT broker publisher (feature `mqtt`)
+//! - `prometheus`: Prometheus metrics exporter (feature `prometheus`)
+//! - `ros2`: ROS 2 `sensor_msgs/Range` publisher (feature `ros2`)
+//! - `websocket`: WebSocket server streaming measurements (feature `websocket`)
+
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "influxdb")]
+pub mod influxdb;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+#[cfg(feature = "ros2")]
+pub mod ros2;
+#[cfg(any(feature = "http", feature = "prometheus"))]
+mod server;
+#[cfg(feature = "websocket")]
+pub mod websocket;
@@ -0,0 +1,183 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! HTTP endpoint serving the current distance, available with the `http` feature.
+//!
+//! [`DistanceEndpoint`] answers `GET /distance` with the latest measurement as a JSON object, so
+//! that dashboards and other devices on the LAN can read the sensor without extra glue code:
+//!
+//! ```json
+//! {"status": "ok", "timestamp": 1717171717.25, "unit": "cm", "value": 42.1}
+//! ```
+//!
+//! `status` is one of `ok`, `out_of_range` (`value` is `null`), `error` (`value` is `null`, and
+//! `error` describes the failure) and `pending`, before the first measurement completes.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{sinks::http::DistanceEndpoint, HcSr04, Unit};
+//! use std::time::Duration;
+//!
+//! let ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//!
+//! let endpoint = DistanceEndpoint::new(Unit::Centimeters);
+//! endpoint.serve("0.0.0.0:8080").unwrap();
+//!
+//! ultrasonic
+//!     .serve_distance(Duration::from_millis(500), endpoint)
+//!     .join();
+//! ```
+
+use super::server::{self, Response};
+use crate::Unit;
+use crate::{backend::Backend, continuous::ContinuousWorker, Float, HcSr04, Measurement, Result};
+use serde_json::{json, Value};
+use std::{
+    net::ToSocketAddrs,
+    sync::{Arc, Mutex, PoisonError},
+    thread::JoinHandle,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Path the distance is served at.
+const PATH: &str = "/distance";
+
+/// Latest reading served by [`DistanceEndpoint`].
+#[derive(Debug, Default)]
+enum Reading {
+    /// No measurement completed yet.
+    #[default]
+    Pending,
+    /// Distance in m measured at the given time (`None` if no object is in range).
+    Distance(Option<Float>, SystemTime),
+    /// Latest measurement failed with the contained message.
+    Error(String, SystemTime),
+}
+
+/// Latest distance, shared between the measuring thread and the HTTP server.
+///
+/// Cloning yields a new handle to the same reading.
+///
+/// # Fields
+///
+/// - `reading`: latest reading
+/// - `unit`: unit the distance is served in
+#[derive(Debug, Clone)]
+pub struct DistanceEndpoint {
+    reading: Arc<Mutex<Reading>>,
+    unit: Unit,
+}
+
+impl DistanceEndpoint {
+    /// Create a new endpoint serving distances expressed as `unit`, with no measurement recorded.
+    pub fn new(unit: Unit) -> Self {
+        Self {
+            reading: Default::default(),
+            unit,
+        }
+    }
+
+    /// Record `measurement`, or the error it failed with, as the latest reading.
+    pub fn record(&self, measurement: &Result<Measurement>) {
+        let reading = match measurement {
+            Ok(measurement) => Reading::Distance(
+                measurement.distance.map(|distance| distance.as_meters()),
+                measurement.timestamp,
+            ),
+            Err(error) => Reading::Error(error.to_string(), SystemTime::now()),
+        };
+        *self.reading.lock().unwrap_or_else(PoisonError::into_inner) = reading;
+    }
+
+    /// Render the latest reading as a JSON object.
+    pub fn render(&self) -> String {
+        let reading = self.reading.lock().unwrap_or_else(PoisonError::into_inner);
+        let unit = self.unit.symbol();
+
+        let value = match *reading {
+            Reading::Pending => json!({
+                "value": null,
+                "unit": unit,
+                "timestamp": null,
+                "status": "pending",
+            }),
+            Reading::Distance(distance, timestamp) => json!({
+                "value": distance.map(|distance| distance * self.unit.per_meter()),
+                "unit": unit,
+                "timestamp": unix_time(timestamp),
+                "status": if distance.is_some() { "ok" } else { "out_of_range" },
+            }),
+            Reading::Error(ref message, timestamp) => json!({
+                "value": null,
+                "unit": unit,
+                "timestamp": unix_time(timestamp),
+                "status": "error",
+                "error": message,
+            }),
+        };
+        value.to_string()
+    }
+
+    /// Serve the latest reading over HTTP on `addr` from a background thread, answering
+    /// `GET /distance` requests.
+    pub fn serve(&self, addr: impl ToSocketAddrs) -> Result<JoinHandle<()>> {
+        let endpoint = self.clone();
+        server::serve(addr, move |method, path| endpoint.respond(method, path))
+    }
+
+    /// Answer the HTTP request for `path` with the given `method`.
+    fn respond(&self, method: &str, path: &str) -> Response {
+        let (status, body) = match (method, path) {
+            ("GET", PATH) => ("200 OK", self.render()),
+            (_, PATH) => ("405 Method Not Allowed", error("method not allowed")),
+            _ => ("404 Not Found", error("not found")),
+        };
+        Response {
+            status,
+            content_type: "application/json",
+            body,
+        }
+    }
+}
+
+/// JSON body describing the request `message` failed with.
+fn error(message: &str) -> String {
+    json!({ "error": message }).to_string()
+}
+
+/// Seconds elapsed between the UNIX epoch and `time`.
+fn unix_time(time: SystemTime) -> Value {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(elapsed) => json!(elapsed.as_secs_f64()),
+        Err(_) => Value::Null,
+    }
+}
+
+impl<B: Backend + Send + 'static> HcSr04<B> {
+    /// Start measuring distance every `interval` on a worker thread (see
+    /// [`HcSr04::spawn_continuous`]), recording every measurement into `endpoint`.
+    ///
+    /// The worker runs until stopped through the returned [`ContinuousWorker`].
+    pub fn serve_distance(
+        self,
+        interval: Duration,
+        endpoint: DistanceEndpoint,
+    ) -> ContinuousWorker {
+        self.spawn_continuous(interval, move |measurement| {
+            endpoint.record(measurement);
+            true
+        })
+    }
+}
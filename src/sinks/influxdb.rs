@@ -0,0 +1,404 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! InfluxDB writer, available with the `influxdb` feature.
+//!
+//! [`InfluxSink`] batches measurements into InfluxDB [line protocol] and writes them to either
+//! the InfluxDB 1.x `/write` endpoint or the InfluxDB 2.x `/api/v2/write` endpoint, tagging every
+//! point with the sensor name and the distance unit:
+//!
+//! ```text
+//! distance,sensor=garage,unit=cm distance=42.1,confidence=1,out_of_range=false 1717171717250000000
+//! ```
+//!
+//! `distance` is omitted from points of measurements with no object in range. Only plain HTTP is
+//! supported: put a TLS-terminating proxy in front of remote servers.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{sinks::influxdb::InfluxSink, HcSr04, Unit};
+//! use std::time::Duration;
+//!
+//! let ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//!
+//! let sink = InfluxSink::v2("localhost:8086", "home", "sensors", "my-token")
+//!     .sensor("garage")
+//!     .unit(Unit::Centimeters)
+//!     .batch_size(50);
+//!
+//! ultrasonic
+//!     .write_influxdb(Duration::from_secs(1), sink)
+//!     .join();
+//! ```
+//!
+//! [line protocol]: https://docs.influxdata.com/influxdb/v2/reference/syntax/line-protocol/
+
+use crate::{backend::Backend, continuous::ContinuousWorker, error::Error, HcSr04, Measurement};
+use crate::{Result, Unit};
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+/// Default name of the written points.
+const DEFAULT_MEASUREMENT: &str = "distance";
+/// Default number of points written per request.
+const DEFAULT_BATCH_SIZE: usize = 100;
+/// Default maximum time points are held before being written.
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+/// Number of batches held while the server is unreachable, before dropping the oldest points.
+const MAX_PENDING_BATCHES: usize = 10;
+/// Timeout of connecting to the server and of every read and write on the connection.
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// InfluxDB API version points are written through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Api {
+    /// InfluxDB 1.x `/write` endpoint, with optional username and password.
+    V1 {
+        database: String,
+        credentials: Option<(String, String)>,
+    },
+    /// InfluxDB 2.x `/api/v2/write` endpoint, authenticated by an API token.
+    V2 {
+        org: String,
+        bucket: String,
+        token: String,
+    },
+}
+
+/// Writer of measurements to an InfluxDB server, batching them into line protocol.
+///
+/// Points are held until either `batch_size` of them are pending or the flush interval elapsed
+/// since the previous write. Points that fail to be written are retried once the flush interval
+/// elapsed again, holding new points up to a bounded backlog meanwhile; pending points are
+/// written when the sink is dropped.
+///
+/// # Fields
+///
+/// - `addr`: `host:port` address of the server
+/// - `api`: API version and target database or bucket
+/// - `measurement`: name of the written points
+/// - `sensor`: value of the `sensor` tag of the written points
+/// - `unit`: unit distances are written in, also the value of the `unit` tag
+/// - `pending`: points not written yet, as lines of line protocol
+/// - `batch_size`: number of pending points triggering a write
+/// - `flush_interval`: maximum time points are held before being written
+/// - `last_flush`: time of the previous write
+/// - `retrying`: whether the previous write failed, deferring the next one to the flush interval
+#[derive(Debug)]
+pub struct InfluxSink {
+    addr: String,
+    api: Api,
+    measurement: String,
+    sensor: Option<String>,
+    unit: Unit,
+    pending: VecDeque<String>,
+    batch_size: usize,
+    flush_interval: Duration,
+    last_flush: Instant,
+    retrying: bool,
+}
+
+impl InfluxSink {
+    /// Create a new sink writing to `database` through the InfluxDB 1.x API of the server at
+    /// `addr` (`host:port`).
+    pub fn v1(addr: impl Into<String>, database: impl Into<String>) -> Self {
+        Self::new(
+            addr.into(),
+            Api::V1 {
+                database: database.into(),
+                credentials: None,
+            },
+        )
+    }
+
+    /// Create a new sink writing to `bucket` of `org` through the InfluxDB 2.x API of the server
+    /// at `addr` (`host:port`), authenticated by the API `token`.
+    ///
+    /// InfluxDB 3 serves the same endpoint, with the database name as `bucket`.
+    pub fn v2(
+        addr: impl Into<String>,
+        org: impl Into<String>,
+        bucket: impl Into<String>,
+        token: impl Into<String>,
+    ) -> Self {
+        Self::new(
+            addr.into(),
+            Api::V2 {
+                org: org.into(),
+                bucket: bucket.into(),
+                token: token.into(),
+            },
+        )
+    }
+
+    fn new(addr: String, api: Api) -> Self {
+        Self {
+            addr,
+            api,
+            measurement: DEFAULT_MEASUREMENT.to_string(),
+            sensor: None,
+            unit: Unit::Meters,
+            pending: VecDeque::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            last_flush: Instant::now(),
+            retrying: false,
+        }
+    }
+
+    /// Authenticate InfluxDB 1.x writes as `username` (unauthenticated by default). Ignored by
+    /// InfluxDB 2.x sinks, authenticated by their token.
+    pub fn credentials(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        if let Api::V1 { credentials, .. } = &mut self.api {
+            *credentials = Some((username.into(), password.into()));
+        }
+        self
+    }
+
+    /// Name of the written points (defaults to `distance`).
+    pub fn measurement(mut self, name: impl Into<String>) -> Self {
+        self.measurement = name.into();
+        self
+    }
+
+    /// Tag the written points with `sensor=name` (untagged by default), to tell apart the
+    /// sensors writing to the same database.
+    pub fn sensor(mut self, name: impl Into<String>) -> Self {
+        self.sensor = Some(name.into());
+        self
+    }
+
+    /// Unit distances are written in (defaults to [`Unit::Meters`]), tagging the points with
+    /// `unit=<symbol>`.
+    pub fn unit(mut self, unit: Unit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Number of pending points triggering a write (defaults to *100*). A batch size of *1* or
+    /// less writes every measurement.
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch_size = size.max(1);
+        self
+    }
+
+    /// Maximum time points are held before being written (defaults to *10s*).
+    pub fn flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// Number of points not written yet.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Line protocol representation of `measurement`, with nanosecond precision timestamp.
+    pub fn line(&self, measurement: &Measurement) -> String {
+        let mut line = escape(&self.measurement, &[',', ' ']);
+        if let Some(sensor) = &self.sensor {
+            line.push_str(",sensor=");
+            line.push_str(&escape(sensor, &[',', '=', ' ']));
+        }
+        line.push_str(",unit=");
+        line.push_str(self.unit.symbol());
+
+        line.push(' ');
+        if let Some(distance) = measurement.distance {
+            let _ = write!(line, "distance={},", distance.as_unit(self.unit));
+        }
+        let _ = write!(
+            line,
+            "confidence={},out_of_range={} {}",
+            measurement.confidence,
+            measurement.distance.is_none(),
+            measurement
+                .timestamp
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos()
+        );
+
+        line
+    }
+
+    /// Queue `measurement` for writing, writing the pending points if the batch is full or the
+    /// flush interval elapsed. After a failed write, the batch being full no longer triggers
+    /// writes until the flush interval elapsed, so an unreachable server doesn't stall every
+    /// measurement.
+    pub fn write(&mut self, measurement: &Measurement) -> Result<()> {
+        let line = self.line(measurement);
+        self.pending.push_back(line);
+
+        // Bound the backlog while the server is unreachable.
+        let capacity = self.batch_size.saturating_mul(MAX_PENDING_BATCHES);
+        while self.pending.len() > capacity {
+            self.pending.pop_front();
+        }
+
+        let full = self.pending.len() >= self.batch_size && !self.retrying;
+        if full || self.last_flush.elapsed() >= self.flush_interval {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write the pending points, keeping them for the next write on failure.
+    pub fn flush(&mut self) -> Result<()> {
+        self.last_flush = Instant::now();
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut body = String::new();
+        for line in &self.pending {
+            body.push_str(line);
+            body.push('\n');
+        }
+        let result = self.post(&body);
+        self.retrying = result.is_err();
+        result?;
+
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Request path and query of the write endpoint.
+    fn path(&self) -> String {
+        match &self.api {
+            Api::V1 {
+                database,
+                credentials,
+            } => {
+                let mut path = format!("/write?db={}&precision=ns", encode(database));
+                if let Some((username, password)) = credentials {
+                    let _ = write!(path, "&u={}&p={}", encode(username), encode(password));
+                }
+                path
+            }
+            Api::V2 { org, bucket, .. } => format!(
+                "/api/v2/write?org={}&bucket={}&precision=ns",
+                encode(org),
+                encode(bucket)
+            ),
+        }
+    }
+
+    /// Send `body` to the write endpoint, failing unless the server answers with a 2xx status.
+    fn post(&self, body: &str) -> Result<()> {
+        let mut stream = connect(&self.addr)?;
+
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\
+             Content-Length: {}\r\nConnection: close\r\n",
+            self.path(),
+            self.addr,
+            body.len()
+        );
+        if let Api::V2 { token, .. } = &self.api {
+            let _ = write!(request, "Authorization: Token {}\r\n", token);
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes())?;
+        stream.write_all(body.as_bytes())?;
+
+        let mut status = String::new();
+        BufReader::new(stream).read_line(&mut status)?;
+        let code = status
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP response"))?;
+
+        match code {
+            200..=299 => Ok(()),
+            _ => Err(Error::Influx(code)),
+        }
+    }
+}
+
+impl Drop for InfluxSink {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Connect to `addr`, trying every address it resolves to.
+fn connect(addr: &str) -> io::Result<TcpStream> {
+    let mut last_error = None;
+    for addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, IO_TIMEOUT) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(IO_TIMEOUT))?;
+                stream.set_write_timeout(Some(IO_TIMEOUT))?;
+                return Ok(stream);
+            }
+            Err(error) => last_error = Some(error),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "address resolved to nothing")
+    }))
+}
+
+/// Escape `special` characters of a line protocol identifier with backslashes.
+fn escape(value: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Percent-encode `value` for use in a URL query.
+fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => {
+                let _ = write!(encoded, "%{:02X}", byte);
+            }
+        }
+    }
+    encoded
+}
+
+impl<B: Backend + Send + 'static> HcSr04<B> {
+    /// Start measuring distance every `interval` on a worker thread (see
+    /// [`HcSr04::spawn_continuous`]), writing every measurement through `sink`.
+    ///
+    /// Failed measurements are skipped, while points failing to be written are retried after the
+    /// flush interval. The worker runs until stopped through the returned [`ContinuousWorker`],
+    /// writing the pending points as it stops.
+    pub fn write_influxdb(self, interval: Duration, mut sink: InfluxSink) -> ContinuousWorker {
+        self.spawn_continuous(interval, move |measurement| {
+            if let Ok(measurement) = measurement {
+                let _ = sink.write(measurement);
+            }
+            true
+        })
+    }
+}
@@ -0,0 +1,308 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! MQTT publisher, available with the `mqtt` feature.
+//!
+//! [`MqttSink`] publishes measurements as JSON payloads to a topic of an MQTT broker, e.g. to
+//! feed home-automation systems. [`HcSr04::publish_mqtt`] publishes measurements taken at a fixed
+//! interval from a worker thread.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{
+//!     sinks::mqtt::{MqttOptions, MqttSink, QoS},
+//!     HcSr04,
+//! };
+//! use std::time::Duration;
+//!
+//! let ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//!
+//! let sink = MqttSink::connect(
+//!     MqttOptions::new("hc-sr04", "localhost", 1883),
+//!     "home/garage/distance",
+//! )
+//! .qos(QoS::AtLeastOnce);
+//!
+//! ultrasonic
+//!     .publish_mqtt(Duration::from_secs(1), sink)
+//!     .join();
+//! ```
+//!
+//! If the broker feeds [Home Assistant](https://www.home-assistant.io), the sensor can be
+//! announced through [MQTT discovery] so that it automatically shows up as a distance entity (see
+//! [`MqttSink::announce`]):
+//!
+//! ```rust,no_run
+//! use hc_sr04::{
+//!     sinks::mqtt::{Discovery, MqttOptions, MqttSink},
+//!     Unit,
+//! };
+//!
+//! let sink = MqttSink::connect(
+//!     MqttOptions::new("hc-sr04", "localhost", 1883),
+//!     "home/garage/distance",
+//! );
+//!
+//! sink.announce(
+//!     &Discovery::new("garage_distance", "Garage distance")
+//!         .unit(Unit::Centimeters)
+//!         .device("Garage parking sensor"),
+//! )
+//! .unwrap();
+//! ```
+//!
+//! [MQTT discovery]: https://www.home-assistant.io/integrations/mqtt/#mqtt-discovery
+
+use crate::{backend::Backend, continuous::ContinuousWorker, error::Error, HcSr04, Measurement};
+use crate::{Result, Unit};
+pub use rumqttc::{Client, MqttOptions, QoS};
+use rumqttc::{Connection, ConnectionError};
+use serde_json::json;
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Capacity of the MQTT client requests queue.
+const REQUESTS_CAPACITY: usize = 10;
+/// Default Home Assistant discovery topic prefix.
+const DISCOVERY_PREFIX: &str = "homeassistant";
+/// Delay before reconnecting to the broker after a connection error.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Publisher of measurements to a topic of an MQTT broker.
+///
+/// Measurements are serialized as JSON objects (see [`Measurement`]).
+///
+/// # Fields
+///
+/// - `client`: MQTT client
+/// - `topic`: topic measurements are published to
+/// - `qos`: quality of service of published messages
+/// - `retain`: whether the broker retains the latest published message
+/// - `dropped`: number of measurements dropped because the requests queue was full, shared
+///   between clones
+#[derive(Clone)]
+pub struct MqttSink {
+    client: Client,
+    topic: String,
+    qos: QoS,
+    retain: bool,
+    dropped: Arc<AtomicU64>,
+}
+
+impl MqttSink {
+    /// Create a new sink publishing to `topic` through `client`, with [`QoS::AtMostOnce`] and no
+    /// retained messages.
+    ///
+    /// The [`Connection`] paired with `client` must be polled for messages to be actually sent.
+    pub fn new(client: Client, topic: impl Into<String>) -> Self {
+        Self {
+            client,
+            topic: topic.into(),
+            qos: QoS::AtMostOnce,
+            retain: false,
+            dropped: Default::default(),
+        }
+    }
+
+    /// Connect to the broker described by `options`, publishing to `topic`.
+    ///
+    /// The connection is polled on a background thread, reconnecting after errors, until the sink
+    /// and all its clones are dropped.
+    pub fn connect(options: MqttOptions, topic: impl Into<String>) -> Self {
+        let (client, connection) = Client::new(options, REQUESTS_CAPACITY);
+        thread::spawn(move || poll(connection));
+
+        Self::new(client, topic)
+    }
+
+    /// Quality of service of published messages (defaults to [`QoS::AtMostOnce`]).
+    pub fn qos(mut self, qos: QoS) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    /// Whether the broker retains the latest published message, delivering it to new subscribers
+    /// (disabled by default).
+    pub fn retain(mut self, retain: bool) -> Self {
+        self.retain = retain;
+        self
+    }
+
+    /// Topic measurements are published to.
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Announce the sensor to Home Assistant through MQTT `discovery`, as a distance entity
+    /// reading measurements from the topic of the sink.
+    ///
+    /// The configuration message is retained by the broker, so that Home Assistant discovers the
+    /// sensor even after restarting.
+    pub fn announce(&self, discovery: &Discovery) -> Result<()> {
+        let payload =
+            serde_json::to_vec(&discovery.config(&self.topic)).map_err(Error::Serialize)?;
+        Ok(self
+            .client
+            .publish(discovery.topic(), self.qos, true, payload)?)
+    }
+
+    /// Publish `measurement` without blocking, dropping it (and failing) if the client requests
+    /// queue is full, e.g. while the broker is unreachable.
+    pub fn publish(&self, measurement: &Measurement) -> Result<()> {
+        let payload = serde_json::to_vec(measurement).map_err(Error::Serialize)?;
+        self.client
+            .try_publish(self.topic.as_str(), self.qos, self.retain, payload)
+            .map_err(|error| {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                error.into()
+            })
+    }
+
+    /// Number of measurements dropped so far by this sink and its clones, because the client
+    /// requests queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl fmt::Debug for MqttSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MqttSink")
+            .field("topic", &self.topic)
+            .field("qos", &self.qos)
+            .field("retain", &self.retain)
+            .field("dropped", &self.dropped())
+            .finish()
+    }
+}
+
+/// Home Assistant MQTT discovery configuration, announcing the sensor as a distance entity.
+///
+/// # Fields
+///
+/// - `prefix`: discovery topic prefix
+/// - `object_id`: entity identifier, unique among the entities of the broker
+/// - `name`: entity name
+/// - `unit`: unit distances are displayed in
+/// - `device`: name of the device the entity belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discovery {
+    prefix: String,
+    object_id: String,
+    name: String,
+    unit: Unit,
+    device: Option<String>,
+}
+
+impl Discovery {
+    /// Create a new discovery configuration for the entity `name` identified by `object_id`,
+    /// with the default `homeassistant` topic prefix, displaying distances in meters.
+    pub fn new(object_id: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            prefix: DISCOVERY_PREFIX.to_string(),
+            object_id: object_id.into(),
+            name: name.into(),
+            unit: Unit::Meters,
+            device: None,
+        }
+    }
+
+    /// Discovery topic prefix, as configured in Home Assistant (defaults to `homeassistant`).
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Unit distances are displayed in (defaults to [`Unit::Meters`]).
+    pub fn unit(mut self, unit: Unit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Group the entity under the device `name` (by default the entity belongs to no device).
+    pub fn device(mut self, name: impl Into<String>) -> Self {
+        self.device = Some(name.into());
+        self
+    }
+
+    /// Topic the discovery configuration is published to.
+    pub fn topic(&self) -> String {
+        format!("{}/sensor/{}/config", self.prefix, self.object_id)
+    }
+
+    /// Discovery configuration payload for the entity reading measurements from `state_topic`.
+    fn config(&self, state_topic: &str) -> serde_json::Value {
+        // Measurements carry distances in meters.
+        let value_template = format!(
+            "{{{{ value_json.distance * {} if value_json.distance is not none else none }}}}",
+            self.unit.per_meter()
+        );
+
+        let mut config = json!({
+            "name": self.name,
+            "unique_id": self.object_id,
+            "state_topic": state_topic,
+            "device_class": "distance",
+            "state_class": "measurement",
+            "unit_of_measurement": self.unit.symbol(),
+            "value_template": value_template,
+        });
+        if let Some(device) = &self.device {
+            config["device"] = json!({
+                "identifiers": [self.object_id],
+                "name": device,
+            });
+        }
+
+        config
+    }
+}
+
+/// Poll `connection` until the requests channel is closed, i.e. all clients are dropped.
+fn poll(mut connection: Connection) {
+    for notification in connection.iter() {
+        match notification {
+            Ok(_) => {}
+            Err(ConnectionError::RequestsDone) => break,
+            // Reconnect on next iteration.
+            Err(_) => thread::sleep(RECONNECT_DELAY),
+        }
+    }
+}
+
+impl<B: Backend + Send + 'static> HcSr04<B> {
+    /// Start measuring distance every `interval` on a worker thread (see
+    /// [`HcSr04::spawn_continuous`]), publishing every measurement through `sink`.
+    ///
+    /// Failed measurements and publications are skipped. Measurements are dropped rather than
+    /// queued while the broker is unreachable and the client requests queue is full (see
+    /// [`MqttSink::dropped`]), so that sampling never stalls and the worker can always be stopped.
+    /// The worker runs until stopped through the returned [`ContinuousWorker`].
+    pub fn publish_mqtt(self, interval: Duration, sink: MqttSink) -> ContinuousWorker {
+        self.spawn_continuous(interval, move |measurement| {
+            if let Ok(measurement) = measurement {
+                let _ = sink.publish(measurement);
+            }
+            true
+        })
+    }
+}
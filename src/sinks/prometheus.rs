@@ -0,0 +1,230 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Prometheus metrics exporter, available with the `prometheus` feature.
+//!
+//! [`Metrics`] collects sensor readings and health statistics, served over HTTP in the
+//! Prometheus text exposition format so that fleet deployments can scrape them:
+//!
+//! - `hc_sr04_distance_meters`: latest measured distance (`NaN` if no object is in range)
+//! - `hc_sr04_measurements_total`: performed measurements
+//! - `hc_sr04_out_of_range_total`: measurements with no object in range
+//! - `hc_sr04_timeouts_total`: **ECHO** pulse timeouts, labelled by `kind` (`start` or `end`)
+//! - `hc_sr04_errors_total`: failed measurements, timeouts included
+//! - `hc_sr04_measurement_latency_seconds`: histogram of the time taken by measurements
+//!
+//! ```rust,no_run
+//! use hc_sr04::{sinks::prometheus::Metrics, HcSr04};
+//! use std::time::Duration;
+//!
+//! let ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//!
+//! let metrics = Metrics::new();
+//! metrics.serve("0.0.0.0:9184").unwrap();
+//!
+//! ultrasonic
+//!     .export_metrics(Duration::from_secs(1), metrics)
+//!     .join();
+//! ```
+
+use super::server::{self, Response};
+use crate::{backend::Backend, continuous::ContinuousWorker, error::Error, Float, HcSr04};
+use crate::{Measurement, Result};
+use std::{
+    fmt::{self, Write as _},
+    net::ToSocketAddrs,
+    sync::{Arc, Mutex, PoisonError},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+/// Upper bounds of the measurement latency histogram buckets in s.
+const LATENCY_BUCKETS: [f64; 8] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25];
+
+/// Statistics collected by [`Metrics`].
+///
+/// # Fields
+///
+/// - `distance`: latest measured distance in m (`None` if no object is in range)
+/// - `measured`: whether any measurement succeeded yet
+/// - `measurements`: number of performed measurements
+/// - `out_of_range`: number of measurements with no object in range
+/// - `start_timeouts`: number of **ECHO** pulse start timeouts
+/// - `pulse_timeouts`: number of **ECHO** pulse end timeouts
+/// - `errors`: number of failed measurements
+/// - `latency_buckets`: number of measurements per latency histogram bucket (not cumulative)
+/// - `latency_sum`: total time taken by measurements in s
+#[derive(Debug, Default)]
+struct State {
+    distance: Option<Float>,
+    measured: bool,
+    measurements: u64,
+    out_of_range: u64,
+    start_timeouts: u64,
+    pulse_timeouts: u64,
+    errors: u64,
+    latency_buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    latency_sum: f64,
+}
+
+impl State {
+    /// Render the statistics to `out` in the Prometheus text exposition format.
+    fn render(&self, out: &mut String) -> fmt::Result {
+        writeln!(
+            out,
+            "# HELP hc_sr04_distance_meters Latest measured distance."
+        )?;
+        writeln!(out, "# TYPE hc_sr04_distance_meters gauge")?;
+        if self.measured {
+            let distance = self.distance.unwrap_or(Float::NAN);
+            writeln!(out, "hc_sr04_distance_meters {}", distance)?;
+        }
+
+        counter(
+            out,
+            "hc_sr04_measurements_total",
+            "Performed measurements.",
+            self.measurements,
+        )?;
+        counter(
+            out,
+            "hc_sr04_out_of_range_total",
+            "Measurements with no object in range.",
+            self.out_of_range,
+        )?;
+
+        writeln!(out, "# HELP hc_sr04_timeouts_total ECHO pulse timeouts.")?;
+        writeln!(out, "# TYPE hc_sr04_timeouts_total counter")?;
+        writeln!(
+            out,
+            "hc_sr04_timeouts_total{{kind=\"start\"}} {}",
+            self.start_timeouts
+        )?;
+        writeln!(
+            out,
+            "hc_sr04_timeouts_total{{kind=\"end\"}} {}",
+            self.pulse_timeouts
+        )?;
+
+        counter(
+            out,
+            "hc_sr04_errors_total",
+            "Failed measurements.",
+            self.errors,
+        )?;
+
+        let name = "hc_sr04_measurement_latency_seconds";
+        writeln!(out, "# HELP {} Time taken by measurements.", name)?;
+        writeln!(out, "# TYPE {} histogram", name)?;
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(&self.latency_buckets) {
+            cumulative += count;
+            writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulative)?;
+        }
+        writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, self.measurements)?;
+        writeln!(out, "{}_sum {}", name, self.latency_sum)?;
+        writeln!(out, "{}_count {}", name, self.measurements)
+    }
+}
+
+/// Sensor metrics, shared between the measuring thread and the HTTP exporter.
+///
+/// Cloning yields a new handle to the same metrics.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    state: Arc<Mutex<State>>,
+}
+
+impl Metrics {
+    /// Create a new set of metrics, with no measurement recorded.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `measurement`, or the error it failed with, which took `latency` to perform.
+    pub fn record(&self, measurement: &Result<Measurement>, latency: Duration) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        state.measurements += 1;
+        match measurement {
+            Ok(measurement) => {
+                state.measured = true;
+                state.distance = measurement.distance.map(|distance| distance.as_meters());
+                if measurement.distance.is_none() {
+                    state.out_of_range += 1;
+                }
+            }
+            Err(error) => {
+                state.errors += 1;
+                match error {
+                    Error::EchoStartTimeout => state.start_timeouts += 1,
+                    Error::EchoPulseTimeout => state.pulse_timeouts += 1,
+                    _ => {}
+                }
+            }
+        }
+
+        let latency = latency.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| latency <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        state.latency_buckets[bucket] += 1;
+        state.latency_sum += latency;
+    }
+
+    /// Render the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut out = String::new();
+
+        // Writing to a `String` never fails.
+        let _ = state.render(&mut out);
+        out
+    }
+
+    /// Serve the metrics over HTTP on `addr` from a background thread, answering every request
+    /// with the rendered metrics.
+    pub fn serve(&self, addr: impl ToSocketAddrs) -> Result<JoinHandle<()>> {
+        let metrics = self.clone();
+        server::serve(addr, move |_, _| Response {
+            status: "200 OK",
+            content_type: "text/plain; version=0.0.4",
+            body: metrics.render(),
+        })
+    }
+}
+
+/// Write the counter `name` with its `help` text and `value` to `out`.
+fn counter(out: &mut String, name: &str, help: &str, value: u64) -> fmt::Result {
+    writeln!(out, "# HELP {} {}", name, help)?;
+    writeln!(out, "# TYPE {} counter", name)?;
+    writeln!(out, "{} {}", name, value)
+}
+
+impl<B: Backend + Send + 'static> HcSr04<B> {
+    /// Start measuring distance every `interval` on a worker thread (see
+    /// [`HcSr04::spawn_continuous`]), recording every measurement and its latency into `metrics`.
+    ///
+    /// The worker runs until stopped through the returned [`ContinuousWorker`]. If a measurement
+    /// takes longer than `interval`, the next one starts right away.
+    pub fn export_metrics(self, interval: Duration, metrics: Metrics) -> ContinuousWorker {
+        self.spawn_worker(interval, move |measurement, latency| {
+            metrics.record(measurement, latency);
+            true
+        })
+    }
+}
@@ -0,0 +1,235 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! ROS 2 publisher, available with the `ros2` feature.
+//!
+//! [`RangePublisher`] publishes measurements as [`sensor_msgs/Range`] messages on a topic of a
+//! ROS 2 graph, so that robots can consume the sensor straight from their navigation stack,
+//! without a bridge node. Messages are exchanged over DDS by [ros2-client], a native Rust client
+//! library: no ROS 2 installation is needed on the Raspberry Pi.
+//!
+//! Messages follow [REP 117]: `range` is `+Inf` when no object is in range and `-Inf` when the
+//! object is closer than the minimum measuring range, while `min_range` and `max_range` carry the
+//! measuring range of the sensor.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{
+//!     sinks::ros2::{Context, NodeName, NodeOptions, RangePublisher},
+//!     HcSr04,
+//! };
+//! use std::time::Duration;
+//!
+//! let ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//!
+//! let context = Context::new().unwrap();
+//! let mut node = context
+//!     .new_node(NodeName::new("/robot", "ultrasonic").unwrap(), NodeOptions::new())
+//!     .unwrap();
+//! let publisher = RangePublisher::new(&mut node, "front/range", "front_ultrasonic").unwrap();
+//!
+//! ultrasonic
+//!     .publish_ros2(Duration::from_millis(100), publisher)
+//!     .join();
+//! ```
+//!
+//! [`sensor_msgs/Range`]: https://docs.ros2.org/latest/api/sensor_msgs/msg/Range.html
+//! [ros2-client]: https://docs.rs/ros2-client
+//! [REP 117]: https://www.ros.org/reps/rep-0117.html
+
+use crate::{backend::Backend, continuous::ContinuousWorker, error::Error, Float, HcSr04};
+use crate::{Measurement, Result};
+use ros2_client::{
+    builtin_interfaces::Time,
+    pubsub::Publisher,
+    qos::{Durability, History},
+    Message, MessageTypeName, Name, QosProfile,
+};
+pub use ros2_client::{Context, Node, NodeName, NodeOptions};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+/// Default field of view of the sensor, i.e. the *15°* measuring angle of the HC-SR04, in rad.
+const DEFAULT_FIELD_OF_VIEW: f32 = 0.2618;
+/// Depth of the publisher history, as in the ROS 2 sensor data QoS profile.
+const HISTORY_DEPTH: usize = 5;
+
+/// `radiation_type` of `sensor_msgs/Range` messages from ultrasonic sensors.
+pub const ULTRASOUND: u8 = 0;
+
+/// `std_msgs/Header` message.
+///
+/// # Fields
+///
+/// - `stamp`: acquisition time of the data
+/// - `frame_id`: coordinate frame the data is associated with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Header {
+    pub stamp: Time,
+    pub frame_id: String,
+}
+
+/// `sensor_msgs/Range` message.
+///
+/// # Fields
+///
+/// - `header`: measurement time and frame of the sensor (pointing along its x axis)
+/// - `radiation_type`: type of radiation used by the sensor ([`ULTRASOUND`])
+/// - `field_of_view`: size of the arc the distance reading is valid for, in rad
+/// - `min_range`: minimum measuring range in m
+/// - `max_range`: maximum measuring range in m
+/// - `range`: measured distance in m (`+Inf` if no object is in range, `-Inf` if it is closer
+///   than `min_range`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Range {
+    pub header: Header,
+    pub radiation_type: u8,
+    pub field_of_view: f32,
+    pub min_range: f32,
+    pub max_range: f32,
+    pub range: f32,
+}
+
+impl Message for Range {}
+
+/// Publisher of measurements as `sensor_msgs/Range` messages on a ROS 2 topic.
+///
+/// # Fields
+///
+/// - `publisher`: publisher to the topic
+/// - `frame_id`: coordinate frame of the sensor
+/// - `field_of_view`: field of view of the sensor in rad
+/// - `min_range`: minimum measuring range in m
+/// - `max_range`: maximum measuring range in m
+pub struct RangePublisher {
+    publisher: Publisher<Range>,
+    frame_id: String,
+    field_of_view: f32,
+    min_range: f32,
+    max_range: f32,
+}
+
+impl RangePublisher {
+    /// Create a new publisher of `sensor_msgs/Range` messages from the sensor in `frame_id` to
+    /// `topic` (absolute or relative to the namespace of `node`), with the sensor data QoS
+    /// profile (best effort, keeping the last 5 messages).
+    ///
+    /// The field of view defaults to the *15°* of the HC-SR04 and the measuring range to *2cm* to
+    /// *4m*, until replaced by the range of the sensor when publishing through
+    /// [`HcSr04::publish_ros2`].
+    pub fn new(node: &mut Node, topic: &str, frame_id: impl Into<String>) -> Result<Self> {
+        let qos = QosProfile::publisher_default()
+            .reliability_best_effort()
+            .durability(Durability::Volatile)
+            .history(History::KeepLast {
+                depth: HISTORY_DEPTH,
+            });
+
+        let name = Name::parse(topic).map_err(|error| Error::Ros2(error.to_string()))?;
+        let topic = node
+            .create_topic(&name, MessageTypeName::new("sensor_msgs", "Range"), &qos)
+            .map_err(|error| Error::Ros2(error.to_string()))?;
+        let publisher = node
+            .create_publisher(&topic, None)
+            .map_err(|error| Error::Ros2(error.to_string()))?;
+
+        Ok(Self {
+            publisher,
+            frame_id: frame_id.into(),
+            field_of_view: DEFAULT_FIELD_OF_VIEW,
+            min_range: 0.02,
+            max_range: 4.,
+        })
+    }
+
+    /// Field of view of the sensor in rad (defaults to *0.26rad*, i.e. *15°*), e.g. widened for
+    /// the JSN-SR04T.
+    pub fn field_of_view(mut self, radians: f32) -> Self {
+        self.field_of_view = radians;
+        self
+    }
+
+    /// Measuring range of the sensor in m, reported in published messages.
+    pub fn range(mut self, min: f32, max: f32) -> Self {
+        self.min_range = min;
+        self.max_range = max;
+        self
+    }
+
+    /// Message reporting `measurement`.
+    pub fn message(&self, measurement: &Measurement) -> Range {
+        let range = match measurement.distance {
+            Some(distance) => single(distance.as_meters()),
+            None => f32::INFINITY,
+        };
+        self.range_message(range, measurement.timestamp)
+    }
+
+    /// Message reporting `range` in m, measured at `timestamp`.
+    fn range_message(&self, range: f32, timestamp: SystemTime) -> Range {
+        Range {
+            header: Header {
+                stamp: Time::try_from(timestamp).unwrap_or(Time::ZERO),
+                frame_id: self.frame_id.clone(),
+            },
+            radiation_type: ULTRASOUND,
+            field_of_view: self.field_of_view,
+            min_range: self.min_range,
+            max_range: self.max_range,
+            range,
+        }
+    }
+
+    /// Publish `measurement`.
+    pub fn publish(&self, measurement: &Measurement) -> Result<()> {
+        self.send(self.message(measurement))
+    }
+
+    fn send(&self, message: Range) -> Result<()> {
+        self.publisher
+            .publish(message)
+            .map_err(|error| Error::Ros2(error.to_string()))
+    }
+}
+
+/// `value` in the single precision of ROS messages.
+// `Float` is already `f32` without the `f64` feature.
+#[allow(clippy::unnecessary_cast)]
+fn single(value: Float) -> f32 {
+    value as f32
+}
+
+impl<B: Backend + Send + 'static> HcSr04<B> {
+    /// Start measuring distance every `interval` on a worker thread (see
+    /// [`HcSr04::spawn_continuous`]), publishing every measurement through `publisher`,
+    /// with the measuring range of the sensor.
+    ///
+    /// Objects closer than the minimum measuring range are published as `-Inf` (see
+    /// [`Error::TooClose`]), while other failed measurements and publications are skipped. The
+    /// worker runs until stopped through the returned [`ContinuousWorker`].
+    pub fn publish_ros2(self, interval: Duration, publisher: RangePublisher) -> ContinuousWorker {
+        let publisher = publisher.range(single(self.min_range()), single(self.max_range()));
+
+        self.spawn_continuous(interval, move |measurement| {
+            let _ = match measurement {
+                Ok(measurement) => publisher.publish(measurement),
+                Err(Error::TooClose) => {
+                    publisher.send(publisher.range_message(f32::NEG_INFINITY, SystemTime::now()))
+                }
+                Err(_) => Ok(()),
+            };
+            true
+        })
+    }
+}
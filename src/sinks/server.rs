@@ -0,0 +1,108 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Minimal HTTP server shared by the `http` and `prometheus` sinks.
+
+use crate::Result;
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// Maximum time a client may take to send its request, or stall the response, before being
+/// disconnected.
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+/// Maximum size of a request line and headers in bytes.
+const MAX_REQUEST: u64 = 8192;
+
+/// Response to an HTTP request.
+///
+/// # Fields
+///
+/// - `status`: status code and reason phrase
+/// - `content_type`: media type of the body
+/// - `body`: response body
+#[derive(Debug)]
+pub(crate) struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+/// Serve HTTP requests on `addr` from a background thread, one connection at a time, answering
+/// each with the response `respond` returns given the request method and path (without query).
+pub(crate) fn serve<F>(addr: impl ToSocketAddrs, respond: F) -> Result<JoinHandle<()>>
+where
+    F: Fn(&str, &str) -> Response + Send + 'static,
+{
+    let listener = TcpListener::bind(addr)?;
+
+    Ok(thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // Clients retry on failure: ignore broken and stalled connections.
+            let _ = handle(stream, &respond);
+        }
+    }))
+}
+
+/// Read the HTTP request from `stream` and write back the response `respond` returns.
+fn handle(mut stream: TcpStream, respond: impl Fn(&str, &str) -> Response) -> io::Result<()> {
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    let deadline = Instant::now() + IO_TIMEOUT;
+    let mut reader = BufReader::new(stream.try_clone()?.take(MAX_REQUEST));
+    let mut read_line = |line: &mut String| {
+        let remaining = deadline
+            .checked_duration_since(Instant::now())
+            .filter(|remaining| !remaining.is_zero())
+            .ok_or(io::ErrorKind::TimedOut)?;
+        reader
+            .get_ref()
+            .get_ref()
+            .set_read_timeout(Some(remaining))?;
+        reader.read_line(line)
+    };
+
+    let mut request = String::new();
+    read_line(&mut request)?;
+    // Skip the request headers, up to the empty line.
+    let mut line = String::new();
+    while read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let mut parts = request.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+    let path = path.split('?').next().unwrap_or_default();
+
+    let response = respond(method, path);
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\n\
+         Content-Type: {}\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\
+         \r\n\
+         {}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    )
+}
@@ -0,0 +1,140 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! WebSocket streaming server, available with the `websocket` feature.
+//!
+//! [`WebSocketServer`] pushes every measurement as a JSON object (see [`Measurement`]) to the
+//! WebSocket clients connected to it, so that browser dashboards display live distance without
+//! polling:
+//!
+//! ```js
+//! const socket = new WebSocket("ws://raspberrypi.local:9001");
+//! socket.onmessage = (event) => console.log(JSON.parse(event.data).distance);
+//! ```
+//!
+//! ```rust,no_run
+//! use hc_sr04::{sinks::websocket::WebSocketServer, HcSr04};
+//! use std::time::Duration;
+//!
+//! let ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//!
+//! let server = WebSocketServer::new();
+//! server.serve("0.0.0.0:9001").unwrap();
+//!
+//! ultrasonic
+//!     .stream_websocket(Duration::from_millis(100), server)
+//!     .join();
+//! ```
+
+use crate::Result;
+use crate::{backend::Backend, continuous::ContinuousWorker, error::Error, HcSr04, Measurement};
+use std::{
+    fmt,
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use tungstenite::{Message, WebSocket};
+
+/// Maximum time a client may take to complete the WebSocket handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Maximum time a client may stall a message, before being disconnected.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Broadcaster of measurements to the WebSocket clients connected to its server.
+///
+/// Cloning yields a new handle to the same set of clients.
+///
+/// # Fields
+///
+/// - `clients`: connected clients
+#[derive(Clone, Default)]
+pub struct WebSocketServer {
+    clients: Arc<Mutex<Vec<WebSocket<TcpStream>>>>,
+}
+
+impl WebSocketServer {
+    /// Create a new server, with no client connected.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept WebSocket connections on `addr`, on any path, from a background thread.
+    pub fn serve(&self, addr: impl ToSocketAddrs) -> Result<JoinHandle<()>> {
+        let listener = TcpListener::bind(addr)?;
+        let server = self.clone();
+
+        Ok(thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // Clients reconnect on failure: ignore failed handshakes.
+                let _ = server.accept(stream);
+            }
+        }))
+    }
+
+    /// Perform the WebSocket handshake on `stream`, adding the client on success.
+    fn accept(&self, stream: TcpStream) -> std::io::Result<()> {
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        if let Ok(client) = tungstenite::accept(stream) {
+            self.lock().push(client);
+        }
+        Ok(())
+    }
+
+    /// Number of connected clients.
+    pub fn clients(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Push `measurement` to every connected client, disconnecting the ones failing to receive
+    /// it (e.g. once the browser tab is closed).
+    pub fn broadcast(&self, measurement: &Measurement) -> Result<()> {
+        let payload = serde_json::to_string(measurement).map_err(Error::Serialize)?;
+        self.lock()
+            .retain_mut(|client| client.send(Message::text(payload.clone())).is_ok());
+        Ok(())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<WebSocket<TcpStream>>> {
+        self.clients.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl fmt::Debug for WebSocketServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebSocketServer")
+            .field("clients", &self.clients())
+            .finish()
+    }
+}
+
+impl<B: Backend + Send + 'static> HcSr04<B> {
+    /// Start measuring distance every `interval` on a worker thread (see
+    /// [`HcSr04::spawn_continuous`]), pushing every measurement to the clients of `server`.
+    ///
+    /// Failed measurements are skipped. The worker runs until stopped through the returned
+    /// [`ContinuousWorker`].
+    pub fn stream_websocket(self, interval: Duration, server: WebSocketServer) -> ContinuousWorker {
+        self.spawn_continuous(interval, move |measurement| {
+            if let Ok(measurement) = measurement {
+                let _ = server.broadcast(measurement);
+            }
+            true
+        })
+    }
+}
@@ -0,0 +1,319 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Statistics of measured distances.
+//!
+//! [`Stats`] accumulates the latest distances it is fed, e.g. by continuous measurement, and
+//! exposes their count, mean, extremes and standard deviation, so that consumers such as
+//! monitoring dashboards don't need to recompute them.
+//!
+//! [`Histogram`] counts distances falling into configurable buckets over time, e.g. to study
+//! occupancy or to tune zone boundaries (see the [`zone`](crate::zone) module) from real data.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{stats::Stats, HcSr04};
+//! use std::{
+//!     sync::{Arc, Mutex},
+//!     thread,
+//!     time::Duration,
+//! };
+//!
+//! let ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//! let stats = Arc::new(Mutex::new(Stats::new(50)));
+//!
+//! let feed = Arc::clone(&stats);
+//! let _worker = ultrasonic.start_continuous_with(Duration::from_millis(100), move |measurement| {
+//!     if let Ok(measurement) = measurement {
+//!         feed.lock().unwrap().update(&measurement);
+//!     }
+//!     true
+//! });
+//!
+//! loop {
+//!     thread::sleep(Duration::from_secs(5));
+//!     let stats = stats.lock().unwrap();
+//!     println!("{:?} ± {:?}", stats.mean(), stats.std_dev());
+//! }
+//! ```
+
+use crate::{Distance, Float, Measurement};
+use std::{collections::VecDeque, mem};
+
+/// Statistics over a sliding window of the latest measured distances.
+///
+/// Measurements with no object in range are not part of the window.
+///
+/// # Fields
+///
+/// - `size`: window size
+/// - `window`: latest distances in m
+///
+/// ```rust
+/// use hc_sr04::{stats::Stats, Distance};
+///
+/// let mut stats = Stats::new(3);
+/// for meters in [4., 1., 2., 3.] {
+///     stats.push(Distance::from_meters(meters));
+/// }
+///
+/// assert_eq!(stats.count(), 3);
+/// assert_eq!(stats.mean(), Some(Distance::from_meters(2.)));
+/// assert_eq!(stats.min(), Some(Distance::from_meters(1.)));
+/// assert_eq!(stats.max(), Some(Distance::from_meters(3.)));
+/// assert!((stats.std_dev().unwrap().as_meters() - 0.8165).abs() < 1e-4);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    size: usize,
+    window: VecDeque<Float>,
+}
+
+impl Stats {
+    /// Create new statistics over the latest `size` distances (at least one).
+    pub fn new(size: usize) -> Self {
+        let size = size.max(1);
+        Self {
+            size,
+            window: VecDeque::with_capacity(size),
+        }
+    }
+
+    /// Feed `measurement`, ignoring it if no object is in range.
+    pub fn update(&mut self, measurement: &Measurement) {
+        if let Some(distance) = measurement.distance {
+            self.push(distance);
+        }
+    }
+
+    /// Feed `distance`, discarding the oldest one if the window is full.
+    pub fn push(&mut self, distance: Distance) {
+        if self.window.len() == self.size {
+            self.window.pop_front();
+        }
+        self.window.push_back(distance.as_meters());
+    }
+
+    /// Window size.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Number of distances in the window.
+    pub fn count(&self) -> usize {
+        self.window.len()
+    }
+
+    /// Mean distance over the window, `None` if empty.
+    pub fn mean(&self) -> Option<Distance> {
+        self.mean_meters().map(Distance::from_meters)
+    }
+
+    /// Shortest distance in the window, `None` if empty.
+    pub fn min(&self) -> Option<Distance> {
+        self.window
+            .iter()
+            .copied()
+            .reduce(Float::min)
+            .map(Distance::from_meters)
+    }
+
+    /// Longest distance in the window, `None` if empty.
+    pub fn max(&self) -> Option<Distance> {
+        self.window
+            .iter()
+            .copied()
+            .reduce(Float::max)
+            .map(Distance::from_meters)
+    }
+
+    /// Population standard deviation of the distances in the window, `None` if empty.
+    pub fn std_dev(&self) -> Option<Distance> {
+        let mean = self.mean_meters()?;
+        let variance = self
+            .window
+            .iter()
+            .map(|distance| (distance - mean) * (distance - mean))
+            .sum::<Float>()
+            / self.window.len() as Float;
+
+        Some(Distance::from_meters(variance.sqrt()))
+    }
+
+    /// Empty the window.
+    pub fn reset(&mut self) {
+        self.window.clear();
+    }
+
+    /// Mean distance in m over the window, `None` if empty.
+    fn mean_meters(&self) -> Option<Float> {
+        if self.window.is_empty() {
+            return None;
+        }
+        Some(self.window.iter().sum::<Float>() / self.window.len() as Float)
+    }
+}
+
+/// Histogram bucket.
+///
+/// # Fields
+///
+/// - `upper`: inclusive upper bound of the bucket (`None` for the last, unbounded one)
+/// - `count`: number of distances in the bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bucket {
+    pub upper: Option<Distance>,
+    pub count: u64,
+}
+
+/// Histogram of measured distances over configurable buckets.
+///
+/// Every bucket counts the distances up to its upper bound (inclusive) and greater than the
+/// previous bucket's one, while a last bucket counts distances beyond the greatest bound.
+/// Measurements with no object in range are counted separately.
+///
+/// # Fields
+///
+/// - `bounds`: ascending bucket upper bounds in m
+/// - `counts`: number of distances per bucket, including the last unbounded one
+/// - `out_of_range`: number of measurements with no object in range
+///
+/// ```rust
+/// use hc_sr04::{stats::Histogram, Distance};
+///
+/// // Buckets up to 0.5m, 1m and 1.5m, plus beyond.
+/// let half = Distance::from_meters(0.5);
+/// let mut histogram = Histogram::linear(half, half, 3);
+/// for meters in [0.2, 0.7, 0.8, 1.2, 3.] {
+///     histogram.push(Distance::from_meters(meters));
+/// }
+///
+/// let counts: Vec<_> = histogram.buckets().map(|bucket| bucket.count).collect();
+/// assert_eq!(counts, [1, 2, 1, 1]);
+///
+/// let snapshot = histogram.take();
+/// assert_eq!(snapshot.total(), 5);
+/// assert_eq!(histogram.total(), 0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RawHistogram"))]
+pub struct Histogram {
+    bounds: Vec<Float>,
+    counts: Vec<u64>,
+    out_of_range: u64,
+}
+
+/// Deserialized [`Histogram`], before checking its buckets are consistent.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawHistogram {
+    bounds: Vec<Float>,
+    counts: Vec<u64>,
+    out_of_range: u64,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawHistogram> for Histogram {
+    type Error = &'static str;
+
+    /// Check the bounds are ascending with no duplicates, and that there is a count per bucket.
+    fn try_from(raw: RawHistogram) -> Result<Self, Self::Error> {
+        if !raw.bounds.windows(2).all(|pair| pair[0] < pair[1]) {
+            return Err("histogram bounds must be strictly ascending");
+        }
+        if raw.counts.len() != raw.bounds.len() + 1 {
+            return Err("histogram must hold one count per bound, plus the unbounded bucket");
+        }
+
+        Ok(Self {
+            bounds: raw.bounds,
+            counts: raw.counts,
+            out_of_range: raw.out_of_range,
+        })
+    }
+}
+
+impl Histogram {
+    /// Create an empty histogram with buckets up to the given upper `bounds`, sorting them and
+    /// discarding duplicates.
+    pub fn new(bounds: impl IntoIterator<Item = Distance>) -> Self {
+        let mut bounds: Vec<Float> = bounds.into_iter().map(Distance::as_meters).collect();
+        bounds.sort_by(Float::total_cmp);
+        bounds.dedup();
+
+        Self {
+            counts: vec![0; bounds.len() + 1],
+            bounds,
+            out_of_range: 0,
+        }
+    }
+
+    /// Create an empty histogram with `count` buckets of the given `width`, the first one up to
+    /// `first`.
+    pub fn linear(first: Distance, width: Distance, count: usize) -> Self {
+        Self::new((0..count).map(|index| first + width * index as Float))
+    }
+
+    /// Feed `measurement`, counting it as out of range if no object is in range.
+    pub fn update(&mut self, measurement: &Measurement) {
+        match measurement.distance {
+            Some(distance) => self.push(distance),
+            None => self.out_of_range += 1,
+        }
+    }
+
+    /// Feed `distance`, counting it in its bucket.
+    pub fn push(&mut self, distance: Distance) {
+        let meters = distance.as_meters();
+        let index = self.bounds.partition_point(|bound| *bound < meters);
+        self.counts[index] += 1;
+    }
+
+    /// Buckets in ascending order, the last one being unbounded.
+    pub fn buckets(&self) -> impl Iterator<Item = Bucket> + '_ {
+        self.counts.iter().enumerate().map(|(index, count)| Bucket {
+            upper: self.bounds.get(index).copied().map(Distance::from_meters),
+            count: *count,
+        })
+    }
+
+    /// Number of distances counted, excluding out of range measurements.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Number of measurements with no object in range.
+    pub fn out_of_range(&self) -> u64 {
+        self.out_of_range
+    }
+
+    /// Snapshot the histogram, resetting it.
+    pub fn take(&mut self) -> Self {
+        let empty = Self {
+            bounds: self.bounds.clone(),
+            counts: vec![0; self.counts.len()],
+            out_of_range: 0,
+        };
+        mem::replace(self, empty)
+    }
+
+    /// Reset all counts, keeping the buckets.
+    pub fn reset(&mut self) {
+        self.counts.iter_mut().for_each(|count| *count = 0);
+        self.out_of_range = 0;
+    }
+}
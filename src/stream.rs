@@ -0,0 +1,120 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! [`Stream`] of measurements, available with the `stream` feature.
+//!
+//! ```rust,no_run
+//! use futures::StreamExt;
+//! use hc_sr04::HcSr04Async;
+//! use std::time::Duration;
+//!
+//! # async fn run() {
+//! let ultrasonic = HcSr04Async::new(24, 23, None).unwrap();
+//!
+//! let mut stream = Box::pin(
+//!     ultrasonic
+//!         .stream(Duration::from_millis(100))
+//!         .filter_map(|measurement| async move { measurement.ok()?.distance })
+//!         .chunks(10),
+//! );
+//!
+//! while let Some(distances) = stream.next().await {
+//!     println!("Last 10 distances: {:?}", distances);
+//! }
+//! # }
+//! ```
+
+use crate::{
+    backend::Backend,
+    filter::{DistanceFilter, Pipeline},
+    HcSr04Async, Measurement, Result,
+};
+use futures_core::Stream;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{
+    sync::mpsc,
+    time::{self, MissedTickBehavior},
+};
+
+/// [`Stream`] of measurements taken at a fixed interval, obtained through
+/// [`HcSr04Async::stream`].
+///
+/// Measurements are taken by a background task, which stops as soon as the stream is dropped.
+#[derive(Debug)]
+pub struct DistanceStream {
+    receiver: mpsc::Receiver<Result<Measurement>>,
+    filter: Pipeline,
+}
+
+impl DistanceStream {
+    /// Filter streamed distances through `filter`, on top of the filters set on the sensor.
+    /// Calling this more than once chains the filters, in the given order.
+    ///
+    /// Readings rejected by the filter are streamed with no distance, while the variance estimate
+    /// of streamed measurements accounts for the filter (see [`Measurement::variance`]).
+    pub fn filter(mut self, filter: impl DistanceFilter + Send + 'static) -> Self {
+        self.filter.push(filter);
+        self
+    }
+}
+
+impl Stream for DistanceStream {
+    type Item = Result<Measurement>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.receiver.poll_recv(cx).map(|measurement| {
+            measurement.map(|measurement| this.filter.filter_measurement(measurement))
+        })
+    }
+}
+
+impl<B: Backend + Send + 'static> HcSr04Async<B> {
+    /// Stream measurements taken every `interval`.
+    ///
+    /// Measuring pauses while the consumer lags behind. If a measurement takes longer than
+    /// `interval`, the next one starts right away.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn stream(&self, interval: Duration) -> DistanceStream {
+        let (sender, receiver) = mpsc::channel(1);
+        let sensor = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = time::interval(interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                if sender.send(sensor.measurement().await).await.is_err() {
+                    // Stream dropped: stop measuring.
+                    break;
+                }
+            }
+        });
+
+        DistanceStream {
+            receiver,
+            filter: Pipeline::new(),
+        }
+    }
+}
@@ -0,0 +1,191 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! Tank level measurement.
+//!
+//! [`TankLevel`] converts the distance measured by a sensor mounted above a tank, pointing down
+//! to the liquid surface, into fill height, volume and percent full, given the tank [`Shape`]
+//! and the mounting offset of the sensor above the full level.
+//!
+//! ```rust
+//! use hc_sr04::{
+//!     tank::{Shape, TankLevel},
+//!     Distance, Unit,
+//! };
+//!
+//! // 1m deep, 50cm × 40cm rectangular tank, sensor mounted 10cm above the full level.
+//! let tank = TankLevel::new(
+//!     Distance::new(1., Unit::Meters),
+//!     Shape::Rectangular {
+//!         length: Distance::new(50., Unit::Centimeters),
+//!         width: Distance::new(40., Unit::Centimeters),
+//!     },
+//! )
+//! .offset(Distance::new(10., Unit::Centimeters));
+//! assert!((tank.capacity() - 200.).abs() < 1e-3);
+//!
+//! let level = tank.level(Distance::new(35., Unit::Centimeters));
+//! assert!((level.height.as_cm() - 75.).abs() < 1e-3);
+//! assert!((level.volume - 150.).abs() < 1e-3);
+//! assert!((level.percent - 75.).abs() < 1e-3);
+//!
+//! // Irregular tank, described by its strapping table.
+//! let tank = TankLevel::new(
+//!     Distance::new(1., Unit::Meters),
+//!     Shape::Custom(vec![
+//!         (Distance::from_meters(0.), 0.),
+//!         (Distance::from_meters(0.5), 100.),
+//!         (Distance::from_meters(1.), 300.),
+//!     ]),
+//! );
+//! let level = tank.level(Distance::new(25., Unit::Centimeters));
+//! assert!((level.volume - 200.).abs() < 1e-3);
+//! ```
+
+use crate::{backend::Backend, Distance, Float, HcSr04, Result};
+#[cfg(not(feature = "f64"))]
+use std::f32::consts::PI;
+#[cfg(feature = "f64")]
+use std::f64::consts::PI;
+
+/// Liters per cubic meter.
+const LITERS_PER_M3: Float = 1000.;
+
+/// Tank geometry.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Shape {
+    /// Vertical cylinder.
+    Cylinder { diameter: Distance },
+    /// Rectangular cuboid.
+    Rectangular { length: Distance, width: Distance },
+    /// Custom profile, given as `(height, volume)` points with the volume in liters up to each
+    /// fill height (e.g. from the tank's strapping table), linearly interpolated in between.
+    Custom(Vec<(Distance, Float)>),
+}
+
+/// Fill level of the tank.
+///
+/// # Fields
+///
+/// - `height`: height of the liquid surface above the tank bottom
+/// - `volume`: volume of liquid in liters
+/// - `percent`: percent of the tank capacity filled
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Level {
+    pub height: Distance,
+    pub volume: Float,
+    pub percent: Float,
+}
+
+/// Tank level converter.
+///
+/// # Fields
+///
+/// - `height`: tank height, from the bottom to the full level
+/// - `offset`: sensor mounting offset above the full level
+/// - `shape`: tank geometry
+#[derive(Debug, Clone, PartialEq)]
+pub struct TankLevel {
+    height: Distance,
+    offset: Distance,
+    shape: Shape,
+}
+
+impl TankLevel {
+    /// Create a new converter for a tank of the given `height` (from the bottom to the full
+    /// level) and `shape`, with the sensor mounted at the full level.
+    pub fn new(height: Distance, mut shape: Shape) -> Self {
+        if let Shape::Custom(points) = &mut shape {
+            points.sort_by(|(a, _), (b, _)| a.as_meters().total_cmp(&b.as_meters()));
+        }
+        Self {
+            height,
+            offset: Distance::default(),
+            shape,
+        }
+    }
+
+    /// Sensor mounting offset above the full level (none by default).
+    pub fn offset(mut self, offset: Distance) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Height of the liquid surface above the tank bottom given the measured `distance` to it,
+    /// clamped to the tank height.
+    pub fn fill_height(&self, distance: Distance) -> Distance {
+        let height = self.height.as_meters() - (distance - self.offset).as_meters();
+        Distance::from_meters(height.clamp(0., self.height.as_meters()))
+    }
+
+    /// Volume in liters of liquid filling the tank up to `height`.
+    fn volume_at(&self, height: Distance) -> Float {
+        let meters = height.as_meters();
+        match &self.shape {
+            Shape::Cylinder { diameter } => {
+                let radius = diameter.as_meters() / 2.;
+                PI * radius * radius * meters * LITERS_PER_M3
+            }
+            Shape::Rectangular { length, width } => {
+                length.as_meters() * width.as_meters() * meters * LITERS_PER_M3
+            }
+            Shape::Custom(points) => {
+                let next = points.partition_point(|(point, _)| point.as_meters() < meters);
+                match (
+                    next.checked_sub(1).map(|prev| points[prev]),
+                    points.get(next),
+                ) {
+                    (Some((low, low_volume)), Some(&(high, high_volume))) => {
+                        let span = (high - low).as_meters();
+                        let ratio = (meters - low.as_meters()) / span;
+                        low_volume + (high_volume - low_volume) * ratio
+                    }
+                    (Some((_, volume)), None) | (None, Some(&(_, volume))) => volume,
+                    (None, None) => 0.,
+                }
+            }
+        }
+    }
+
+    /// Tank capacity in liters.
+    pub fn capacity(&self) -> Float {
+        self.volume_at(self.height)
+    }
+
+    /// Fill level of the tank given the measured `distance` to the liquid surface.
+    pub fn level(&self, distance: Distance) -> Level {
+        let height = self.fill_height(distance);
+        let volume = self.volume_at(height);
+        let capacity = self.capacity();
+        Level {
+            height,
+            volume,
+            percent: if capacity > 0. {
+                volume / capacity * 100.
+            } else {
+                0.
+            },
+        }
+    }
+
+    /// Measure distance through `sensor` and convert it to the fill level of the tank.
+    ///
+    /// Returns `Ok(None)` if the liquid surface is out of range.
+    pub fn measure<B: Backend>(&self, sensor: &mut HcSr04<B>) -> Result<Option<Level>> {
+        Ok(sensor.measure()?.map(|distance| self.level(distance)))
+    }
+}
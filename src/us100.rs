@@ -0,0 +1,149 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+//! US-100 ultrasonic sensor in UART mode.
+//!
+//! With its mode jumper in place, the US-100 measures on its own and replies with the distance
+//! (and its on-board temperature) over a *9600 baud* serial line, leaving no pulse timing to the
+//! host: measurements are unaffected by GPIO timing jitter.
+//!
+//! ```rust,no_run
+//! use hc_sr04::{us100::Us100, Unit};
+//!
+//! let mut ultrasonic = Us100::new().unwrap();
+//!
+//! println!("Temperature: {:.0}C", ultrasonic.temperature().unwrap());
+//! match ultrasonic.measure_distance(Unit::Centimeters).unwrap() {
+//!     Some(dist) => println!("Distance: {:.1}cm", dist),
+//!     None => println!("Object out of range"),
+//! }
+//! ```
+
+use crate::{error::Error, Distance, Float, Result, Unit};
+use rppal::uart::{Parity, Queue, Uart};
+use std::{path::Path, time::Duration};
+
+/// Serial line baud rate of the US-100.
+const BAUD_RATE: u32 = 9600;
+/// Command requesting a distance measurement, replied with 2 bytes (big endian mm).
+const MEASURE_DISTANCE: u8 = 0x55;
+/// Command requesting a temperature measurement, replied with 1 byte (C offset by 45).
+const MEASURE_TEMPERATURE: u8 = 0x50;
+/// Offset of the temperature reply in C.
+const TEMPERATURE_OFFSET: Float = 45.;
+/// Maximum time to wait for a reply.
+const REPLY_TIMEOUT: Duration = Duration::from_millis(100);
+/// Minimum measuring range for US-100 sensor in m.
+pub(crate) const MIN_RANGE: Float = 0.02;
+/// Default maximum measuring range for US-100 sensor in m.
+const DEFAULT_MAX_RANGE: Float = 4.5;
+
+/// US-100 sensor in UART mode.
+///
+/// # Fields
+///
+/// - `uart`: serial line the sensor is wired to
+/// - `max_range`: maximum measuring range in m, beyond which objects are out of range
+#[derive(Debug)]
+pub struct Us100 {
+    uart: Uart,
+    max_range: Float,
+}
+
+impl Us100 {
+    /// Initialize US-100 sensor wired to the *Raspberry Pi* primary UART (GPIO pins 14 and 15).
+    pub fn new() -> Result<Self> {
+        Self::with_path("/dev/serial0")
+    }
+
+    /// Initialize US-100 sensor wired to the serial device at `path` (e.g. a USB to serial
+    /// adapter).
+    pub fn with_path(path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_uart(Uart::with_path(path, BAUD_RATE, Parity::None, 8, 1)?)
+    }
+
+    /// Initialize US-100 sensor wired to the already configured `uart` (*9600 baud*, no parity,
+    /// 8 data bits and 1 stop bit).
+    pub fn with_uart(mut uart: Uart) -> Result<Self> {
+        uart.set_read_mode(0, REPLY_TIMEOUT)?;
+        Ok(Self {
+            uart,
+            max_range: DEFAULT_MAX_RANGE,
+        })
+    }
+
+    /// Maximum measuring range in m (defaults to *4.5m*, as per US-100 specification).
+    pub fn max_range(&self) -> Float {
+        self.max_range
+    }
+
+    /// Set the maximum measuring range in m.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is not positive and finite.
+    pub fn set_max_range(&mut self, range: Float) {
+        crate::builder::assert_max_range(range);
+        self.max_range = range;
+    }
+
+    /// Send `command` and wait for the `reply` to fill up, failing with [`Error::NoReply`] if it
+    /// doesn't within the timeout.
+    fn query(&mut self, command: u8, reply: &mut [u8]) -> Result<()> {
+        // Discard late replies to previous commands.
+        self.uart.flush(Queue::Input)?;
+        self.uart.write(&[command])?;
+
+        let mut len = 0;
+        while len < reply.len() {
+            match self.uart.read(&mut reply[len..])? {
+                0 => return Err(Error::NoReply),
+                read => len += read,
+            }
+        }
+        Ok(())
+    }
+
+    /// Perform distance measurement.
+    ///
+    /// Returns `Ok(None)` if the object is out of range.
+    pub fn measure(&mut self) -> Result<Option<Distance>> {
+        let mut reply = [0; 2];
+        self.query(MEASURE_DISTANCE, &mut reply)?;
+
+        let distance = Distance::new(u16::from_be_bytes(reply) as Float, Unit::Millimeters);
+        if distance.as_meters() > self.max_range {
+            return Ok(None);
+        }
+        Ok(Some(distance))
+    }
+
+    /// Perform distance measurement, in the given `unit`.
+    ///
+    /// Returns `Ok(None)` if the object is out of range.
+    pub fn measure_distance(&mut self, unit: Unit) -> Result<Option<Float>> {
+        Ok(self.measure()?.map(|distance| distance.as_unit(unit)))
+    }
+
+    /// Read the on-board temperature sensor, in *Celsius degrees* (e.g. for calibrating other
+    /// sensors through [`HcSr04::calibrate`]).
+    ///
+    /// [`HcSr04::calibrate`]: crate::HcSr04::calibrate
+    pub fn temperature(&mut self) -> Result<Float> {
+        let mut reply = [0];
+        self.query(MEASURE_TEMPERATURE, &mut reply)?;
+        Ok(reply[0] as Float - TEMPERATURE_OFFSET)
+    }
+}
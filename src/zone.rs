@@ -0,0 +1,333 @@
+// hc-sr04: Raspberry Pi Rust driver for the HC-SR04 ultrasonic distance sensor.
+// Copyright (C) 2022 Marco Radocchia
+//
+// This program is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later
+// version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see https://www.gnu.org/licenses/.
+
+//! Zone detection.
+//!
+//! [`Zones`] split the measuring range into named, contiguous distance bands (e.g. *near*, *mid*
+//! and *far* for a parking sensor), each one extending up to its limit distance. A
+//! [`ZoneDetector`] tracks the zone the object occupies and reports zone changes, optionally with
+//! a hysteresis band around zone boundaries so that objects lingering around them don't make the
+//! zone flicker.
+//!
+//! ```rust
+//! use hc_sr04::{
+//!     zone::{ZoneChange, ZoneDetector, Zones},
+//!     Distance,
+//! };
+//!
+//! let zones = Zones::new()
+//!     .zone("near", Distance::from_meters(0.3))
+//!     .zone("mid", Distance::from_meters(1.))
+//!     .zone("far", Distance::from_meters(2.))
+//!     .hysteresis(Distance::from_meters(0.05));
+//! assert_eq!(zones.locate(Some(Distance::from_meters(0.5))), Some("mid"));
+//!
+//! let mut detector = ZoneDetector::new(zones);
+//! let reading = |meters| Some(Distance::from_meters(meters));
+//! assert_eq!(
+//!     detector.update(reading(1.5)),
+//!     Some(ZoneChange {
+//!         exited: None,
+//!         entered: Some("far"),
+//!     })
+//! );
+//! // Within the hysteresis band: still far.
+//! assert_eq!(detector.update(reading(0.97)), None);
+//! assert_eq!(
+//!     detector.update(reading(0.9)),
+//!     Some(ZoneChange {
+//!         exited: Some("far"),
+//!         entered: Some("mid"),
+//!     })
+//! );
+//! ```
+//!
+//! Reactive applications can register callbacks fired as the object enters or exits zones through
+//! [`ZoneEvents`], running on the continuous sampling thread (see [`HcSr04::watch_zones`]):
+//!
+//! ```rust,no_run
+//! use hc_sr04::{
+//!     zone::{ZoneEvents, Zones},
+//!     Distance, HcSr04,
+//! };
+//! use std::time::Duration;
+//!
+//! let ultrasonic = HcSr04::new(24, 23, None).unwrap();
+//!
+//! let zones = Zones::new()
+//!     .zone("near", Distance::from_meters(0.3))
+//!     .zone("far", Distance::from_meters(2.));
+//! let events = ZoneEvents::new(zones)
+//!     .on_enter("near", |measurement| println!("Stop! {:?}", measurement.distance))
+//!     .on_exit("near", |_| println!("Clear"));
+//!
+//! ultrasonic
+//!     .watch_zones(Duration::from_millis(100), events)
+//!     .join();
+//! ```
+
+use crate::Result;
+use crate::{backend::Backend, continuous::ContinuousWorker, Distance, HcSr04, Measurement};
+use std::{fmt, time::Duration};
+
+/// Named, contiguous distance bands, ordered by limit distance.
+///
+/// The first zone starts at the sensor, every other one at the limit of the previous zone.
+/// Objects beyond the limit of the last zone (or out of range) occupy no zone.
+///
+/// # Fields
+///
+/// - `zones`: zone names and limit distances, in ascending limit order
+/// - `hysteresis`: distance an object must move beyond the boundaries of a zone to leave it
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Zones {
+    zones: Vec<(String, Distance)>,
+    hysteresis: Distance,
+}
+
+impl Zones {
+    /// Create an empty zones configuration, with no hysteresis.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the zone `name` extending up to `limit`.
+    pub fn zone(mut self, name: impl Into<String>, limit: Distance) -> Self {
+        let index = self.zones.partition_point(|(_, other)| *other <= limit);
+        self.zones.insert(index, (name.into(), limit));
+        self
+    }
+
+    /// Distance an object must move beyond the boundaries of the zone it occupies in order to
+    /// leave it (no hysteresis by default).
+    pub fn hysteresis(mut self, hysteresis: Distance) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    /// Number of zones.
+    pub fn len(&self) -> usize {
+        self.zones.len()
+    }
+
+    /// Returns `true` if no zones are configured.
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+
+    /// Name of the zone occupied by an object at `distance` (`None` if no object is in range),
+    /// regardless of hysteresis.
+    pub fn locate(&self, distance: Option<Distance>) -> Option<&str> {
+        self.index(distance).map(|index| self.name(index))
+    }
+
+    /// Index of the zone occupied by an object at `distance`.
+    fn index(&self, distance: Option<Distance>) -> Option<usize> {
+        let distance = distance?;
+        self.zones.iter().position(|(_, limit)| distance < *limit)
+    }
+
+    /// Name of the zone at `index`.
+    fn name(&self, index: usize) -> &str {
+        &self.zones[index].0
+    }
+
+    /// Returns `true` if an object at `distance` is still within the zone at `index`, widened by
+    /// the hysteresis band.
+    fn holds(&self, index: usize, distance: Option<Distance>) -> bool {
+        let distance = match distance {
+            Some(distance) => distance,
+            None => return false,
+        };
+        let lower = match index {
+            0 => Distance::ZERO,
+            _ => self.zones[index - 1].1,
+        };
+
+        distance >= lower - self.hysteresis && distance < self.zones[index].1 + self.hysteresis
+    }
+}
+
+/// Change of the zone occupied by the object.
+///
+/// # Fields
+///
+/// - `exited`: zone the object left (`None` if it occupied no zone)
+/// - `entered`: zone the object entered (`None` if it occupies no zone)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZoneChange<'a> {
+    pub exited: Option<&'a str>,
+    pub entered: Option<&'a str>,
+}
+
+/// Tracker of the zone occupied by the object, reporting zone changes.
+///
+/// # Fields
+///
+/// - `zones`: zones configuration
+/// - `current`: index of the zone currently occupied
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneDetector {
+    zones: Zones,
+    current: Option<usize>,
+}
+
+impl ZoneDetector {
+    /// Create a new detector over `zones`, initially occupying no zone.
+    pub fn new(zones: Zones) -> Self {
+        Self {
+            zones,
+            current: None,
+        }
+    }
+
+    /// Zones configuration.
+    pub fn zones(&self) -> &Zones {
+        &self.zones
+    }
+
+    /// Name of the zone currently occupied, `None` if no zone is occupied.
+    pub fn current(&self) -> Option<&str> {
+        self.current.map(|index| self.zones.name(index))
+    }
+
+    /// Update the detector with the measured `distance` (`None` if no object is in range),
+    /// returning the zone change, if any.
+    pub fn update(&mut self, distance: Option<Distance>) -> Option<ZoneChange<'_>> {
+        let previous = self.current;
+        if matches!(previous, Some(index) if self.zones.holds(index, distance)) {
+            return None;
+        }
+
+        self.current = self.zones.index(distance);
+        if self.current == previous {
+            return None;
+        }
+
+        Some(ZoneChange {
+            exited: previous.map(|index| self.zones.name(index)),
+            entered: self.current.map(|index| self.zones.name(index)),
+        })
+    }
+
+    /// Measure distance through `sensor` and update the detector (see
+    /// [`ZoneDetector::update`]).
+    pub fn poll<B: Backend>(&mut self, sensor: &mut HcSr04<B>) -> Result<Option<ZoneChange<'_>>> {
+        let distance = sensor.measure()?;
+        Ok(self.update(distance))
+    }
+
+    /// Discard the detector state, back to occupying no zone.
+    pub fn reset(&mut self) {
+        self.current = None;
+    }
+}
+
+/// Callback fired on zone changes.
+type Callback = Box<dyn FnMut(&Measurement) + Send>;
+
+/// Callbacks fired as the object enters or exits zones.
+///
+/// # Fields
+///
+/// - `detector`: zone tracker
+/// - `enter`: callbacks fired when entering the named zones
+/// - `exit`: callbacks fired when exiting the named zones
+pub struct ZoneEvents {
+    detector: ZoneDetector,
+    enter: Vec<(String, Callback)>,
+    exit: Vec<(String, Callback)>,
+}
+
+impl ZoneEvents {
+    /// Create a new set of callbacks over `zones`, with no callback registered.
+    pub fn new(zones: Zones) -> Self {
+        Self {
+            detector: ZoneDetector::new(zones),
+            enter: Vec::new(),
+            exit: Vec::new(),
+        }
+    }
+
+    /// Register `callback`, fired with the triggering measurement whenever the object enters
+    /// `zone`.
+    pub fn on_enter(
+        mut self,
+        zone: impl Into<String>,
+        callback: impl FnMut(&Measurement) + Send + 'static,
+    ) -> Self {
+        self.enter.push((zone.into(), Box::new(callback)));
+        self
+    }
+
+    /// Register `callback`, fired with the triggering measurement whenever the object exits
+    /// `zone`.
+    pub fn on_exit(
+        mut self,
+        zone: impl Into<String>,
+        callback: impl FnMut(&Measurement) + Send + 'static,
+    ) -> Self {
+        self.exit.push((zone.into(), Box::new(callback)));
+        self
+    }
+
+    /// Update the zone tracker with `measurement`, firing the callbacks of the exited zone first
+    /// and of the entered zone then.
+    pub fn handle(&mut self, measurement: &Measurement) {
+        let change = match self.detector.update(measurement.distance) {
+            Some(change) => change,
+            None => return,
+        };
+
+        for (zone, callback) in &mut self.exit {
+            if change.exited == Some(zone.as_str()) {
+                callback(measurement);
+            }
+        }
+        for (zone, callback) in &mut self.enter {
+            if change.entered == Some(zone.as_str()) {
+                callback(measurement);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ZoneEvents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ZoneEvents")
+            .field("detector", &self.detector)
+            .field("enter", &self.enter.len())
+            .field("exit", &self.exit.len())
+            .finish()
+    }
+}
+
+impl<B: Backend + Send + 'static> HcSr04<B> {
+    /// Start measuring distance every `interval` on a worker thread (see
+    /// [`HcSr04::spawn_continuous`]), firing `events` callbacks on zone changes.
+    ///
+    /// Failed measurements are skipped. The worker runs until stopped through the returned
+    /// [`ContinuousWorker`].
+    pub fn watch_zones(self, interval: Duration, mut events: ZoneEvents) -> ContinuousWorker {
+        self.spawn_continuous(interval, move |measurement| {
+            if let Ok(measurement) = measurement {
+                events.handle(measurement);
+            }
+            true
+        })
+    }
+}